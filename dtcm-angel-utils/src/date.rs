@@ -1,8 +1,26 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Deserializer, Serializer};
 
 use crate::UtilsResult;
 
+// Indian Standard Time offset from UTC in seconds (+05:30)
+const IST_OFFSET_SECS: i32 = 5 * 3600 + 30 * 60;
+
+/// Returns the Indian Standard Time offset, exchange times are reported in IST
+pub fn ist() -> FixedOffset {
+    FixedOffset::east_opt(IST_OFFSET_SECS).expect("valid IST offset")
+}
+
+/// Converts milliseconds since the unix epoch to the IST date time
+pub fn from_epoch_millis_ist(millis: i64) -> Option<DateTime<FixedOffset>> {
+    DateTime::from_timestamp_millis(millis).map(|dt| dt.with_timezone(&ist()))
+}
+
+/// Converts seconds since the unix epoch to the IST date time
+pub fn from_epoch_secs_ist(secs: i64) -> Option<DateTime<FixedOffset>> {
+    DateTime::from_timestamp(secs, 0).map(|dt| dt.with_timezone(&ist()))
+}
+
 /// Converts yyyy-mm-dd hh:mm e.g. 2001-07-08 00:34 to the NaiveDateTime
 pub fn from_yyyy_mm_dd_hh_mm<D>(date_str: D) -> UtilsResult<NaiveDateTime>
 where
@@ -11,6 +29,15 @@ where
     NaiveDateTime::parse_from_str(date_str.as_ref(), "%F %R").map_err(|e| e.into())
 }
 
+/// Converts dd-MMM-yyyy hh:mm:ss e.g. 26-Dec-2024 15:55:01 to the NaiveDateTime
+pub fn from_dd_mmm_yyyy_hh_mm_ss<D>(date_str: D) -> UtilsResult<NaiveDateTime>
+where
+    D: AsRef<str>,
+{
+    NaiveDateTime::parse_from_str(date_str.as_ref().trim(), "%d-%b-%Y %H:%M:%S")
+        .map_err(|e| e.into())
+}
+
 /// Serializes the NaiveDateTime to yyyy-mm-dd hh:mm e.g. 2001-07-08 00:34
 pub fn serde_yyyy_mm_dd_hh_mm<S>(
     date: &NaiveDateTime,
@@ -31,3 +58,64 @@ where
 {
     String::deserialize(deserializer).map(|s| chrono::NaiveDate::parse_from_str(&s, "%d%b%Y").ok())
 }
+
+/// Serializes the optional NaiveDateTime to dd-MMM-yyyy hh:mm:ss e.g. 26-Dec-2024 15:55:01,
+/// None as an empty string
+pub fn serialize_dd_mmm_yyyy_hh_mm_ss<S>(
+    date: &Option<NaiveDateTime>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serializer.serialize_str(&date.format("%d-%b-%Y %H:%M:%S").to_string()),
+        None => serializer.serialize_str(""),
+    }
+}
+
+/// Deserializes date times in the format of dd-MMM-yyyy hh:mm:ss e.g. 26-Dec-2024 15:55:01 to
+/// optional NaiveDateTime, empty values and values in another format are None
+pub fn serde_dd_mmm_yyyy_hh_mm_ss<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    match s.trim() {
+        "" => Ok(None),
+        s => match from_dd_mmm_yyyy_hh_mm_ss(s) {
+            Ok(date_time) => Ok(Some(date_time)),
+            Err(e) => {
+                warn!("Unexpected date time {s} ignored: {e}");
+                Ok(None)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::serde_dd_mmm_yyyy_hh_mm_ss;
+
+    #[derive(Debug, Deserialize)]
+    struct Sample {
+        #[serde(default, deserialize_with = "serde_dd_mmm_yyyy_hh_mm_ss")]
+        update_time: Option<NaiveDateTime>,
+    }
+
+    #[test]
+    fn date_times_in_another_format_are_none() {
+        let time = |json: &str| serde_json::from_str::<Sample>(json).unwrap().update_time;
+
+        assert_eq!(
+            time(r#"{"update_time": "26-Dec-2024 15:55:01"}"#),
+            NaiveDateTime::parse_from_str("2024-12-26 15:55:01", "%Y-%m-%d %H:%M:%S").ok()
+        );
+        assert_eq!(time(r#"{"update_time": ""}"#), None);
+        assert_eq!(time(r#"{"update_time": "2024-12-26T15:55:01"}"#), None);
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::RwLock;
+
 use reqwest::{Client, ClientBuilder, IntoUrl, Method, StatusCode, redirect::Policy};
 use serde::{Serialize, de::DeserializeOwned};
 
@@ -9,8 +11,8 @@ use crate::{UtilsError, UtilsResult};
 pub struct HttpClient {
     /// Inner client
     client: Client,
-    /// JWT token for bearer
-    jwt_token: Option<String>,
+    /// JWT token for bearer, replaced when the session is refreshed
+    jwt_token: RwLock<Option<String>>,
 }
 
 impl HttpClient {
@@ -32,16 +34,16 @@ impl HttpClient {
 
         Ok(Self {
             client,
-            jwt_token: None,
+            jwt_token: RwLock::new(None),
         })
     }
 
     /// Sets the jwt token for authorization header
-    pub fn jwt_token<J>(&mut self, jwt_token: J)
+    pub fn jwt_token<J>(&self, jwt_token: J)
     where
         J: Into<String>,
     {
-        self.jwt_token = Some(jwt_token.into());
+        *self.jwt_token.write().unwrap_or_else(|e| e.into_inner()) = Some(jwt_token.into());
     }
 
     /// Makes the http request
@@ -63,7 +65,12 @@ impl HttpClient {
             _ => unimplemented!(),
         };
 
-        let request = match self.jwt_token.as_ref() {
+        let jwt_token = self
+            .jwt_token
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let request = match jwt_token {
             Some(token) => request.bearer_auth(token),
             None => request,
         };
@@ -24,6 +24,8 @@ use totp_rs::{SecretParseError, TotpUrlError};
 pub mod date;
 /// Contains http related functionality
 pub mod http;
+/// Contains tolerant number deserializers
+pub mod num;
 /// Contains system related functionality
 pub mod sys;
 /// Contains websocket related functionality
@@ -0,0 +1,89 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de::Error, Deserialize, Deserializer};
+
+/// Number sent either as a JSON number or as a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawNumber<T> {
+    Number(T),
+    Text(String),
+    Null(()),
+}
+
+/// Parses the raw value, empty strings and nulls are returned as None
+fn parse<T, E>(raw: RawNumber<T>) -> Result<Option<T>, E>
+where
+    T: FromStr,
+    T::Err: Display,
+    E: Error,
+{
+    match raw {
+        RawNumber::Number(n) => Ok(Some(n)),
+        RawNumber::Null(()) => Ok(None),
+        RawNumber::Text(s) => match s.trim() {
+            "" => Ok(None),
+            s => s
+                .parse()
+                .map(Some)
+                .map_err(|e| E::custom(format!("invalid number {s:?}: {e}"))),
+        },
+    }
+}
+
+/// Deserializes a number sent as a number or a string, empty values default to zero
+pub fn serde_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Default + Deserialize<'de>,
+    T::Err: Display,
+{
+    RawNumber::deserialize(deserializer)
+        .and_then(parse)
+        .map(Option::unwrap_or_default)
+}
+
+/// Deserializes a number sent as a number or a string, empty values are None
+pub fn serde_opt_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    RawNumber::deserialize(deserializer).and_then(parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serde_number, serde_opt_number};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Sample {
+        #[serde(deserialize_with = "serde_number")]
+        quantity: u64,
+        #[serde(deserialize_with = "serde_number")]
+        price: f64,
+        #[serde(default, deserialize_with = "serde_opt_number")]
+        span: Option<f64>,
+    }
+
+    #[test]
+    fn numbers_from_strings() {
+        let sample: Sample =
+            serde_json::from_str(r#"{"quantity": "25", "price": 101.5, "span": ""}"#).unwrap();
+        assert_eq!(
+            (sample.quantity, sample.price, sample.span),
+            (25, 101.5, None)
+        );
+
+        let sample: Sample =
+            serde_json::from_str(r#"{"quantity": "", "price": "-1.000000", "span": null}"#)
+                .unwrap();
+        assert_eq!(
+            (sample.quantity, sample.price, sample.span),
+            (0, -1.0, None)
+        );
+
+        assert!(serde_json::from_str::<Sample>(r#"{"quantity": "x", "price": 0}"#).is_err());
+    }
+}
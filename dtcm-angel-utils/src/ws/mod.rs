@@ -1,4 +1,6 @@
 mod ws_stream;
 pub use ws_stream::WsStream;
 
-pub use tokio_tungstenite::tungstenite::{client::IntoClientRequest, handshake::client::Request};
+pub use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest, handshake::client::Request, Error as WsError,
+};
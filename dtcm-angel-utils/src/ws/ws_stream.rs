@@ -62,6 +62,18 @@ where
         Ok(())
     }
 
+    /// Sends the text as is, e.g. a `ping` heartbeat
+    pub async fn send_text<S>(&mut self, text: S) -> UtilsResult<()>
+    where
+        S: Into<String>,
+    {
+        let text = text.into();
+        trace!("Sending text {}", text);
+        self.inner.send(WsMessage::Text(text)).await?;
+
+        Ok(())
+    }
+
     /// Parses the [`WsMessage`] received from the [`WebSocket`]
     fn parse(msg: UtilsResult<WsMessage>) -> Option<UtilsResult<M>> {
         match msg {
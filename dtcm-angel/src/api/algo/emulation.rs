@@ -0,0 +1,672 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    order::{
+        unique_order_tag, CancelOrderReq, CancelOrderRes, NoInstrument, Order, OrderBook,
+        OrderKind, OrderState, PlaceOrderReq, PlaceOrderRes,
+    },
+    types::{ExchangeType, OrderVariety, ProductType, TransactionType},
+    ws::{AngelOneWs, Message, OrderStatus, SubscriptionExchange, SubscriptionRequest},
+    Error, Result, SmartConnect,
+};
+
+/// Open position protected by an [`EmulatedOrder`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmulatedPosition {
+    /// Exchange of the instrument
+    pub exchange: ExchangeType,
+    /// Trading symbol of the instrument
+    pub trading_symbol: String,
+    /// Symbol token of the instrument
+    pub symbol_token: String,
+    /// Product type of the position, used for the exit order
+    pub product_type: ProductType,
+    /// Side of the position, buy for a long position
+    pub side: TransactionType,
+    /// Quantity of the position
+    pub quantity: u64,
+}
+
+impl EmulatedPosition {
+    /// Returns true for a long position
+    fn is_long(&self) -> bool {
+        self.side == TransactionType::Buy
+    }
+
+    /// Returns the exit order for the quantity, a limit order when the price is set
+    fn exit_order(&self, quantity: u64, price: Option<f64>) -> PlaceOrderReq {
+        match price {
+            Some(price) => self.exit(Order::limit(price), quantity),
+            None => self.exit(Order::market(), quantity),
+        }
+    }
+
+    fn exit<K: OrderKind>(&self, order: Order<K, NoInstrument>, quantity: u64) -> PlaceOrderReq {
+        let order = order.product_type(self.product_type.clone()).instrument(
+            self.exchange.clone(),
+            &self.trading_symbol,
+            &self.symbol_token,
+        );
+        match self.is_long() {
+            true => order.sell(quantity).into(),
+            false => order.buy(quantity).into(),
+        }
+    }
+}
+
+/// Target order resting at the broker as the target leg of an OCO
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestingOrder {
+    /// Variety of the order, needed to cancel it
+    pub variety: OrderVariety,
+    /// Order ID
+    pub order_id: String,
+    /// Quantity filled so far
+    #[serde(default)]
+    pub filled_quantity: u64,
+}
+
+/// Exit condition emulated on the client
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EmulatedTrigger {
+    /// Stop following the price at a fixed distance, only ever moving in favour of the position
+    TrailingStop {
+        /// Distance from the best price seen, in rupees
+        trail: f64,
+        /// Current stop price, set from the first tick when empty
+        stop_price: Option<f64>,
+    },
+    /// Target and stop, the first one hit exits the position and cancels the other
+    Oco {
+        /// Target price
+        target: f64,
+        /// Stop price
+        stop: f64,
+        /// Target order resting at the broker, the target is emulated when empty
+        target_order: Option<RestingOrder>,
+    },
+}
+
+/// Leg of an [`EmulatedOrder`] that was hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// Stop or trailing stop hit
+    Stop,
+    /// Target hit
+    Target,
+}
+
+/// Exit condition emulated on the client for an open position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmulatedOrder {
+    /// Id of the emulated order
+    pub id: String,
+    /// Protected position
+    pub position: EmulatedPosition,
+    /// Exit condition
+    pub trigger: EmulatedTrigger,
+}
+
+impl EmulatedOrder {
+    /// Returns true if the tick is for the instrument of the position
+    fn matches(&self, message: &Message) -> bool {
+        message.token == self.position.symbol_token
+            && SubscriptionExchange::try_from(&self.position.exchange)
+                .is_ok_and(|exchange| exchange == message.exchange)
+    }
+
+    /// Applies the last traded price in rupees, moving the trailing stop and returning the
+    /// leg that was hit
+    pub fn check(&mut self, price: f64) -> Option<TriggerKind> {
+        let long = self.position.is_long();
+
+        match &mut self.trigger {
+            EmulatedTrigger::TrailingStop { trail, stop_price } => {
+                let trailed = match long {
+                    true => price - *trail,
+                    false => price + *trail,
+                };
+                let stop = stop_price.get_or_insert(trailed);
+                let hit = match long {
+                    true => price <= *stop,
+                    false => price >= *stop,
+                };
+                if hit {
+                    return Some(TriggerKind::Stop);
+                }
+                *stop = match long {
+                    true => stop.max(trailed),
+                    false => stop.min(trailed),
+                };
+                None
+            }
+            EmulatedTrigger::Oco { target, stop, .. } => match long {
+                true if price <= *stop => Some(TriggerKind::Stop),
+                true if price >= *target => Some(TriggerKind::Target),
+                false if price >= *stop => Some(TriggerKind::Stop),
+                false if price <= *target => Some(TriggerKind::Target),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Orders sent when an [`EmulatedOrder`] was hit
+#[derive(Debug)]
+pub struct EmulationFill {
+    /// Emulated order which was hit
+    pub order: EmulatedOrder,
+    /// Leg which was hit
+    pub kind: TriggerKind,
+    /// Last traded price that hit the leg, in rupees
+    pub price: f64,
+    /// Cancellation of the resting target order
+    pub cancellation: Option<Result<CancelOrderRes>>,
+    /// Placement of the exit order, none when the resting target order exits the position
+    pub exit: Option<Result<PlaceOrderRes>>,
+}
+
+/// Emulated orders with the ids of those whose exit is being placed
+#[derive(Debug, Default)]
+struct EmulatorState {
+    orders: HashMap<String, EmulatedOrder>,
+    triggering: HashSet<String>,
+}
+
+/// Emulates trailing stops and OCO exits on the client from the websocket ticks, placing and
+/// cancelling the orders through [`SmartConnect`] when a leg is hit
+#[derive(Debug, Clone)]
+pub struct OrderEmulator {
+    smart_connect: Arc<SmartConnect>,
+    state: Arc<Mutex<EmulatorState>>,
+    // serializes the writes of the persistence file
+    saving: Arc<Mutex<()>>,
+    path: Option<PathBuf>,
+    price_divisor: f64,
+}
+
+impl OrderEmulator {
+    /// Returns a new instance for [`OrderEmulator`]
+    pub fn new(smart_connect: Arc<SmartConnect>) -> Self {
+        Self {
+            smart_connect,
+            state: Default::default(),
+            saving: Default::default(),
+            path: None,
+            price_divisor: 100.0,
+        }
+    }
+
+    /// Sets the divisor converting the feed prices to rupees, 100 (paise) by default
+    pub fn price_divisor(mut self, price_divisor: f64) -> Self {
+        self.price_divisor = price_divisor;
+        self
+    }
+
+    /// Persists the emulated orders to the JSON file at path after every change, the orders
+    /// already stored in the file are loaded
+    pub fn persist<P>(mut self, path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            let orders: Vec<EmulatedOrder> =
+                serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+            info!("Loaded {} emulated orders from {path:?}", orders.len());
+            self.lock()
+                .orders
+                .extend(orders.into_iter().map(|order| (order.id.clone(), order)));
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, EmulatorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes the orders to the persistence file through a temporary file
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        // the orders are copied under the save lock so that a later copy is never
+        // overwritten by an earlier one, ticks only wait for the copy
+        let _saving = self.saving.lock().unwrap_or_else(|e| e.into_inner());
+        let orders = self.lock().orders.values().cloned().collect::<Vec<_>>();
+        let tmp = path.with_extension("tmp");
+
+        let written = File::create(&tmp)
+            .and_then(|file| Ok(serde_json::to_writer(BufWriter::new(file), &orders)?))
+            .and_then(|_| fs::rename(&tmp, path));
+        if let Err(e) = written {
+            error!("Failed to persist the emulated orders to {path:?}: {e}");
+        }
+    }
+
+    /// Adds the emulated order, replacing the one with the same id
+    pub fn add(&self, order: EmulatedOrder) -> String {
+        let id = order.id.clone();
+        debug!(
+            "Emulating {:?} for {}",
+            order.trigger, order.position.trading_symbol
+        );
+        self.lock().orders.insert(id.clone(), order);
+        self.save();
+        id
+    }
+
+    /// Adds a trailing stop `trail` rupees away from the best price, returns its id
+    pub fn trailing_stop(&self, position: EmulatedPosition, trail: f64) -> String {
+        self.add(EmulatedOrder {
+            id: unique_order_tag(),
+            position,
+            trigger: EmulatedTrigger::TrailingStop {
+                trail,
+                stop_price: None,
+            },
+        })
+    }
+
+    /// Adds an OCO exit, the target may already rest at the broker, returns its id
+    pub fn oco(
+        &self,
+        position: EmulatedPosition,
+        target: f64,
+        stop: f64,
+        target_order: Option<RestingOrder>,
+    ) -> String {
+        self.add(EmulatedOrder {
+            id: unique_order_tag(),
+            position,
+            trigger: EmulatedTrigger::Oco {
+                target,
+                stop,
+                target_order,
+            },
+        })
+    }
+
+    /// Removes the emulated order
+    pub fn remove(&self, id: &str) -> Option<EmulatedOrder> {
+        let order = {
+            let mut state = self.lock();
+            state.triggering.remove(id);
+            state.orders.remove(id)
+        };
+        self.save();
+        order
+    }
+
+    /// Returns the emulated orders
+    pub fn orders(&self) -> Vec<EmulatedOrder> {
+        self.lock().orders.values().cloned().collect()
+    }
+
+    /// Applies the order update to the resting target orders, the OCO is done once its
+    /// target is filled and falls back to an emulated target if it is cancelled
+    pub fn apply_update(&self, update: &OrderStatus) {
+        let order = &update.order_data;
+        let Some(state) = OrderState::from_order_book(order) else {
+            return;
+        };
+
+        let mut guard = self.lock();
+        let orders = &mut guard.orders;
+        let Some((id, resting)) = orders
+            .iter_mut()
+            .find_map(|(id, emulated)| match &mut emulated.trigger {
+                EmulatedTrigger::Oco {
+                    target_order: Some(resting),
+                    ..
+                } if resting.order_id == order.order_id => Some((id.clone(), resting)),
+                _ => None,
+            })
+        else {
+            return;
+        };
+
+        resting.filled_quantity = order.filled_shares;
+        match state {
+            OrderState::Complete => {
+                info!("Target order {} filled, OCO {id} done", order.order_id);
+                orders.remove(&id);
+            }
+            OrderState::Cancelled | OrderState::Rejected => {
+                warn!(
+                    "Target order {} ended {state:?}, emulating the target",
+                    order.order_id
+                );
+                if let Some(EmulatedOrder {
+                    trigger: EmulatedTrigger::Oco { target_order, .. },
+                    position,
+                    ..
+                }) = orders.get_mut(&id)
+                {
+                    let filled = target_order.take().map_or(0, |t| t.filled_quantity);
+                    position.quantity = position.quantity.saturating_sub(filled);
+                }
+            }
+            _ => {}
+        }
+        drop(guard);
+        self.save();
+    }
+
+    /// Applies the tick to the emulated orders of its instrument and sends the orders of
+    /// the legs that were hit. An order stays emulated until its exit is placed, it is
+    /// triggered again by a later tick if the placement fails.
+    pub async fn on_tick(&self, message: &Message) -> Vec<EmulationFill> {
+        let price = message.last_traded_price as f64 / self.price_divisor;
+
+        let (hit, moved) = {
+            let mut state = self.lock();
+            let EmulatorState { orders, triggering } = &mut *state;
+            let mut hit = vec![];
+            let mut moved = false;
+            for order in orders
+                .values_mut()
+                .filter(|order| order.matches(message) && !triggering.contains(&order.id))
+            {
+                let trigger = order.trigger.clone();
+                match order.check(price) {
+                    Some(kind) => hit.push((order.clone(), kind)),
+                    None => moved |= order.trigger != trigger,
+                }
+            }
+            triggering.extend(hit.iter().map(|(order, _)| order.id.clone()));
+            (hit, moved)
+        };
+        // the trailed stops are persisted so that a restart does not restore looser ones
+        if moved {
+            self.save();
+        }
+
+        let mut fills = Vec::with_capacity(hit.len());
+        for (order, kind) in hit {
+            let fill = self.fill(order, kind, price).await;
+            self.settle(&fill);
+            fills.push(fill);
+        }
+        fills
+    }
+
+    /// Removes the emulated order once its exit is placed, or releases it to be triggered
+    /// again when the placement failed
+    fn settle(&self, fill: &EmulationFill) {
+        let id = &fill.order.id;
+        let mut state = self.lock();
+        state.triggering.remove(id);
+        match &fill.exit {
+            Some(Err(e)) => {
+                warn!("Exit of emulated order {id} failed, retried on the next trigger: {e}");
+                // the target order is gone once cancelled, the stop then exits the rest
+                if let (
+                    Some(Ok(_)),
+                    Some(EmulatedOrder {
+                        trigger: EmulatedTrigger::Oco { target_order, .. },
+                        position,
+                        ..
+                    }),
+                ) = (&fill.cancellation, state.orders.get_mut(id))
+                {
+                    let filled = target_order.take().map_or(0, |t| t.filled_quantity);
+                    position.quantity = position.quantity.saturating_sub(filled);
+                }
+            }
+            _ => {
+                state.orders.remove(id);
+            }
+        }
+        drop(state);
+        self.save();
+    }
+
+    /// Cancels the resting target if the stop was hit and places the exit order
+    async fn fill(&self, order: EmulatedOrder, kind: TriggerKind, price: f64) -> EmulationFill {
+        info!(
+            "Emulated {kind:?} hit at {price} for {}",
+            order.position.trading_symbol
+        );
+
+        let (resting, target) = match &order.trigger {
+            EmulatedTrigger::Oco {
+                target_order,
+                target,
+                ..
+            } => (target_order.as_ref(), Some(*target)),
+            EmulatedTrigger::TrailingStop { .. } => (None, None),
+        };
+
+        let mut fill = EmulationFill {
+            kind,
+            price,
+            cancellation: None,
+            exit: None,
+            order: order.clone(),
+        };
+
+        let quantity = match (kind, resting) {
+            // the resting target order exits the position at the broker
+            (TriggerKind::Target, Some(_)) => return fill,
+            (TriggerKind::Target, None) => order.position.quantity,
+            (TriggerKind::Stop, None) => order.position.quantity,
+            (TriggerKind::Stop, Some(resting)) => {
+                let cancel_req = CancelOrderReq::new(resting.variety.clone(), &resting.order_id);
+                let cancellation = self.smart_connect.cancel_order(&cancel_req).await;
+                let quantity = match &cancellation {
+                    Ok(_) => Ok(order
+                        .position
+                        .quantity
+                        .saturating_sub(resting.filled_quantity)),
+                    Err(e) => {
+                        error!("Failed to cancel target order {}: {e}", resting.order_id);
+                        let target = self.smart_connect.order_book().await.map(|orders| {
+                            orders
+                                .into_iter()
+                                .find(|target| target.order_id == resting.order_id)
+                        });
+                        open_quantity(&order.position, &resting.order_id, target)
+                    }
+                };
+                fill.cancellation = Some(cancellation);
+                match quantity {
+                    // the target filled before it could be cancelled
+                    Ok(0) => return fill,
+                    Ok(quantity) => quantity,
+                    Err(e) => {
+                        fill.exit = Some(Err(e));
+                        return fill;
+                    }
+                }
+            }
+        };
+
+        let limit = match kind {
+            TriggerKind::Target => target,
+            TriggerKind::Stop => None,
+        };
+        let exit = order.position.exit_order(quantity, limit);
+        let result = self.smart_connect.place_order_once(&exit, 0).await;
+        if let Err(e) = &result {
+            error!(
+                "Failed to place the exit order of {}: {e}",
+                order.position.trading_symbol
+            );
+        }
+        fill.exit = Some(result);
+        fill
+    }
+
+    /// Connects the websocket, sends the subscription and spawns a task applying every
+    /// received tick. The task ends when the stream closes.
+    pub async fn subscribe(
+        &self,
+        ws: &AngelOneWs,
+        subscription: SubscriptionRequest,
+    ) -> Result<EmulationHandle> {
+        let mut stream = ws.stream::<Message>().await?;
+        stream.subscribe(subscription).await?;
+
+        let (sender, fills) = mpsc::unbounded_channel();
+        let emulator = self.clone();
+        let task = tokio::spawn(async move {
+            while let Some(message) = stream.next().await {
+                match message {
+                    Ok(message) => {
+                        for fill in emulator.on_tick(&message).await {
+                            // fills are dropped once the handle is gone
+                            let _ = sender.send(fill);
+                        }
+                    }
+                    Err(e) => error!("Order emulator failed to read the websocket message: {e}"),
+                }
+            }
+            debug!("Order emulator websocket stream closed");
+        });
+        Ok(EmulationHandle { fills, task })
+    }
+}
+
+/// Returns the quantity of the position left open by the target order read back after its
+/// cancellation failed, an error while the target may still fill
+fn open_quantity(
+    position: &EmulatedPosition,
+    order_id: &str,
+    target: Result<Option<OrderBook>>,
+) -> Result<u64> {
+    let target = target?.ok_or_else(|| Error::OrderNotFound(order_id.to_string()))?;
+    match OrderState::from_order_book(&target) {
+        Some(state) if state.is_terminal() => {
+            Ok(position.quantity.saturating_sub(target.filled_shares))
+        }
+        _ => Err(Error::TargetOrderWorking(order_id.to_string())),
+    }
+}
+
+/// Handle to the task of [`OrderEmulator::subscribe`]. Dropping the handle does not stop
+/// the task.
+#[derive(Debug)]
+pub struct EmulationHandle {
+    fills: mpsc::UnboundedReceiver<EmulationFill>,
+    task: JoinHandle<()>,
+}
+
+impl EmulationHandle {
+    /// Returns the next fill, with the results of its cancellation and exit orders, none
+    /// once the stream has closed
+    pub async fn next_fill(&mut self) -> Option<EmulationFill> {
+        self.fills.recv().await
+    }
+
+    /// Stops applying the ticks
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        order::OrderBook,
+        types::{ExchangeType, OrderStatusKind, ProductType, TransactionType},
+        Error,
+    };
+
+    use super::{open_quantity, EmulatedOrder, EmulatedPosition, EmulatedTrigger, TriggerKind};
+
+    fn emulated(side: TransactionType, trigger: EmulatedTrigger) -> EmulatedOrder {
+        EmulatedOrder {
+            id: String::from("1"),
+            position: EmulatedPosition {
+                exchange: ExchangeType::NSE,
+                trading_symbol: String::from("INFY-EQ"),
+                symbol_token: String::from("1594"),
+                product_type: ProductType::Delivery,
+                side,
+                quantity: 10,
+            },
+            trigger,
+        }
+    }
+
+    #[test]
+    fn emulated_triggers() {
+        let mut trailing = emulated(
+            TransactionType::Buy,
+            EmulatedTrigger::TrailingStop {
+                trail: 10.0,
+                stop_price: None,
+            },
+        );
+        assert_eq!(trailing.check(100.0), None);
+        assert_eq!(trailing.check(120.0), None);
+        // the stop never moves against the position
+        assert_eq!(trailing.check(115.0), None);
+        assert_eq!(
+            trailing.trigger,
+            EmulatedTrigger::TrailingStop {
+                trail: 10.0,
+                stop_price: Some(110.0)
+            }
+        );
+        assert_eq!(trailing.check(110.0), Some(TriggerKind::Stop));
+
+        let oco = emulated(
+            TransactionType::Sell,
+            EmulatedTrigger::Oco {
+                target: 90.0,
+                stop: 105.0,
+                target_order: None,
+            },
+        );
+        assert_eq!(oco.clone().check(100.0), None);
+        assert_eq!(oco.clone().check(89.5), Some(TriggerKind::Target));
+        assert_eq!(oco.clone().check(105.0), Some(TriggerKind::Stop));
+
+        let json = serde_json::to_string(&oco).unwrap();
+        assert_eq!(serde_json::from_str::<EmulatedOrder>(&json).unwrap(), oco);
+    }
+
+    #[test]
+    fn exit_after_a_failed_target_cancellation() {
+        let position = emulated(
+            TransactionType::Buy,
+            EmulatedTrigger::TrailingStop {
+                trail: 10.0,
+                stop_price: None,
+            },
+        )
+        .position;
+        let target = |order_status, filled_shares| {
+            Ok(Some(OrderBook {
+                order_id: String::from("T1"),
+                order_status,
+                filled_shares,
+                ..Default::default()
+            }))
+        };
+
+        // the cancellation failed because the target filled, there is nothing left to exit
+        let filled = open_quantity(&position, "T1", target(OrderStatusKind::Complete, 10));
+        assert_eq!(filled.unwrap(), 0);
+        let cancelled = open_quantity(&position, "T1", target(OrderStatusKind::Cancelled, 4));
+        assert_eq!(cancelled.unwrap(), 6);
+        // no exit while the target may still fill
+        let working = open_quantity(&position, "T1", target(OrderStatusKind::Open, 4));
+        assert!(matches!(working, Err(Error::TargetOrderWorking(_))));
+        let missing = open_quantity(&position, "T1", Ok(None));
+        assert!(matches!(missing, Err(Error::OrderNotFound(_))));
+    }
+}
@@ -0,0 +1,705 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{debug, error, info, warn};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::{
+    market::Instrument,
+    order::{
+        CancelOrderReq, OrderSetter, OrderState, OrderValidationError, PlaceOrderReq, RateLimiter,
+    },
+    Error, Result, SmartConnect,
+};
+
+// Default interval between the status polls of a working child order
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Default time a child order is waited for before the execution fails
+const DEFAULT_SETTLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Default number of child orders sent per second, half of the broker limit
+const DEFAULT_ORDERS_PER_SECOND: u32 = 10;
+
+/// How the parent quantity is split into child orders
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStyle {
+    /// Child orders of `child_lots` lots, each sent once the previous one is filled
+    Iceberg {
+        /// Lots per child order
+        child_lots: u64,
+    },
+    /// Quantity spread evenly over `slices` child orders sent across the window
+    Twap {
+        /// Time over which the child orders are sent
+        window: Duration,
+        /// Number of child orders
+        slices: u32,
+    },
+    /// Child orders capped at the freeze quantity, all sent at once
+    Sliced,
+}
+
+/// Execution of a large order as child orders, see [`ExecutionStyle`]. Every child order
+/// is also capped at the freeze quantity of the instrument when it is set.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    /// Parent order, its quantity is the total quantity to execute
+    pub order: PlaceOrderReq,
+    /// Execution style
+    pub style: ExecutionStyle,
+    /// Lot size of the instrument
+    pub lot_size: u64,
+    /// Maximum quantity of a single order accepted by the exchange
+    pub freeze_quantity: Option<u64>,
+    /// Interval between the status polls of a working child order
+    pub poll_interval: Duration,
+    /// Time a child order is waited for to be terminal before the execution fails
+    pub settle_timeout: Duration,
+    /// Number of child orders sent per second
+    pub orders_per_second: u32,
+}
+
+impl Execution {
+    fn with_style(order: PlaceOrderReq, style: ExecutionStyle) -> Self {
+        Self {
+            order,
+            style,
+            lot_size: 1,
+            freeze_quantity: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            settle_timeout: DEFAULT_SETTLE_TIMEOUT,
+            orders_per_second: DEFAULT_ORDERS_PER_SECOND,
+        }
+    }
+
+    /// Returns an iceberg execution sending child orders of `child_lots` lots one at a time
+    pub fn iceberg(order: PlaceOrderReq, child_lots: u64) -> Self {
+        Self::with_style(order, ExecutionStyle::Iceberg { child_lots })
+    }
+
+    /// Returns a TWAP execution sending `slices` equal child orders evenly over the window
+    pub fn twap(order: PlaceOrderReq, window: Duration, slices: u32) -> Self {
+        Self::with_style(order, ExecutionStyle::Twap { window, slices })
+    }
+
+    /// Returns an execution sending the order in child orders of at most the freeze quantity
+    pub fn sliced(order: PlaceOrderReq, freeze_quantity: u64) -> Self {
+        Self::with_style(order, ExecutionStyle::Sliced).freeze_quantity(freeze_quantity)
+    }
+
+    /// Sets the lot size, 1 by default
+    pub fn lot_size(mut self, lot_size: u64) -> Self {
+        self.lot_size = lot_size.max(1);
+        self
+    }
+
+    /// Sets the lot size from the [`Instrument`]
+    pub fn instrument(self, instrument: &Instrument) -> Self {
+        let lot_size = instrument.lot_size_value().unwrap_or(1);
+        self.lot_size(lot_size)
+    }
+
+    /// Caps every child order at the freeze quantity
+    pub fn freeze_quantity(mut self, freeze_quantity: u64) -> Self {
+        self.freeze_quantity = Some(freeze_quantity);
+        self
+    }
+
+    /// Sets the interval between the status polls of a working child order
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the time a child order is waited for to be terminal before the execution fails
+    pub fn settle_timeout(mut self, settle_timeout: Duration) -> Self {
+        self.settle_timeout = settle_timeout;
+        self
+    }
+
+    /// Sets the number of child orders sent per second
+    pub fn orders_per_second(mut self, orders_per_second: u32) -> Self {
+        self.orders_per_second = orders_per_second;
+        self
+    }
+
+    /// Returns the child quantities grouped by the slot they are sent in
+    pub fn schedule(&self) -> Result<Vec<Vec<u64>>> {
+        let quantity = self.order.inner.quantity.trim();
+        let quantity =
+            quantity
+                .parse::<u64>()
+                .map_err(|_| OrderValidationError::InvalidNumber {
+                    field: "quantity",
+                    value: quantity.to_string(),
+                })?;
+        if quantity == 0 {
+            return Err(OrderValidationError::ZeroQuantity.into());
+        }
+        if !quantity.is_multiple_of(self.lot_size) {
+            return Err(OrderValidationError::LotSize {
+                quantity,
+                lot_size: self.lot_size,
+            }
+            .into());
+        }
+
+        if let Some(freeze_quantity) = self.freeze_quantity
+            && freeze_quantity < self.lot_size
+        {
+            return Err(OrderValidationError::FreezeQuantity {
+                freeze_quantity,
+                lot_size: self.lot_size,
+            }
+            .into());
+        }
+
+        let lots = quantity / self.lot_size;
+        let max_lots = self
+            .freeze_quantity
+            .map_or(lots, |freeze_quantity| freeze_quantity / self.lot_size);
+
+        let slots = match self.style {
+            ExecutionStyle::Iceberg { child_lots } => {
+                split_capped(lots, child_lots.clamp(1, max_lots))
+                    .into_iter()
+                    .map(|lots| vec![lots])
+                    .collect()
+            }
+            ExecutionStyle::Twap { slices, .. } => split_even(lots, slices.max(1) as u64)
+                .into_iter()
+                .filter(|lots| *lots > 0)
+                .map(|lots| split_capped(lots, max_lots))
+                .collect(),
+            ExecutionStyle::Sliced => vec![split_capped(lots, max_lots)],
+        };
+
+        Ok(slots
+            .into_iter()
+            .map(|slot: Vec<u64>| slot.into_iter().map(|lots| lots * self.lot_size).collect())
+            .collect())
+    }
+
+    /// Time between two slots
+    fn slot_interval(&self) -> Duration {
+        match self.style {
+            ExecutionStyle::Twap { window, slices } => window / slices.max(1),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Starts the execution in a background task
+    pub fn start(self, smart_connect: Arc<SmartConnect>) -> Result<ExecutionHandle> {
+        let schedule = self.schedule()?;
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let (cancel, cancel_rx) = watch::channel(false);
+
+        let runner = Runner {
+            smart_connect,
+            execution: self,
+            events: events_tx,
+            cancel: cancel_rx,
+            children: vec![],
+        };
+        let task = tokio::spawn(runner.run(schedule));
+
+        Ok(ExecutionHandle {
+            events,
+            cancel,
+            task,
+        })
+    }
+}
+
+/// Splits the lots into `parts` parts differing by at most one lot, larger parts first
+fn split_even(lots: u64, parts: u64) -> Vec<u64> {
+    (0..parts)
+        .map(|i| lots / parts + u64::from(i < lots % parts))
+        .collect()
+}
+
+/// Splits the lots into parts of at most `max_lots`
+fn split_capped(lots: u64, max_lots: u64) -> Vec<u64> {
+    let mut parts = vec![max_lots; (lots / max_lots) as usize];
+    if !lots.is_multiple_of(max_lots) {
+        parts.push(lots % max_lots);
+    }
+    parts
+}
+
+/// Progress of an [`Execution`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionEvent {
+    /// Child order placed
+    Placed {
+        /// Index of the child order
+        child: usize,
+        /// Order ID
+        order_id: String,
+        /// Quantity of the child order
+        quantity: u64,
+    },
+    /// Child order fully filled
+    Filled {
+        /// Index of the child order
+        child: usize,
+        /// Order ID
+        order_id: String,
+        /// Filled quantity
+        quantity: u64,
+        /// Average fill price
+        average_price: f64,
+    },
+    /// Child order cancelled or rejected
+    Ended {
+        /// Index of the child order
+        child: usize,
+        /// Order ID
+        order_id: String,
+        /// Final state
+        state: OrderState,
+        /// Filled quantity
+        filled_quantity: u64,
+        /// Status text from the broker
+        text: String,
+    },
+    /// Child order failed to be placed or was not terminal within the settle timeout
+    Failed {
+        /// Index of the child order
+        child: usize,
+        /// Quantity of the child order
+        quantity: u64,
+        /// Placement or settle error
+        error: String,
+    },
+    /// Execution cancelled, the working child orders were cancelled
+    Cancelled,
+}
+
+/// Outcome of an [`Execution`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionSummary {
+    /// Total quantity of the parent order
+    pub quantity: u64,
+    /// Quantity placed in child orders
+    pub placed_quantity: u64,
+    /// Quantity filled
+    pub filled_quantity: u64,
+    /// Average fill price over the child orders
+    pub average_price: f64,
+    /// True if the execution was cancelled
+    pub cancelled: bool,
+    /// True if the execution stopped because a child order failed, was rejected or cancelled,
+    /// or did not settle, the working child orders were then cancelled
+    pub failed: bool,
+}
+
+impl ExecutionSummary {
+    /// Returns true if the whole quantity was filled
+    pub fn is_complete(&self) -> bool {
+        self.quantity > 0 && self.filled_quantity == self.quantity
+    }
+}
+
+/// Handle to a running [`Execution`]. Dropping the handle does not stop the execution.
+#[derive(Debug)]
+pub struct ExecutionHandle {
+    events: mpsc::UnboundedReceiver<ExecutionEvent>,
+    cancel: watch::Sender<bool>,
+    task: JoinHandle<ExecutionSummary>,
+}
+
+impl ExecutionHandle {
+    /// Stops sending child orders and cancels the working ones
+    pub fn cancel(&self) {
+        self.cancel.send_replace(true);
+    }
+
+    /// Returns the next progress event, none once the execution has finished
+    pub async fn next_event(&mut self) -> Option<ExecutionEvent> {
+        self.events.recv().await
+    }
+
+    /// Waits for the execution to finish
+    pub async fn join(self) -> Result<ExecutionSummary> {
+        self.task.await.map_err(|e| Error::BoxedError(Box::new(e)))
+    }
+}
+
+/// Child order placed by the [`Runner`]
+#[derive(Debug)]
+struct Child {
+    order_id: String,
+    unique_order_id: Option<String>,
+    quantity: u64,
+    state: OrderState,
+    filled_quantity: u64,
+    average_price: f64,
+}
+
+/// Reason a [`Runner`] stops before the end of the schedule
+enum Stop {
+    Cancelled,
+    Failed,
+}
+
+/// Background task of an [`Execution`]
+struct Runner {
+    smart_connect: Arc<SmartConnect>,
+    execution: Execution,
+    events: mpsc::UnboundedSender<ExecutionEvent>,
+    cancel: watch::Receiver<bool>,
+    children: Vec<Child>,
+}
+
+impl Runner {
+    async fn run(mut self, schedule: Vec<Vec<u64>>) -> ExecutionSummary {
+        let limiter = RateLimiter::new(self.execution.orders_per_second);
+        let wait_for_fill = matches!(self.execution.style, ExecutionStyle::Iceberg { .. });
+        let mut summary = ExecutionSummary {
+            quantity: schedule.iter().flatten().sum(),
+            ..Default::default()
+        };
+
+        'slots: for (slot, quantities) in schedule.into_iter().enumerate() {
+            if slot > 0 {
+                match self.wait_slot(self.execution.slot_interval()).await {
+                    Some(Stop::Cancelled) => {
+                        summary.cancelled = true;
+                        break;
+                    }
+                    Some(Stop::Failed) => {
+                        summary.failed = true;
+                        break;
+                    }
+                    None => {}
+                }
+            }
+
+            for quantity in quantities {
+                if *self.cancel.borrow() {
+                    summary.cancelled = true;
+                    break 'slots;
+                }
+                limiter.acquire().await;
+                if !self.place(quantity).await {
+                    summary.failed = true;
+                    break 'slots;
+                }
+                if !wait_for_fill {
+                    continue;
+                }
+
+                let child = self.children.len() - 1;
+                match self.settle(child).await {
+                    Some(OrderState::Complete) => {}
+                    Some(_) => {
+                        summary.failed = true;
+                        break 'slots;
+                    }
+                    None => {
+                        summary.cancelled = true;
+                        break 'slots;
+                    }
+                }
+            }
+        }
+
+        // the remaining child orders are waited for unless the execution is stopped
+        if !summary.cancelled && !summary.failed {
+            for child in 0..self.children.len() {
+                match self.settle(child).await {
+                    Some(OrderState::Complete) => {}
+                    Some(_) => {
+                        summary.failed = true;
+                        break;
+                    }
+                    None => {
+                        summary.cancelled = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if summary.cancelled || summary.failed {
+            self.cancel_working().await;
+        }
+        if summary.cancelled {
+            self.emit(ExecutionEvent::Cancelled);
+        }
+
+        summary.placed_quantity = self.children.iter().map(|c| c.quantity).sum();
+        summary.filled_quantity = self.children.iter().map(|c| c.filled_quantity).sum();
+        if summary.filled_quantity > 0 {
+            summary.average_price = self
+                .children
+                .iter()
+                .map(|c| c.filled_quantity as f64 * c.average_price)
+                .sum::<f64>()
+                / summary.filled_quantity as f64;
+        }
+
+        info!(
+            "Execution of {} finished, filled {} of {}",
+            self.execution.order.inner.trading_symbol, summary.filled_quantity, summary.quantity
+        );
+        summary
+    }
+
+    fn emit(&self, event: ExecutionEvent) {
+        // the handle may have been dropped, the execution keeps running
+        let _ = self.events.send(event);
+    }
+
+    /// Waits for the duration, returns true if the execution was cancelled meanwhile
+    async fn wait(&mut self, duration: Duration) -> bool {
+        let cancelled = async {
+            if self.cancel.wait_for(|cancelled| *cancelled).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => false,
+            _ = cancelled => true,
+        }
+    }
+
+    /// Waits for the next slot while polling the working child orders, returns why the
+    /// execution has to stop if it was cancelled or a child order ended unfilled meanwhile
+    async fn wait_slot(&mut self, duration: Duration) -> Option<Stop> {
+        let deadline = Instant::now() + duration;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            if self.wait(remaining.min(self.execution.poll_interval)).await {
+                return Some(Stop::Cancelled);
+            }
+            for index in 0..self.children.len() {
+                if !self.children[index].state.is_terminal() {
+                    self.refresh(index).await;
+                }
+            }
+            if let Some(index) = self.children.iter().position(|child| {
+                matches!(child.state, OrderState::Rejected | OrderState::Cancelled)
+            }) {
+                error!(
+                    "Execution child {index} ended {:?}, stopping",
+                    self.children[index].state
+                );
+                return Some(Stop::Failed);
+            }
+        }
+    }
+
+    /// Places the child order, returns false if it failed
+    async fn place(&mut self, quantity: u64) -> bool {
+        let index = self.children.len();
+        let mut order = self.execution.order.clone().quantity(quantity);
+        order.inner.order_tag = None;
+
+        let res = match self.smart_connect.place_order_once(&order, 0).await {
+            Ok(res) => res,
+            Err(e) => {
+                error!("Execution child {index} failed: {e}");
+                self.emit(ExecutionEvent::Failed {
+                    child: index,
+                    quantity,
+                    error: e.to_string(),
+                });
+                return false;
+            }
+        };
+        let Some(order_id) = res.order_id else {
+            self.emit(ExecutionEvent::Failed {
+                child: index,
+                quantity,
+                error: Error::OrderIdMissing.to_string(),
+            });
+            return false;
+        };
+
+        debug!("Execution child {index} placed as {order_id}");
+        self.emit(ExecutionEvent::Placed {
+            child: index,
+            order_id: order_id.clone(),
+            quantity,
+        });
+        self.children.push(Child {
+            order_id,
+            unique_order_id: res.unique_order_id,
+            quantity,
+            state: OrderState::Pending,
+            filled_quantity: 0,
+            average_price: 0.0,
+        });
+        true
+    }
+
+    /// Polls the child order until it is terminal or the settle timeout elapses, returns its
+    /// last known state, none if the execution was cancelled
+    async fn settle(&mut self, index: usize) -> Option<OrderState> {
+        let deadline = Instant::now() + self.execution.settle_timeout;
+        loop {
+            let child = &self.children[index];
+            if child.state.is_terminal() {
+                return Some(child.state.clone());
+            }
+            if Instant::now() >= deadline {
+                let error = format!(
+                    "order {} not terminal after {:?}",
+                    child.order_id, self.execution.settle_timeout
+                );
+                error!("Execution child {index} failed: {error}");
+                self.emit(ExecutionEvent::Failed {
+                    child: index,
+                    quantity: child.quantity,
+                    error,
+                });
+                return Some(child.state.clone());
+            }
+            if self.wait(self.execution.poll_interval).await {
+                return None;
+            }
+            self.refresh(index).await;
+        }
+    }
+
+    /// Refreshes the state of the child order from the broker
+    async fn refresh(&mut self, index: usize) {
+        let child = &self.children[index];
+        let order = match &child.unique_order_id {
+            Some(unique_order_id) => self
+                .smart_connect
+                .order_status(unique_order_id)
+                .await
+                .map(|status| Some(status.order)),
+            None => self.smart_connect.order_book().await.map(|orders| {
+                orders
+                    .into_iter()
+                    .find(|order| order.order_id == child.order_id)
+            }),
+        };
+
+        let order = match order {
+            Ok(Some(order)) => order,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Execution failed to refresh order {}: {e}", child.order_id);
+                return;
+            }
+        };
+        let Some(state) = OrderState::from_order_book(&order) else {
+            return;
+        };
+
+        let child = &mut self.children[index];
+        child.filled_quantity = order.filled_shares;
+        child.average_price = order.average_price;
+        if child.state == state {
+            return;
+        }
+        child.state = state.clone();
+
+        let event = match state {
+            OrderState::Complete => ExecutionEvent::Filled {
+                child: index,
+                order_id: child.order_id.clone(),
+                quantity: child.filled_quantity,
+                average_price: child.average_price,
+            },
+            OrderState::Cancelled | OrderState::Rejected => ExecutionEvent::Ended {
+                child: index,
+                order_id: child.order_id.clone(),
+                state,
+                filled_quantity: child.filled_quantity,
+                text: order.text,
+            },
+            _ => return,
+        };
+        self.emit(event);
+    }
+
+    /// Cancels the child orders which are not known to be terminal
+    async fn cancel_working(&mut self) {
+        let variety = self.execution.order.inner.variety.clone();
+        for index in 0..self.children.len() {
+            if self.children[index].state.is_terminal() {
+                continue;
+            }
+            let order_id = &self.children[index].order_id;
+            let cancel_req = CancelOrderReq::new(variety.clone(), order_id);
+            if let Err(e) = self.smart_connect.cancel_order(&cancel_req).await {
+                warn!("Execution failed to cancel order {order_id}: {e}");
+            }
+            self.refresh(index).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        order::{OrderSetter, OrderValidationError, PlaceOrderReq},
+        types::TransactionType,
+        Error,
+    };
+
+    use super::Execution;
+
+    fn order(quantity: u64) -> PlaceOrderReq {
+        PlaceOrderReq::new("NIFTY26DEC24FUT", "35003", TransactionType::Buy).quantity(quantity)
+    }
+
+    #[test]
+    fn execution_schedules() {
+        let iceberg = Execution::iceberg(order(1_000), 8)
+            .lot_size(25)
+            .freeze_quantity(150);
+        assert_eq!(
+            iceberg.schedule().unwrap(),
+            vec![
+                vec![150],
+                vec![150],
+                vec![150],
+                vec![150],
+                vec![150],
+                vec![150],
+                vec![100]
+            ]
+        );
+
+        let twap = Execution::twap(order(2_000), Duration::from_secs(60), 3)
+            .lot_size(25)
+            .freeze_quantity(500);
+        assert_eq!(
+            twap.schedule().unwrap(),
+            vec![vec![500, 175], vec![500, 175], vec![500, 150]]
+        );
+
+        let sliced = Execution::sliced(order(1_800), 900).lot_size(75);
+        assert_eq!(sliced.schedule().unwrap(), vec![vec![900, 900]]);
+
+        assert!(matches!(
+            Execution::sliced(order(100), 900).lot_size(75).schedule(),
+            Err(Error::OrderValidation(_))
+        ));
+        // a single lot would exceed the freeze quantity
+        assert!(matches!(
+            Execution::sliced(order(1_800), 900)
+                .lot_size(75)
+                .freeze_quantity(50)
+                .schedule(),
+            Err(Error::OrderValidation(
+                OrderValidationError::FreezeQuantity { .. }
+            ))
+        ));
+    }
+}
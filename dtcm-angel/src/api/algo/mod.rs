@@ -0,0 +1,8 @@
+mod execution;
+pub use execution::{Execution, ExecutionEvent, ExecutionHandle, ExecutionStyle, ExecutionSummary};
+
+mod emulation;
+pub use emulation::{
+    EmulatedOrder, EmulatedPosition, EmulatedTrigger, EmulationFill, EmulationHandle,
+    OrderEmulator, RestingOrder, TriggerKind,
+};
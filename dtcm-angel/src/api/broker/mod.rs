@@ -0,0 +1,5 @@
+mod traits;
+pub use traits::{Broker, FundsApi, GttApi, MarketDataApi, OrderApi, PortfolioApi, UserApi};
+
+mod paper;
+pub use paper::PaperBroker;
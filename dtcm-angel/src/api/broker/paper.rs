@@ -0,0 +1,807 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use dtcm_angel_utils::date::ist;
+use futures_util::{Stream, StreamExt};
+use http_serde::http::StatusCode;
+use log::{debug, info, warn};
+use tokio::sync::broadcast;
+
+use crate::{
+    funds::{
+        MarginBreakup, MarginCalculatorPosition, MarginCalculatorRes, MarginComponents, OptionsBuy,
+        Rms,
+    },
+    order::{
+        CancelOrderReq, CancelOrderRes, IndividualOrderStatus, ModifyOrderReq, ModifyOrderRes,
+        OrderBook, OrderInner, OrderState, PlaceOrderReq, PlaceOrderRes, TradeBook,
+    },
+    portfolio::{AllHoldings, ConvertPositionReq, Holding, Position},
+    types::{ExchangeType, OrderStatusKind, OrderType, TransactionType},
+    ws::{
+        AngelOneWsOrderStatusEn as StatusEn, AngelOneWsOrderStatusErrorCode, Message, OrderStatus,
+        StatusCode_, SubscriptionExchange,
+    },
+    Error, Result,
+};
+
+use super::{FundsApi, OrderApi, PortfolioApi};
+
+// Order updates buffered for the slowest subscriber
+const UPDATES_CAPACITY: usize = 1024;
+
+// Client code reported in the order updates
+const DEFAULT_CLIENT_CODE: &str = "PAPER";
+
+/// Paper trading backend keeping the orders, trades, positions and cash locally.
+///
+/// Orders are filled in full at the last traded price fed with [`PaperBroker::on_tick`],
+/// from live or replayed ticks: market orders on the next price, limit orders once the
+/// price reaches the limit, and stop loss orders once the price crosses the trigger. The
+/// account is a cash account without leverage or charges, buy orders whose value exceeds
+/// the available cash are rejected at placement or fill time.
+#[derive(Debug)]
+pub struct PaperBroker {
+    client_code: String,
+    state: Mutex<PaperState>,
+    updates: broadcast::Sender<OrderStatus>,
+    price_divisor: f64,
+}
+
+#[derive(Debug, Default)]
+struct PaperState {
+    cash: f64,
+    last_order_id: u64,
+    orders: Vec<OrderBook>,
+    trades: Vec<TradeBook>,
+    positions: Vec<Position>,
+    prices: HashMap<(SubscriptionExchange, String), f64>,
+}
+
+/// Returns the current exchange time
+fn now() -> NaiveDateTime {
+    Utc::now().with_timezone(&ist()).naive_local()
+}
+
+/// Parses a validated order field
+fn number<T>(value: &str) -> T
+where
+    T: FromStr + Default,
+{
+    value.parse().unwrap_or_default()
+}
+
+/// Returns the status of a new or modified order, stop loss orders wait for the trigger
+fn working_status(order_type: &OrderType) -> OrderStatusKind {
+    match order_type {
+        OrderType::StopLossLimit | OrderType::StopLossMarket => OrderStatusKind::TriggerPending,
+        OrderType::Market | OrderType::Limit | OrderType::Unknown(_) => OrderStatusKind::Open,
+    }
+}
+
+/// Returns true if the order is still working
+fn is_working(order: &OrderBook) -> bool {
+    OrderState::from_order_book(order).is_some_and(|state| !state.is_terminal())
+}
+
+/// Applies the order fields shared by placement and modification
+fn apply_inner(order: &mut OrderBook, inner: &OrderInner) {
+    let quantity = number(&inner.quantity);
+    let status = working_status(&inner.order_type);
+
+    order.order_type = inner.order_type.clone();
+    order.duration = inner.duration.clone();
+    order.price = number(&inner.price);
+    order.trigger_price = inner.trigger_price.as_deref().map_or(0.0, number);
+    order.quantity = quantity;
+    order.disclosed_quantity = number(&inner.disclosed_quantity);
+    order.unfilled_shares = quantity;
+    order.status = status.clone();
+    order.order_status = status;
+    order.update_time = Some(now());
+}
+
+/// Returns the fill price of the working order at the last traded price, triggering the
+/// stop loss order on the way
+fn fill_price(order: &mut OrderBook, ltp: f64) -> Option<f64> {
+    let buy = match order.transaction_type {
+        TransactionType::Buy => true,
+        TransactionType::Sell => false,
+        TransactionType::Unknown(_) => return None,
+    };
+
+    if order.order_status == OrderStatusKind::TriggerPending {
+        let triggered = if buy {
+            ltp >= order.trigger_price
+        } else {
+            ltp <= order.trigger_price
+        };
+        if !triggered {
+            return None;
+        }
+        order.status = OrderStatusKind::Open;
+        order.order_status = OrderStatusKind::Open;
+    }
+
+    match order.order_type {
+        OrderType::Market | OrderType::StopLossMarket => Some(ltp),
+        OrderType::Limit | OrderType::StopLossLimit => {
+            let marketable = if buy {
+                ltp <= order.price
+            } else {
+                ltp >= order.price
+            };
+            marketable.then_some(ltp)
+        }
+        OrderType::Unknown(_) => None,
+    }
+}
+
+/// Returns the price the order is expected to fill at, the last traded price for market
+/// orders
+fn expected_price(order: &OrderBook, ltp: Option<f64>) -> Option<f64> {
+    match order.order_type {
+        OrderType::Limit | OrderType::StopLossLimit => Some(order.price),
+        OrderType::StopLossMarket => Some(order.trigger_price),
+        OrderType::Market | OrderType::Unknown(_) => ltp,
+    }
+}
+
+/// Records the fill in the position, creating it on the first fill
+fn record_fill(position: &mut Position, side: &TransactionType, quantity: u64, price: f64) {
+    let value = quantity as f64 * price;
+
+    match side {
+        TransactionType::Buy => {
+            position.buy_quantity += quantity;
+            position.buy_amount += value;
+            position.buy_average_price = position.buy_amount / position.buy_quantity as f64;
+            position.net_qty += quantity as i64;
+        }
+        TransactionType::Sell => {
+            position.sell_quantity += quantity;
+            position.sell_amount += value;
+            position.sell_average_price = position.sell_amount / position.sell_quantity as f64;
+            position.net_qty -= quantity as i64;
+        }
+        // never filled, see `fill_price`
+        TransactionType::Unknown(_) => return,
+    }
+
+    position.total_buy_value = position.buy_amount;
+    position.total_sell_value = position.sell_amount;
+    position.total_buy_average_price = position.buy_average_price;
+    position.total_sell_average_price = position.sell_average_price;
+    position.net_value = position.sell_amount - position.buy_amount;
+    position.net_price = match position.net_qty {
+        0 => 0.0,
+        net_qty => -position.net_value / net_qty as f64,
+    };
+    position.average_net_price = position.net_price;
+}
+
+impl PaperState {
+    fn next_order_id(&mut self) -> String {
+        self.last_order_id += 1;
+        format!("{:015}", self.last_order_id)
+    }
+
+    fn price(&self, exchange: &ExchangeType, symbol_token: &str) -> Option<f64> {
+        let exchange = SubscriptionExchange::try_from(exchange).ok()?;
+        self.prices
+            .get(&(exchange, symbol_token.to_string()))
+            .copied()
+    }
+
+    fn find(&mut self, order_id: &str) -> Result<&mut OrderBook> {
+        let order = self
+            .orders
+            .iter_mut()
+            .find(|order| order.order_id == order_id)
+            .ok_or_else(|| Error::OrderNotFound(order_id.to_string()))?;
+
+        if is_working(order) {
+            Ok(order)
+        } else {
+            Err(Error::OrderClosed(order_id.to_string()))
+        }
+    }
+
+    /// Rejects the buy order at the index if its value at the price exceeds the cash,
+    /// returning its update
+    fn check_cash(&mut self, index: usize, price: f64) -> Option<(OrderBook, StatusEn)> {
+        let cash = self.cash;
+        let order = &mut self.orders[index];
+        let value = order.quantity as f64 * price;
+        if order.transaction_type != TransactionType::Buy || value <= cash {
+            return None;
+        }
+
+        warn!(
+            "Paper order {} rejected, {value:.2} required, {cash:.2} available",
+            order.order_id
+        );
+        order.text = format!("insufficient funds, {value:.2} required, {cash:.2} available");
+        order.status = OrderStatusKind::Rejected;
+        order.order_status = OrderStatusKind::Rejected;
+        order.update_time = Some(now());
+        Some((order.clone(), StatusEn::Rejected))
+    }
+
+    /// Matches the order at the index against the last traded price, returning its updates
+    fn execute(&mut self, index: usize, ltp: f64) -> Vec<(OrderBook, StatusEn)> {
+        let order = &mut self.orders[index];
+        let pending = order.order_status == OrderStatusKind::TriggerPending;
+        let Some(price) = fill_price(order, ltp) else {
+            // a stop loss limit order triggered without reaching its limit
+            if pending && order.order_status == OrderStatusKind::Open {
+                return vec![(order.clone(), StatusEn::Open)];
+            }
+            return vec![];
+        };
+        if let Some(rejection) = self.check_cash(index, price) {
+            return vec![rejection];
+        }
+        let order = &mut self.orders[index];
+
+        let time = now();
+        let fill_id = (self.trades.len() + 1).to_string();
+        order.average_price = price;
+        order.filled_shares = order.quantity;
+        order.unfilled_shares = 0;
+        order.status = OrderStatusKind::Complete;
+        order.order_status = OrderStatusKind::Complete;
+        order.update_time = Some(time);
+        order.exch_time = Some(time);
+        order.exch_order_update_time = Some(time);
+        order.fill_id = fill_id.clone();
+        order.fill_time = time.format("%H:%M:%S").to_string();
+        let order = order.clone();
+
+        info!(
+            "Paper {:?} {} {} filled at {price}",
+            order.transaction_type, order.quantity, order.trading_symbol
+        );
+
+        let value = order.quantity as f64 * price;
+        self.cash += match order.transaction_type {
+            TransactionType::Buy => -value,
+            TransactionType::Sell => value,
+            TransactionType::Unknown(_) => 0.0,
+        };
+
+        self.trades.push(TradeBook {
+            exchange: order.exchange.clone(),
+            product_type: order.product_type.clone(),
+            trading_symbol: order.trading_symbol.clone(),
+            instrument_type: order.instrument_type.clone(),
+            market_lot: order.lot_size,
+            precision: 2,
+            multiplier: 1.0,
+            trade_value: value,
+            transaction_type: order.transaction_type.clone(),
+            fill_price: price,
+            fill_size: order.quantity,
+            order_id: order.order_id.clone(),
+            fill_id,
+            fill_time: order.fill_time.clone(),
+            ..Default::default()
+        });
+
+        let index = match self.positions.iter().position(|position| {
+            position.exchange == order.exchange
+                && position.symbol_token == order.symbol_token
+                && position.product_type == order.product_type
+        }) {
+            Some(index) => index,
+            None => {
+                self.positions.push(Position {
+                    exchange: order.exchange.clone(),
+                    symbol_token: order.symbol_token.clone(),
+                    product_type: order.product_type.clone(),
+                    trading_symbol: order.trading_symbol.clone(),
+                    symbol_name: order.trading_symbol.clone(),
+                    instrument_type: order.instrument_type.clone(),
+                    lot_size: order.lot_size,
+                    board_lot_size: order.lot_size,
+                    multiplier: 1.0,
+                    precision: 2,
+                    ..Default::default()
+                });
+                self.positions.len() - 1
+            }
+        };
+        record_fill(
+            &mut self.positions[index],
+            &order.transaction_type,
+            order.quantity,
+            price,
+        );
+
+        vec![(order, StatusEn::Complete)]
+    }
+}
+
+impl PaperBroker {
+    /// Returns a new instance for [`PaperBroker`] with the opening cash balance
+    pub fn new(cash: f64) -> Self {
+        let (updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        Self {
+            client_code: String::from(DEFAULT_CLIENT_CODE),
+            state: Mutex::new(PaperState {
+                cash,
+                ..Default::default()
+            }),
+            updates,
+            price_divisor: 100.0,
+        }
+    }
+
+    /// Sets the divisor converting the feed prices to rupees, 100 (paise) by default
+    pub fn price_divisor(mut self, price_divisor: f64) -> Self {
+        self.price_divisor = price_divisor;
+        self
+    }
+
+    /// Sets the client code reported in the order updates
+    pub fn client_code<C>(mut self, client_code: C) -> Self
+    where
+        C: Into<String>,
+    {
+        self.client_code = client_code.into();
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PaperState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns a receiver of the order updates, shaped as the order status websocket
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderStatus> {
+        self.updates.subscribe()
+    }
+
+    /// Returns the last traded price of the instrument
+    pub fn last_price(&self, exchange: &ExchangeType, symbol_token: &str) -> Option<f64> {
+        self.lock().price(exchange, symbol_token)
+    }
+
+    /// Publishes the order updates and returns them
+    fn emit(&self, updates: Vec<(OrderBook, StatusEn)>) -> Vec<OrderStatus> {
+        updates
+            .into_iter()
+            .map(|(order, order_status)| {
+                let update = OrderStatus {
+                    user_id: self.client_code.clone(),
+                    status_code: StatusCode_(StatusCode::OK),
+                    order_status,
+                    error_message: AngelOneWsOrderStatusErrorCode::NoError,
+                    order_data: order,
+                };
+                // nobody may be subscribed
+                let _ = self.updates.send(update.clone());
+                update
+            })
+            .collect()
+    }
+
+    /// Sets the last traded price of the instrument in rupees, filling the working orders
+    /// it reaches and returning their updates
+    pub fn update_price<T>(
+        &self,
+        exchange: SubscriptionExchange,
+        symbol_token: T,
+        ltp: f64,
+    ) -> Vec<OrderStatus>
+    where
+        T: Into<String>,
+    {
+        let symbol_token = symbol_token.into();
+        let updates = {
+            let mut state = self.lock();
+            let working = state
+                .orders
+                .iter()
+                .enumerate()
+                .filter(|(_, order)| {
+                    is_working(order)
+                        && order.symbol_token == symbol_token
+                        && SubscriptionExchange::try_from(&order.exchange)
+                            .is_ok_and(|e| e == exchange)
+                })
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            state.prices.insert((exchange, symbol_token), ltp);
+
+            working
+                .into_iter()
+                .flat_map(|index| state.execute(index, ltp))
+                .collect()
+        };
+        self.emit(updates)
+    }
+
+    /// Applies the last traded price of the tick
+    pub fn on_tick(&self, message: &Message) -> Vec<OrderStatus> {
+        let ltp = message.last_traded_price as f64 / self.price_divisor;
+        self.update_price(message.exchange, message.token.as_str(), ltp)
+    }
+
+    /// Applies the ticks of a live or replayed feed until it ends
+    pub async fn run<S, E>(&self, mut ticks: S)
+    where
+        S: Stream<Item = std::result::Result<Message, E>> + Unpin,
+        E: Display,
+    {
+        while let Some(tick) = ticks.next().await {
+            match tick {
+                Ok(message) => {
+                    self.on_tick(&message);
+                }
+                Err(e) => warn!("Paper broker skipped a tick: {e}"),
+            }
+        }
+        debug!("Paper broker feed ended");
+    }
+}
+
+#[async_trait]
+impl OrderApi for PaperBroker {
+    async fn place_order(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderRes> {
+        order_req.validate()?;
+        let inner = &order_req.inner;
+
+        let (order_id, updates) = {
+            let mut state = self.lock();
+            let order_id = state.next_order_id();
+            let mut order = OrderBook {
+                variety: inner.variety.clone(),
+                product_type: inner.product_type.clone(),
+                square_off: inner.square_off.as_deref().map_or(0.0, number),
+                stop_loss: inner.stop_loss.as_deref().map_or(0.0, number),
+                trailing_stop_loss: inner.trailing_stop_loss.as_deref().map_or(0.0, number),
+                trading_symbol: inner.trading_symbol.clone(),
+                transaction_type: order_req.transaction_type.clone(),
+                exchange: inner.exchange.clone(),
+                symbol_token: inner.symbol_token.clone(),
+                order_tag: inner.order_tag.clone().unwrap_or_default(),
+                strike_price: -1.0,
+                lot_size: 1,
+                order_id: order_id.clone(),
+                unique_order_id: Some(order_id.clone()),
+                ..Default::default()
+            };
+            apply_inner(&mut order, inner);
+            let status = match order.order_status {
+                OrderStatusKind::TriggerPending => StatusEn::TriggerPending,
+                _ => StatusEn::Open,
+            };
+            let ltp = state.price(&inner.exchange, &inner.symbol_token);
+            let price = expected_price(&order, ltp);
+            state.orders.push(order.clone());
+
+            let index = state.orders.len() - 1;
+            if let Some(rejection) = price.and_then(|price| state.check_cash(index, price)) {
+                (order_id, vec![rejection])
+            } else {
+                let mut updates = vec![(order, status)];
+                if let Some(ltp) = ltp {
+                    updates.extend(state.execute(index, ltp));
+                }
+                (order_id, updates)
+            }
+        };
+        self.emit(updates);
+
+        Ok(PlaceOrderRes {
+            script: inner.trading_symbol.clone(),
+            order_id: Some(order_id.clone()),
+            unique_order_id: Some(order_id),
+        })
+    }
+
+    async fn modify_order(&self, modify_order_req: &ModifyOrderReq) -> Result<ModifyOrderRes> {
+        let order_id = &modify_order_req.order_id;
+
+        let updates = {
+            let mut state = self.lock();
+            let order = state.find(order_id)?;
+            apply_inner(order, &modify_order_req.inner);
+            let order = order.clone();
+
+            let index = state.orders.iter().position(|o| &o.order_id == order_id);
+            let ltp = state.price(&order.exchange, &order.symbol_token);
+            let mut updates = vec![(order, StatusEn::Modified)];
+            if let (Some(index), Some(ltp)) = (index, ltp) {
+                updates.extend(state.execute(index, ltp));
+            }
+            updates
+        };
+        self.emit(updates);
+
+        Ok(ModifyOrderRes {
+            order_id: order_id.clone(),
+            unique_order_id: Some(order_id.clone()),
+        })
+    }
+
+    async fn cancel_order(&self, cancel_order_req: &CancelOrderReq) -> Result<CancelOrderRes> {
+        let order_id = &cancel_order_req.order_id;
+
+        let order = {
+            let mut state = self.lock();
+            let order = state.find(order_id)?;
+            order.cancel_size = order.unfilled_shares;
+            order.status = OrderStatusKind::Cancelled;
+            order.order_status = OrderStatusKind::Cancelled;
+            order.update_time = Some(now());
+            order.clone()
+        };
+        self.emit(vec![(order, StatusEn::Cancelled)]);
+
+        Ok(CancelOrderRes {
+            order_id: order_id.clone(),
+            unique_order_id: Some(order_id.clone()),
+        })
+    }
+
+    async fn order_book(&self) -> Result<Vec<OrderBook>> {
+        Ok(self.lock().orders.clone())
+    }
+
+    async fn order_status(&self, unique_order_id: &str) -> Result<IndividualOrderStatus> {
+        self.lock()
+            .orders
+            .iter()
+            .find(|order| order.unique_order_id.as_deref() == Some(unique_order_id))
+            .map(|order| IndividualOrderStatus {
+                order: order.clone(),
+                unique_order_id: unique_order_id.to_string(),
+            })
+            .ok_or_else(|| Error::OrderNotFound(unique_order_id.to_string()))
+    }
+
+    async fn trade_book(&self) -> Result<Vec<TradeBook>> {
+        Ok(self.lock().trades.clone())
+    }
+}
+
+#[async_trait]
+impl PortfolioApi for PaperBroker {
+    /// The paper account holds no securities
+    async fn holdings(&self) -> Result<Vec<Holding>> {
+        Ok(vec![])
+    }
+
+    async fn all_holdings(&self) -> Result<AllHoldings> {
+        Ok(AllHoldings {
+            holdings: vec![],
+            total_holding: None,
+        })
+    }
+
+    async fn positions(&self) -> Result<Vec<Position>> {
+        Ok(self.lock().positions.clone())
+    }
+
+    /// Converts the whole position, partial conversions are not supported
+    async fn convert_position(&self, convert_position_req: &ConvertPositionReq) -> Result<()> {
+        let req = convert_position_req;
+        let mut state = self.lock();
+        let position = state
+            .positions
+            .iter_mut()
+            .find(|position| {
+                position.exchange == req.exchange
+                    && position.trading_symbol == req.trading_symbol
+                    && position.product_type == req.old_product_type
+                    && position.net_qty.unsigned_abs() == req.quantity as u64
+            })
+            .ok_or_else(|| Error::PositionNotFound(req.trading_symbol.clone()))?;
+
+        position.product_type = req.new_product_type.clone();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FundsApi for PaperBroker {
+    async fn rms_limit(&self) -> Result<Rms> {
+        let state = self.lock();
+        let (value, pnl) = state
+            .positions
+            .iter()
+            .fold((0.0, 0.0), |(value, pnl), position| {
+                let ltp = state
+                    .price(&position.exchange, &position.symbol_token)
+                    .unwrap_or(position.net_price);
+                (
+                    value + position.net_qty as f64 * ltp,
+                    pnl + position.pnl(ltp),
+                )
+            });
+
+        Ok(Rms {
+            net: state.cash + value,
+            available_cash: state.cash,
+            available_intra_day_pay_in: 0.0,
+            available_limit_margin: 0.0,
+            collateral: 0.0,
+            m2m_unrealized: pnl,
+            m2m_realized: 0.0,
+            utilized_debits: 0.0,
+            utilized_span: None,
+            utilized_option_premium: None,
+            utilized_holding_sales: None,
+            utilized_exposure: None,
+            utilized_turnover: None,
+            utilized_payout: None,
+        })
+    }
+
+    /// The paper account is a cash account, every position requires its full value
+    async fn calculate_margin(
+        &self,
+        positions: &[MarginCalculatorPosition],
+    ) -> Result<MarginCalculatorRes> {
+        let mut margin_breakup: Vec<MarginBreakup> = vec![];
+        for position in positions {
+            let value = position.quantity as f64 * position.price;
+            match margin_breakup.iter_mut().find(|breakup| {
+                breakup.exchange == position.exchange
+                    && breakup.product_type == position.product_type
+            }) {
+                Some(breakup) => breakup.total_margin_required += value,
+                None => margin_breakup.push(MarginBreakup {
+                    exchange: position.exchange.clone(),
+                    product_type: position.product_type.clone(),
+                    total_margin_required: value,
+                }),
+            }
+        }
+        let total = margin_breakup
+            .iter()
+            .map(|breakup| breakup.total_margin_required)
+            .sum();
+
+        Ok(MarginCalculatorRes {
+            total_margin_required: total,
+            margin_components: MarginComponents {
+                net_premium: 0.0,
+                span_margin: 0.0,
+                margin_benefit: 0.0,
+                delivery_margin: total,
+                non_nfo_margin: 0.0,
+                total_options_premium: 0.0,
+            },
+            margin_breakup,
+            options_buy: OptionsBuy {
+                total_options_premium: 0.0,
+                option_details: vec![],
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        broker::{FundsApi, OrderApi, PortfolioApi},
+        order::{CancelOrderReq, Order},
+        types::{ExchangeType::NSE, OrderStatusKind, OrderVariety},
+        ws::{AngelOneWsOrderStatusEn as StatusEn, SubscriptionExchange},
+    };
+
+    use super::PaperBroker;
+
+    #[tokio::test]
+    async fn paper_broker_fills_orders() {
+        let broker = PaperBroker::new(100_000.0);
+        let mut updates = broker.subscribe();
+
+        // market orders wait for a price
+        let buy = broker
+            .place_order(
+                &Order::market()
+                    .instrument(NSE, "SBIN-EQ", "3045")
+                    .buy(10)
+                    .into(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(updates.recv().await.unwrap().order_status, StatusEn::Open);
+        let fills = broker.update_price(SubscriptionExchange::NSECM, "3045", 500.0);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].order_status, StatusEn::Complete);
+        assert_eq!(Some(fills[0].order_data.order_id.clone()), buy.order_id);
+
+        let target = broker
+            .place_order(
+                &Order::limit(510.0)
+                    .instrument(NSE, "SBIN-EQ", "3045")
+                    .sell(10)
+                    .into(),
+            )
+            .await
+            .unwrap();
+        let stop = broker
+            .place_order(
+                &Order::stop_loss_market(495.0)
+                    .instrument(NSE, "SBIN-EQ", "3045")
+                    .sell(10)
+                    .into(),
+            )
+            .await
+            .unwrap();
+
+        // neither the target nor the stop is reached
+        assert!(broker
+            .update_price(SubscriptionExchange::NSECM, "3045", 505.0)
+            .is_empty());
+        let fills = broker.update_price(SubscriptionExchange::NSECM, "3045", 512.0);
+        assert_eq!(fills[0].order_data.order_id, target.order_id.unwrap());
+        assert_eq!(fills[0].order_data.average_price, 512.0);
+
+        let stop_id = stop.order_id.unwrap();
+        broker
+            .cancel_order(&CancelOrderReq::new(OrderVariety::StopLoss, &stop_id))
+            .await
+            .unwrap();
+        assert!(broker
+            .cancel_order(&CancelOrderReq::new(OrderVariety::StopLoss, &stop_id))
+            .await
+            .is_err());
+
+        let book = broker.order_book().await.unwrap();
+        assert_eq!(book[2].order_status, OrderStatusKind::Cancelled);
+        assert_eq!(broker.trade_book().await.unwrap().len(), 2);
+
+        let positions = broker.positions().await.unwrap();
+        assert_eq!(positions[0].net_qty, 0);
+        assert_eq!(positions[0].pnl(512.0), 120.0);
+        let rms = broker.rms_limit().await.unwrap();
+        assert_eq!(rms.available_cash, 100_120.0);
+        assert_eq!(rms.net, 100_120.0);
+    }
+
+    #[tokio::test]
+    async fn paper_broker_rejects_orders_beyond_the_cash() {
+        let broker = PaperBroker::new(4_000.0);
+
+        // the limit price is known at placement
+        broker
+            .place_order(
+                &Order::limit(500.0)
+                    .instrument(NSE, "SBIN-EQ", "3045")
+                    .buy(10)
+                    .into(),
+            )
+            .await
+            .unwrap();
+        // the market price only at fill time
+        broker
+            .place_order(
+                &Order::market()
+                    .instrument(NSE, "SBIN-EQ", "3045")
+                    .buy(10)
+                    .into(),
+            )
+            .await
+            .unwrap();
+        let book = broker.order_book().await.unwrap();
+        assert_eq!(book[0].order_status, OrderStatusKind::Rejected);
+        assert_eq!(book[1].order_status, OrderStatusKind::Open);
+
+        let updates = broker.update_price(SubscriptionExchange::NSECM, "3045", 450.0);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].order_status, StatusEn::Rejected);
+        assert!(broker.trade_book().await.unwrap().is_empty());
+        assert_eq!(broker.rms_limit().await.unwrap().available_cash, 4_000.0);
+    }
+}
@@ -0,0 +1,434 @@
+use async_trait::async_trait;
+
+use crate::{
+    funds::{MarginCalculatorPosition, MarginCalculatorRes, Rms},
+    gtt::{
+        CancelRuleReq, CancelRuleRes, CreateRuleReq, CreateRuleRes, ModifyRuleReq, ModifyRuleRes,
+        RuleDetailReq, RuleDetailRes, RuleListReq, RuleListRes,
+    },
+    market::{
+        BrokerageReq, BrokerageResp, CandleDataReq, CandleDataRes, IntradayScrip, LtpDataReq,
+        LtpDataRes, MarketDataReq, MarketDataRes, SearchScripRes,
+    },
+    order::{
+        CancelOrderReq, CancelOrderRes, IndividualOrderStatus, ModifyOrderReq, ModifyOrderRes,
+        OrderBook, PlaceOrderReq, PlaceOrderRes, TradeBook,
+    },
+    portfolio::{AllHoldings, ConvertPositionReq, Holding, Position},
+    types::ExchangeType,
+    user::Profile,
+    Result, SmartConnect,
+};
+
+/// Order placement and order book of a broker backend
+#[async_trait]
+pub trait OrderApi: Send + Sync {
+    /// Places the order
+    async fn place_order(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderRes>;
+
+    /// Modifies the working order
+    async fn modify_order(&self, modify_order_req: &ModifyOrderReq) -> Result<ModifyOrderRes>;
+
+    /// Cancels the working order
+    async fn cancel_order(&self, cancel_order_req: &CancelOrderReq) -> Result<CancelOrderRes>;
+
+    /// Returns the orders of the day
+    async fn order_book(&self) -> Result<Vec<OrderBook>>;
+
+    /// Returns the status of the order by its unique order id
+    async fn order_status(&self, unique_order_id: &str) -> Result<IndividualOrderStatus>;
+
+    /// Returns the trades of the day
+    async fn trade_book(&self) -> Result<Vec<TradeBook>>;
+}
+
+/// Holdings and positions of a broker backend
+#[async_trait]
+pub trait PortfolioApi: Send + Sync {
+    /// Returns the holdings
+    async fn holdings(&self) -> Result<Vec<Holding>>;
+
+    /// Returns the holdings with their totals
+    async fn all_holdings(&self) -> Result<AllHoldings>;
+
+    /// Returns the positions of the day
+    async fn positions(&self) -> Result<Vec<Position>>;
+
+    /// Converts the product type of the position
+    async fn convert_position(&self, convert_position_req: &ConvertPositionReq) -> Result<()>;
+}
+
+/// Profile of the user logged in to a broker backend
+#[async_trait]
+pub trait UserApi: Send + Sync {
+    /// Returns the profile of the user
+    async fn profile(&self) -> Result<Profile>;
+}
+
+/// Funds and margins of a broker backend
+#[async_trait]
+pub trait FundsApi: Send + Sync {
+    /// Returns the fund, cash and margin information
+    async fn rms_limit(&self) -> Result<Rms>;
+
+    /// Returns the margin required by the positions
+    async fn calculate_margin(
+        &self,
+        positions: &[MarginCalculatorPosition],
+    ) -> Result<MarginCalculatorRes>;
+}
+
+/// Quotes, candles and instrument search of a broker backend
+#[async_trait]
+pub trait MarketDataApi: Send + Sync {
+    /// Returns the last traded price of the instrument
+    async fn ltp_data(&self, ltp_data_req: &LtpDataReq) -> Result<LtpDataRes>;
+
+    /// Returns the quotes of the instruments
+    async fn market_data(&self, market_data_req: &MarketDataReq) -> Result<MarketDataRes>;
+
+    /// Returns the historical candles of the instrument
+    async fn candle_data(&self, candle_data_req: &CandleDataReq) -> Result<CandleDataRes>;
+
+    /// Searches the scrip on the exchange
+    async fn search_scrip(&self, exchange: ExchangeType, scrip: &str) -> Result<SearchScripRes>;
+
+    /// Returns the estimated charges of the orders
+    async fn brokerage(&self, brokerage_req: BrokerageReq) -> Result<BrokerageResp>;
+
+    /// Returns the scrips allowed for intraday trading on NSE
+    async fn nse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>>;
+
+    /// Returns the scrips allowed for intraday trading on BSE
+    async fn bse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>>;
+}
+
+/// GTT rules of a broker backend
+#[async_trait]
+pub trait GttApi: Send + Sync {
+    /// Creates the rule
+    async fn create_rule(&self, create_rule_req: &CreateRuleReq) -> Result<CreateRuleRes>;
+
+    /// Modifies the rule
+    async fn modify_rule(&self, modify_rule_req: &ModifyRuleReq) -> Result<ModifyRuleRes>;
+
+    /// Cancels the rule
+    async fn cancel_rule(&self, cancel_rule_req: &CancelRuleReq) -> Result<CancelRuleRes>;
+
+    /// Returns the details of the rule
+    async fn rule_detail(&self, rule_detail_req: &RuleDetailReq) -> Result<RuleDetailRes>;
+
+    /// Returns the rules with the requested statuses
+    async fn rule_list(&self, rule_list_req: &RuleListReq) -> Result<Vec<RuleListRes>>;
+}
+
+/// Trading surface implemented by [`SmartConnect`] and the
+/// [`PaperBroker`](super::PaperBroker), strategies written against it run on either
+pub trait Broker: OrderApi + PortfolioApi + FundsApi {}
+
+impl<B> Broker for B where B: OrderApi + PortfolioApi + FundsApi {}
+
+#[async_trait]
+impl OrderApi for SmartConnect {
+    async fn place_order(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderRes> {
+        SmartConnect::place_order(self, order_req).await
+    }
+
+    async fn modify_order(&self, modify_order_req: &ModifyOrderReq) -> Result<ModifyOrderRes> {
+        SmartConnect::modify_order(self, modify_order_req).await
+    }
+
+    async fn cancel_order(&self, cancel_order_req: &CancelOrderReq) -> Result<CancelOrderRes> {
+        SmartConnect::cancel_order(self, cancel_order_req).await
+    }
+
+    async fn order_book(&self) -> Result<Vec<OrderBook>> {
+        SmartConnect::order_book(self).await
+    }
+
+    async fn order_status(&self, unique_order_id: &str) -> Result<IndividualOrderStatus> {
+        SmartConnect::order_status(self, unique_order_id).await
+    }
+
+    async fn trade_book(&self) -> Result<Vec<TradeBook>> {
+        SmartConnect::trade_book(self).await
+    }
+}
+
+#[async_trait]
+impl PortfolioApi for SmartConnect {
+    async fn holdings(&self) -> Result<Vec<Holding>> {
+        SmartConnect::holdings(self).await
+    }
+
+    async fn all_holdings(&self) -> Result<AllHoldings> {
+        SmartConnect::all_holdings(self).await
+    }
+
+    async fn positions(&self) -> Result<Vec<Position>> {
+        SmartConnect::positions(self).await
+    }
+
+    async fn convert_position(&self, convert_position_req: &ConvertPositionReq) -> Result<()> {
+        SmartConnect::convert_position(self, convert_position_req).await
+    }
+}
+
+#[async_trait]
+impl UserApi for SmartConnect {
+    async fn profile(&self) -> Result<Profile> {
+        SmartConnect::profile(self).await
+    }
+}
+
+#[async_trait]
+impl FundsApi for SmartConnect {
+    async fn rms_limit(&self) -> Result<Rms> {
+        SmartConnect::rms_limit(self).await
+    }
+
+    async fn calculate_margin(
+        &self,
+        positions: &[MarginCalculatorPosition],
+    ) -> Result<MarginCalculatorRes> {
+        SmartConnect::calculate_margin(self, positions).await
+    }
+}
+
+#[async_trait]
+impl MarketDataApi for SmartConnect {
+    async fn ltp_data(&self, ltp_data_req: &LtpDataReq) -> Result<LtpDataRes> {
+        SmartConnect::ltp_data(self, ltp_data_req).await
+    }
+
+    async fn market_data(&self, market_data_req: &MarketDataReq) -> Result<MarketDataRes> {
+        SmartConnect::market_data(self, market_data_req).await
+    }
+
+    async fn candle_data(&self, candle_data_req: &CandleDataReq) -> Result<CandleDataRes> {
+        SmartConnect::candle_data(self, candle_data_req).await
+    }
+
+    async fn search_scrip(&self, exchange: ExchangeType, scrip: &str) -> Result<SearchScripRes> {
+        SmartConnect::search_scrip(self, exchange, scrip).await
+    }
+
+    async fn brokerage(&self, brokerage_req: BrokerageReq) -> Result<BrokerageResp> {
+        SmartConnect::brokerage(self, brokerage_req).await
+    }
+
+    async fn nse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
+        SmartConnect::nse_intraday_scrips(self).await
+    }
+
+    async fn bse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
+        SmartConnect::bse_intraday_scrips(self).await
+    }
+}
+
+#[async_trait]
+impl GttApi for SmartConnect {
+    async fn create_rule(&self, create_rule_req: &CreateRuleReq) -> Result<CreateRuleRes> {
+        SmartConnect::create_rule(self, create_rule_req).await
+    }
+
+    async fn modify_rule(&self, modify_rule_req: &ModifyRuleReq) -> Result<ModifyRuleRes> {
+        SmartConnect::modify_rule(self, modify_rule_req).await
+    }
+
+    async fn cancel_rule(&self, cancel_rule_req: &CancelRuleReq) -> Result<CancelRuleRes> {
+        SmartConnect::cancel_rule(self, cancel_rule_req).await
+    }
+
+    async fn rule_detail(&self, rule_detail_req: &RuleDetailReq) -> Result<RuleDetailRes> {
+        SmartConnect::rule_detail(self, rule_detail_req).await
+    }
+
+    async fn rule_list(&self, rule_list_req: &RuleListReq) -> Result<Vec<RuleListRes>> {
+        SmartConnect::rule_list(self, rule_list_req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use crate::{
+        broker::{Broker, GttApi, MarketDataApi, PaperBroker},
+        gtt::{
+            CancelRuleReq, CancelRuleRes, CreateRuleReq, CreateRuleRes, ModifyRuleReq,
+            ModifyRuleRes, RuleDetailReq, RuleDetailRes, RuleListReq, RuleListRes,
+        },
+        market::{
+            BrokerageReq, BrokerageResp, CandleDataReq, CandleDataRes, IntradayScrip, LtpDataReq,
+            LtpDataRes, MarketDataReq, MarketDataRes, SearchScripRes,
+        },
+        order::Order,
+        types::{ExchangeType, ProductType, RuleType, TransactionType},
+        ws::SubscriptionExchange,
+        Error, Result,
+    };
+
+    // Strategy written against the trait objects, unaware of the backend
+    async fn buy_dip(broker: &dyn Broker, ltp: f64) -> Result<i64> {
+        if broker.rms_limit().await?.available_cash >= ltp * 10.0 {
+            let order = Order::market()
+                .instrument(ExchangeType::NSE, "INFY-EQ", "1594")
+                .buy(10);
+            broker.place_order(&order.into()).await?;
+        }
+        Ok(broker.positions().await?.iter().map(|p| p.net_qty).sum())
+    }
+
+    #[tokio::test]
+    async fn strategy_runs_on_a_dyn_broker() {
+        let paper = PaperBroker::new(10_000.0);
+        paper.update_price(SubscriptionExchange::NSECM, "1594", 900.0);
+
+        assert_eq!(buy_dip(&paper, 900.0).await.unwrap(), 10);
+        // the cash left no longer covers the order
+        assert_eq!(buy_dip(&paper, 900.0).await.unwrap(), 10);
+    }
+
+    // Quotes at a fixed price and rules kept in memory as symbol, quantity and trigger
+    #[derive(Default)]
+    struct Stub {
+        rules: Mutex<Vec<(String, u64, f64)>>,
+    }
+
+    #[async_trait]
+    impl MarketDataApi for Stub {
+        async fn ltp_data(&self, ltp_data_req: &LtpDataReq) -> Result<LtpDataRes> {
+            Ok(LtpDataRes {
+                exchange: ltp_data_req.exchange.clone(),
+                trading_symbol: ltp_data_req.trading_symbol.clone(),
+                symbol_token: ltp_data_req.symbol_token.clone(),
+                open: 1_500.0,
+                high: 1_520.0,
+                low: 1_490.0,
+                close: 1_495.0,
+                ltp: 1_500.0,
+            })
+        }
+
+        async fn market_data(&self, _: &MarketDataReq) -> Result<MarketDataRes> {
+            Ok(MarketDataRes {
+                fetched: vec![],
+                unfetched: vec![],
+            })
+        }
+
+        async fn candle_data(&self, _: &CandleDataReq) -> Result<CandleDataRes> {
+            Ok(CandleDataRes(vec![]))
+        }
+
+        async fn search_scrip(&self, _: ExchangeType, _: &str) -> Result<SearchScripRes> {
+            Ok(SearchScripRes(vec![]))
+        }
+
+        async fn brokerage(&self, _: BrokerageReq) -> Result<BrokerageResp> {
+            let summary = json!({ "total_charges": 0.0, "trade_value": 0.0, "breakup": [] });
+            Ok(serde_json::from_value(
+                json!({ "summary": summary, "charges": [] }),
+            )?)
+        }
+
+        async fn nse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
+            Ok(vec![])
+        }
+
+        async fn bse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl GttApi for Stub {
+        async fn create_rule(&self, create_rule_req: &CreateRuleReq) -> Result<CreateRuleRes> {
+            let mut rules = self.rules.lock().unwrap();
+            rules.push((
+                create_rule_req.trading_symbol.clone(),
+                create_rule_req.qty.parse().unwrap(),
+                create_rule_req.trigger_price.parse().unwrap(),
+            ));
+            Ok(CreateRuleRes {
+                id: rules.len() as u64,
+            })
+        }
+
+        async fn modify_rule(&self, modify_rule_req: &ModifyRuleReq) -> Result<ModifyRuleRes> {
+            Err(Error::OrderNotFound(modify_rule_req.id.to_string()))
+        }
+
+        async fn cancel_rule(&self, cancel_rule_req: &CancelRuleReq) -> Result<CancelRuleRes> {
+            Err(Error::OrderNotFound(cancel_rule_req.id.to_string()))
+        }
+
+        async fn rule_detail(&self, rule_detail_req: &RuleDetailReq) -> Result<RuleDetailRes> {
+            Err(Error::OrderNotFound(rule_detail_req.id.to_string()))
+        }
+
+        async fn rule_list(&self, _: &RuleListReq) -> Result<Vec<RuleListRes>> {
+            let rules = self.rules.lock().unwrap();
+            Ok(rules
+                .iter()
+                .enumerate()
+                .map(|(i, (trading_symbol, qty, trigger_price))| RuleListRes {
+                    client_id: String::from("A123456"),
+                    created_date: String::new(),
+                    exchange: ExchangeType::NSE,
+                    product_type: ProductType::Delivery,
+                    transaction_type: TransactionType::Sell,
+                    expiry_date: String::new(),
+                    id: (i + 1).to_string(),
+                    qty: *qty,
+                    price: *trigger_price,
+                    status: String::from("NEW"),
+                    symbol_token: String::new(),
+                    trading_symbol: trading_symbol.clone(),
+                    trigger_price: *trigger_price,
+                    updated_date: String::new(),
+                })
+                .collect())
+        }
+    }
+
+    // Strategy protecting a holding with a GTT stop 5% below the last price
+    async fn protect(market: &dyn MarketDataApi, gtt: &dyn GttApi, qty: u64) -> Result<u64> {
+        let ltp = market
+            .ltp_data(&LtpDataReq::new(ExchangeType::NSE, "INFY-EQ", "1594"))
+            .await?
+            .ltp;
+        let stop = (ltp * 0.95).to_string();
+        let rule = CreateRuleReq::new("INFY-EQ", "1594")
+            .exchange(ExchangeType::NSE)
+            .transaction_type(TransactionType::Sell)
+            .product_type(ProductType::Delivery)
+            .price(&stop)
+            .trigger_price(&stop)
+            .qty(qty);
+        Ok(gtt.create_rule(&rule).await?.id)
+    }
+
+    #[tokio::test]
+    async fn strategy_runs_on_mocked_market_data_and_gtt() {
+        let stub = Stub::default();
+
+        assert_eq!(protect(&stub, &stub, 10).await.unwrap(), 1);
+        let rules = stub
+            .rule_list(&RuleListReq {
+                status: vec![RuleType::New],
+                page: 1,
+                count: 10,
+            })
+            .await
+            .unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].trigger_price, 1_425.0);
+        assert_eq!(rules[0].qty, 10);
+        assert!(stub.nse_intraday_scrips().await.unwrap().is_empty());
+    }
+}
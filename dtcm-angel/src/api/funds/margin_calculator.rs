@@ -1,4 +1,7 @@
-use crate::types::{ExchangeType, ProductType, TransactionType};
+use crate::{
+    order::{OrderBook, PlaceOrderReq},
+    types::{ExchangeType, OrderType, ProductType, TransactionType},
+};
 
 /// Margin calculation request
 #[api(POST, MarginApi)]
@@ -55,7 +58,8 @@ pub struct MarginCalculatorRes {
     pub options_buy: OptionsBuy,
 }
 
-/// MarginComponents
+/// Margin components
+#[allow(missing_docs)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct MarginComponents {
     #[serde(rename = "netPremium")]
@@ -72,6 +76,8 @@ pub struct MarginComponents {
     pub total_options_premium: f64,
 }
 
+/// Margin required per exchange and product type
+#[allow(missing_docs)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct MarginBreakup {
     pub exchange: ExchangeType,
@@ -81,6 +87,8 @@ pub struct MarginBreakup {
     pub total_margin_required: f64,
 }
 
+/// Premium of the bought options
+#[allow(missing_docs)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct OptionsBuy {
     #[serde(rename = "totOptionsPremium")]
@@ -89,6 +97,8 @@ pub struct OptionsBuy {
     pub option_details: Vec<OptionDetail>,
 }
 
+/// Premium of a bought option
+#[allow(missing_docs)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct OptionDetail {
     pub exchange: ExchangeType,
@@ -125,6 +135,45 @@ impl MarginCalculatorPosition {
     }
 }
 
+impl From<&OrderBook> for MarginCalculatorPosition {
+    /// Returns the position of the unfilled quantity of the order
+    fn from(order: &OrderBook) -> Self {
+        Self::new(
+            order.exchange.clone(),
+            order.product_type.clone(),
+            order.transaction_type.clone(),
+            order.symbol_token.as_str(),
+            order.price,
+            order.quantity.saturating_sub(order.filled_shares) as usize,
+        )
+    }
+}
+
+impl From<&PlaceOrderReq> for MarginCalculatorPosition {
+    /// Returns the position of the order, market orders are priced at zero and valued by
+    /// the broker at the LTP
+    fn from(order_req: &PlaceOrderReq) -> Self {
+        let inner = &order_req.inner;
+        let price = match inner.order_type {
+            OrderType::Market => 0.0,
+            OrderType::StopLossMarket => inner
+                .trigger_price
+                .as_deref()
+                .and_then(|price| price.trim().parse().ok())
+                .unwrap_or_default(),
+            _ => inner.price.trim().parse().unwrap_or_default(),
+        };
+        Self::new(
+            inner.exchange.clone(),
+            inner.product_type.clone(),
+            order_req.transaction_type.clone(),
+            inner.symbol_token.as_str(),
+            price,
+            inner.quantity.trim().parse().unwrap_or_default(),
+        )
+    }
+}
+
 impl MarginCalculatorReq {
     /// Returns a new instance for [`MarginCalculatorReq`]
     pub fn new() -> Self {
@@ -2,4 +2,10 @@ mod rms;
 pub use rms::Rms;
 
 mod margin_calculator;
-pub use margin_calculator::{MarginCalculatorPosition, MarginCalculatorReq, MarginCalculatorRes};
+pub use margin_calculator::{
+    MarginBreakup, MarginCalculatorPosition, MarginCalculatorReq, MarginCalculatorRes,
+    MarginComponents, OptionDetail, OptionsBuy,
+};
+
+mod monitor;
+pub use monitor::{Funds, FundsEvent, FundsMonitor, FundsMonitorHandle};
@@ -0,0 +1,310 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{debug, warn};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{broker::Broker, types::OrderStatusKind, Result};
+
+use super::{MarginCalculatorPosition, Rms};
+
+// Default interval between the polls of the RMS limits
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Funds and margin utilization derived from the [`Rms`] limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Funds {
+    /// Margin available for new orders
+    pub net: f64,
+    /// Cash available
+    pub available_cash: f64,
+    /// Margin from the pledged collateral
+    pub collateral: f64,
+    /// Margin used by the positions and the open orders
+    pub utilized: f64,
+    /// Realized and unrealized mark to market profit
+    pub m2m: f64,
+}
+
+impl From<&Rms> for Funds {
+    fn from(rms: &Rms) -> Self {
+        Self {
+            net: rms.net,
+            available_cash: rms.available_cash,
+            collateral: rms.collateral,
+            utilized: rms.utilized_debits,
+            m2m: rms.m2m_realized + rms.m2m_unrealized,
+        }
+    }
+}
+
+impl Funds {
+    /// Returns the total margin, used and available
+    pub fn total_margin(&self) -> f64 {
+        self.net + self.utilized
+    }
+
+    /// Returns the share of the total margin in use, between 0 and 1 unless the account
+    /// is in deficit
+    pub fn utilization(&self) -> f64 {
+        match self.total_margin() {
+            total if total > 0.0 => self.utilized / total,
+            _ if self.utilized > 0.0 => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Event emitted by the [`FundsMonitor`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum FundsEvent {
+    /// Funds polled
+    Updated(Funds),
+    /// Margin utilization rose to or above the threshold
+    UtilizationAbove {
+        /// Threshold crossed
+        threshold: f64,
+        /// Current utilization
+        utilization: f64,
+    },
+    /// Margin utilization fell back below the threshold
+    UtilizationBelow {
+        /// Threshold crossed
+        threshold: f64,
+        /// Current utilization
+        utilization: f64,
+    },
+    /// Available cash dropped below the margin required by the pending orders
+    CashShortfall {
+        /// Margin required by the pending orders
+        required: f64,
+        /// Cash available
+        available: f64,
+    },
+    /// Available cash covers the pending orders again
+    CashRestored,
+    /// Polling failed, retried on the next interval
+    Error(String),
+}
+
+/// Background monitor polling the RMS limits on an interval and alerting on the margin
+/// utilization thresholds and on the cash needed by the pending orders
+#[derive(Clone)]
+pub struct FundsMonitor {
+    broker: Arc<dyn Broker>,
+    interval: Duration,
+    thresholds: Vec<f64>,
+    pending_orders: bool,
+}
+
+impl FundsMonitor {
+    /// Returns a new monitor for the broker
+    pub fn new(broker: Arc<dyn Broker>) -> Self {
+        Self {
+            broker,
+            interval: DEFAULT_POLL_INTERVAL,
+            thresholds: vec![],
+            pending_orders: false,
+        }
+    }
+
+    /// Sets the interval between the polls
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Adds a utilization threshold, e.g. 0.8 for 80% of the margin in use
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.thresholds.push(threshold);
+        self
+    }
+
+    /// Checks the available cash against the margin of the pending orders, priced by the
+    /// margin calculator on every poll
+    pub fn pending_orders(mut self, pending_orders: bool) -> Self {
+        self.pending_orders = pending_orders;
+        self
+    }
+
+    /// Starts polling in the background and returns the handle streaming the events
+    pub fn start(self) -> FundsMonitorHandle {
+        let (sender, events) = mpsc::unbounded_channel();
+        let task = tokio::spawn(self.run(sender));
+        FundsMonitorHandle { events, task }
+    }
+
+    async fn run(self, events: mpsc::UnboundedSender<FundsEvent>) {
+        let mut alerts = Alerts::new(self.thresholds.clone());
+        let mut timer = tokio::time::interval(self.interval);
+
+        while !events.is_closed() {
+            timer.tick().await;
+            let polled = match self.poll().await {
+                Ok((funds, required)) => alerts.update(funds, required),
+                Err(e) => {
+                    warn!("Failed to poll the funds: {e}");
+                    vec![FundsEvent::Error(e.to_string())]
+                }
+            };
+            for event in polled {
+                // the receiver is gone once the handle is dropped, the loop then stops
+                let _ = events.send(event);
+            }
+        }
+        debug!("Funds monitor stopped");
+    }
+
+    /// Returns the funds with the margin required by the pending orders
+    async fn poll(&self) -> Result<(Funds, Option<f64>)> {
+        let funds = Funds::from(&self.broker.rms_limit().await?);
+        if !self.pending_orders {
+            return Ok((funds, None));
+        }
+
+        let positions: Vec<MarginCalculatorPosition> = self
+            .broker
+            .order_book()
+            .await?
+            .iter()
+            .filter(|order| {
+                !order.order_status.is_terminal()
+                    && !matches!(order.order_status, OrderStatusKind::Unknown(_))
+                    && order.quantity > order.filled_shares
+            })
+            .map(Into::into)
+            .collect();
+        if positions.is_empty() {
+            return Ok((funds, Some(0.0)));
+        }
+
+        let margin = self.broker.calculate_margin(&positions).await?;
+        Ok((funds, Some(margin.total_margin_required)))
+    }
+}
+
+/// Handle to a running [`FundsMonitor`], dropping it stops the monitor
+#[derive(Debug)]
+pub struct FundsMonitorHandle {
+    events: mpsc::UnboundedReceiver<FundsEvent>,
+    task: JoinHandle<()>,
+}
+
+impl FundsMonitorHandle {
+    /// Returns the next event, none once the monitor has stopped
+    pub async fn next_event(&mut self) -> Option<FundsEvent> {
+        self.events.recv().await
+    }
+
+    /// Stops polling
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for FundsMonitorHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Alert state carried between the polls
+#[derive(Debug)]
+struct Alerts {
+    thresholds: Vec<f64>,
+    utilization: f64,
+    shortfall: bool,
+}
+
+impl Alerts {
+    fn new(thresholds: Vec<f64>) -> Self {
+        Self {
+            thresholds,
+            utilization: 0.0,
+            shortfall: false,
+        }
+    }
+
+    /// Returns the events of the poll, crossings are reported once per direction
+    fn update(&mut self, funds: Funds, required: Option<f64>) -> Vec<FundsEvent> {
+        let mut events = vec![FundsEvent::Updated(funds)];
+
+        let utilization = funds.utilization();
+        for &threshold in &self.thresholds {
+            if self.utilization < threshold && utilization >= threshold {
+                events.push(FundsEvent::UtilizationAbove {
+                    threshold,
+                    utilization,
+                });
+            } else if self.utilization >= threshold && utilization < threshold {
+                events.push(FundsEvent::UtilizationBelow {
+                    threshold,
+                    utilization,
+                });
+            }
+        }
+        self.utilization = utilization;
+
+        if let Some(required) = required {
+            let shortfall = funds.available_cash < required;
+            if shortfall && !self.shortfall {
+                events.push(FundsEvent::CashShortfall {
+                    required,
+                    available: funds.available_cash,
+                });
+            } else if !shortfall && self.shortfall {
+                events.push(FundsEvent::CashRestored);
+            }
+            self.shortfall = shortfall;
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alerts, Funds, FundsEvent};
+
+    fn funds(net: f64, utilized: f64) -> Funds {
+        Funds {
+            net,
+            available_cash: net,
+            utilized,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn utilization_and_shortfall_alerts() {
+        let mut alerts = Alerts::new(vec![0.5, 0.9]);
+
+        let events = alerts.update(funds(40_000.0, 60_000.0), Some(10_000.0));
+        assert_eq!(
+            events[1],
+            FundsEvent::UtilizationAbove {
+                threshold: 0.5,
+                utilization: 0.6
+            }
+        );
+        assert_eq!(events.len(), 2);
+
+        // unchanged side of both thresholds
+        assert_eq!(alerts.update(funds(30_000.0, 70_000.0), None).len(), 1);
+
+        let events = alerts.update(funds(5_000.0, 95_000.0), Some(10_000.0));
+        assert!(matches!(
+            events[1],
+            FundsEvent::UtilizationAbove { threshold: 0.9, .. }
+        ));
+        assert!(matches!(
+            events[2],
+            FundsEvent::CashShortfall {
+                required: 10_000.0,
+                available: 5_000.0
+            }
+        ));
+
+        let events = alerts.update(funds(80_000.0, 20_000.0), Some(10_000.0));
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[3], FundsEvent::CashRestored);
+    }
+}
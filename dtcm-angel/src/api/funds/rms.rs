@@ -1,32 +1,60 @@
+use dtcm_angel_utils::num::{serde_number, serde_opt_number};
+
 /// Risk Management System returns fund, cash and margin information of the user for equity and commodity segments
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[api(GET, RmsLimit)]
 pub struct Rms {
-    pub net: String,
-    #[serde(rename = "availablecash")]
-    pub available_cash: String,
-    #[serde(rename = "availableintradaypayin")]
-    pub available_intra_day_pay_in: String,
-    #[serde(rename = "availablelimitmargin")]
-    pub available_limit_margin: String,
-    pub collateral: String,
-    #[serde(rename = "m2munrealized")]
-    pub m2m_unrealized: String,
-    #[serde(rename = "m2mrealized")]
-    pub m2m_realized: String,
-    #[serde(rename = "utiliseddebits")]
-    pub utilized_debits: String,
-    #[serde(rename = "utilisedspan")]
-    pub utilized_span: Option<String>,
-    #[serde(rename = "utilisedoptionpremium")]
-    pub utilized_option_premium: Option<String>,
-    #[serde(rename = "utilisedholdingsales")]
-    pub utilized_holding_sales: Option<String>,
-    #[serde(rename = "utilisedexposure")]
-    pub utilized_exposure: Option<String>,
-    #[serde(rename = "utilisedturnover")]
-    pub utilized_turnover: Option<String>,
-    #[serde(rename = "utilisedpayout")]
-    pub utilized_payout: Option<String>,
+    #[serde(deserialize_with = "serde_number")]
+    pub net: f64,
+    #[serde(rename = "availablecash", deserialize_with = "serde_number")]
+    pub available_cash: f64,
+    #[serde(rename = "availableintradaypayin", deserialize_with = "serde_number")]
+    pub available_intra_day_pay_in: f64,
+    #[serde(rename = "availablelimitmargin", deserialize_with = "serde_number")]
+    pub available_limit_margin: f64,
+    #[serde(deserialize_with = "serde_number")]
+    pub collateral: f64,
+    #[serde(rename = "m2munrealized", deserialize_with = "serde_number")]
+    pub m2m_unrealized: f64,
+    #[serde(rename = "m2mrealized", deserialize_with = "serde_number")]
+    pub m2m_realized: f64,
+    #[serde(rename = "utiliseddebits", deserialize_with = "serde_number")]
+    pub utilized_debits: f64,
+    #[serde(
+        rename = "utilisedspan",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_span: Option<f64>,
+    #[serde(
+        rename = "utilisedoptionpremium",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_option_premium: Option<f64>,
+    #[serde(
+        rename = "utilisedholdingsales",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_holding_sales: Option<f64>,
+    #[serde(
+        rename = "utilisedexposure",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_exposure: Option<f64>,
+    #[serde(
+        rename = "utilisedturnover",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_turnover: Option<f64>,
+    #[serde(
+        rename = "utilisedpayout",
+        default,
+        deserialize_with = "serde_opt_number"
+    )]
+    pub utilized_payout: Option<f64>,
 }
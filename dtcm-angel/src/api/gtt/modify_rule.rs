@@ -32,7 +32,7 @@ pub struct ModifyRuleReq {
 #[derive(Debug, Deserialize)]
 pub struct ModifyRuleRes {
     /// Order ID
-    #[serde(rename = "orderid")]
+    #[serde(rename = "orderid", alias = "id")]
     pub order_id: String,
 }
 
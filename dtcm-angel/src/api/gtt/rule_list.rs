@@ -1,3 +1,5 @@
+use dtcm_angel_utils::num::serde_number;
+
 use crate::types::{ExchangeType, ProductType, RuleType, TransactionType};
 
 /// Rule list request
@@ -12,7 +14,7 @@ pub struct RuleListReq {
     pub count: usize,
 }
 
-/// Rule list response
+/// Rule of the rule list response
 #[derive(Debug, Deserialize)]
 pub struct RuleListRes {
     /// Client ID
@@ -36,9 +38,11 @@ pub struct RuleListRes {
     /// ID
     pub id: String,
     /// Quantity
-    pub qty: String,
+    #[serde(deserialize_with = "serde_number")]
+    pub qty: u64,
     /// Price
-    pub price: String,
+    #[serde(deserialize_with = "serde_number")]
+    pub price: f64,
     /// Status
     pub status: String,
     /// Symbol token
@@ -47,9 +51,9 @@ pub struct RuleListRes {
     /// Trading symbol
     #[serde(rename = "tradingsymbol")]
     pub trading_symbol: String,
-    ///Trigger price
-    #[serde(rename = "triggerprice")]
-    pub trigger_price: String,
+    /// Trigger price
+    #[serde(rename = "triggerprice", deserialize_with = "serde_number")]
+    pub trigger_price: f64,
     /// Updated date
     #[serde(rename = "updateddate")]
     pub updated_date: String,
@@ -1,10 +1,12 @@
+use std::collections::HashMap;
+
 use chrono::NaiveDate;
 use dtcm_angel_utils::date::serde_ddMMyyyy;
 
 use crate::types::ExchangeType;
 
 /// Instrument record received from Angel One JSON URL
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Instrument {
     /// Token
     pub token: String,
@@ -28,3 +30,70 @@ pub struct Instrument {
     /// Tick size
     pub tick_size: String,
 }
+
+impl Instrument {
+    /// Returns the lot size, the order quantity must be a multiple of it
+    pub fn lot_size_value(&self) -> Option<u64> {
+        self.lot_size
+            .trim()
+            .parse()
+            .ok()
+            .filter(|lot_size| *lot_size > 0)
+    }
+
+    /// Returns the tick size in rupees, the instrument master reports it in paise
+    pub fn tick_size_value(&self) -> Option<f64> {
+        self.tick_size
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|tick_size| *tick_size > 0.0)
+            .map(|tick_size| tick_size / 100.0)
+    }
+}
+
+/// Instrument master indexed by exchange and symbol token
+#[derive(Debug, Default)]
+pub struct InstrumentMaster {
+    instruments: HashMap<(ExchangeType, String), Instrument>,
+}
+
+impl InstrumentMaster {
+    /// Returns a new instance for [`InstrumentMaster`]
+    pub fn new(instruments: Vec<Instrument>) -> Self {
+        instruments.into_iter().collect()
+    }
+
+    /// Returns the instrument for the exchange and symbol token
+    pub fn get(&self, exchange: &ExchangeType, token: &str) -> Option<&Instrument> {
+        self.instruments.get(&(exchange.clone(), token.to_string()))
+    }
+
+    /// Returns the instrument for the exchange and trading symbol
+    pub fn find_symbol(&self, exchange: &ExchangeType, symbol: &str) -> Option<&Instrument> {
+        self.instruments
+            .values()
+            .find(|i| &i.exch_seg == exchange && i.symbol == symbol)
+    }
+
+    /// Returns the number of instruments
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    /// Returns true if the master holds no instruments
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+}
+
+impl FromIterator<Instrument> for InstrumentMaster {
+    fn from_iter<I: IntoIterator<Item = Instrument>>(iter: I) -> Self {
+        Self {
+            instruments: iter
+                .into_iter()
+                .map(|i| ((i.exch_seg.clone(), i.token.clone()), i))
+                .collect(),
+        }
+    }
+}
@@ -11,7 +11,7 @@ mod candle_data;
 pub use candle_data::{CandleDataReq, CandleDataRes};
 
 mod instrument;
-pub use instrument::Instrument;
+pub use instrument::{Instrument, InstrumentMaster};
 
 mod search_scrip;
 pub use search_scrip::{SearchScripReq, SearchScripRes};
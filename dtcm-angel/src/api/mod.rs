@@ -1,3 +1,7 @@
+/// Execution algorithms
+pub mod algo;
+/// Broker abstraction and paper trading
+pub mod broker;
 /// Funds API
 pub mod funds;
 /// GTT API
@@ -8,6 +12,10 @@ pub mod market;
 pub mod order;
 /// Portfolio API
 pub mod portfolio;
+/// End of day reporting
+pub mod report;
+/// Risk controls
+pub mod risk;
 /// User API
 pub mod user;
 /// WebSocket
@@ -0,0 +1,374 @@
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use log::{info, warn};
+
+use crate::{broker::OrderApi, types::OrderVariety, Result, SmartConnect};
+
+use super::{
+    CancelOrderReq, CancelOrderRes, ModifyOrderReq, ModifyOrderRes, OrderState, PlaceOrderReq,
+    PlaceOrderRes, RateLimiter,
+};
+
+// Default number of orders sent per second, half of the broker limit
+const DEFAULT_ORDERS_PER_SECOND: u32 = 10;
+
+// Default time given to the placed legs to leave the pending states
+const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Interval between the order book polls confirming the placed legs
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Group of orders placed together, e.g. the legs of an option strategy
+#[derive(Debug, Clone)]
+pub struct Basket {
+    /// Orders of the basket
+    pub legs: Vec<PlaceOrderReq>,
+    /// Cancels the placed legs if any leg fails
+    pub all_or_nothing: bool,
+    /// Number of orders sent per second
+    pub orders_per_second: u32,
+    /// Time the order book is polled for the placed legs to be accepted or rejected
+    pub confirm_timeout: Duration,
+}
+
+impl Default for Basket {
+    fn default() -> Self {
+        Self {
+            legs: vec![],
+            all_or_nothing: false,
+            orders_per_second: DEFAULT_ORDERS_PER_SECOND,
+            confirm_timeout: DEFAULT_CONFIRM_TIMEOUT,
+        }
+    }
+}
+
+impl Basket {
+    /// Returns a new instance for [`Basket`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the order to the basket
+    pub fn leg(mut self, order: PlaceOrderReq) -> Self {
+        self.legs.push(order);
+        self
+    }
+
+    /// Sets the all-or-nothing mode, the placed legs are cancelled if any leg fails
+    pub fn all_or_nothing(mut self, all_or_nothing: bool) -> Self {
+        self.all_or_nothing = all_or_nothing;
+        self
+    }
+
+    /// Sets the number of orders sent per second
+    pub fn orders_per_second(mut self, orders_per_second: u32) -> Self {
+        self.orders_per_second = orders_per_second;
+        self
+    }
+
+    /// Sets the time the order book is polled for the placed legs to leave the pending states
+    pub fn confirm_timeout(mut self, confirm_timeout: Duration) -> Self {
+        self.confirm_timeout = confirm_timeout;
+        self
+    }
+
+    /// Places the legs concurrently within the rate limit and confirms their state in the
+    /// order book. In all-or-nothing mode the live legs are cancelled if any leg fails to be
+    /// placed or is rejected by the broker.
+    pub async fn place(&self, broker: &dyn OrderApi) -> PlacedBasket {
+        let limiter = RateLimiter::new(self.orders_per_second);
+
+        let results = join_all(self.legs.iter().map(|order| async {
+            limiter.acquire().await;
+            broker.place_order(order).await
+        }))
+        .await;
+
+        let legs = self
+            .legs
+            .iter()
+            .zip(results)
+            .map(|(order, result)| BasketLeg {
+                variety: order.inner.variety.clone(),
+                result,
+                state: None,
+                cancellation: None,
+            })
+            .collect();
+
+        let mut placed = PlacedBasket {
+            legs,
+            rolled_back: false,
+            orders_per_second: self.orders_per_second,
+        };
+        placed.confirm(broker, self.confirm_timeout).await;
+
+        if self.all_or_nothing && !placed.is_complete() {
+            warn!(
+                "Basket leg failed, rolling back {} placed legs",
+                placed.order_ids().len()
+            );
+            placed.cancel_all(broker).await;
+            placed.rolled_back = true;
+        }
+
+        placed
+    }
+}
+
+impl From<Vec<PlaceOrderReq>> for Basket {
+    fn from(legs: Vec<PlaceOrderReq>) -> Self {
+        Self {
+            legs,
+            ..Default::default()
+        }
+    }
+}
+
+/// Outcome of a single basket leg
+#[derive(Debug)]
+pub struct BasketLeg {
+    /// Variety of the order, needed to cancel it
+    pub variety: OrderVariety,
+    /// Placement result
+    pub result: Result<PlaceOrderRes>,
+    /// State of the placed order in the order book, none until found
+    pub state: Option<OrderState>,
+    /// Cancellation result if the leg was rolled back or cancelled
+    pub cancellation: Option<Result<CancelOrderRes>>,
+}
+
+impl BasketLeg {
+    /// Returns the order id if the leg was placed
+    pub fn order_id(&self) -> Option<&str> {
+        self.result
+            .as_ref()
+            .ok()
+            .and_then(|res| res.order_id.as_deref())
+    }
+
+    /// Returns true if the placed order was rejected by the broker
+    pub fn is_rejected(&self) -> bool {
+        self.state == Some(OrderState::Rejected)
+    }
+
+    /// Returns true if the leg was placed, is still working and was not cancelled since
+    pub fn is_live(&self) -> bool {
+        self.order_id().is_some()
+            && !self.state.as_ref().is_some_and(OrderState::is_terminal)
+            && !matches!(self.cancellation, Some(Ok(_)))
+    }
+}
+
+/// Placed basket with the per-leg results, in the order of the legs
+#[derive(Debug)]
+pub struct PlacedBasket {
+    /// Results of the legs
+    pub legs: Vec<BasketLeg>,
+    /// True if the placed legs were cancelled because another leg failed
+    pub rolled_back: bool,
+    orders_per_second: u32,
+}
+
+impl PlacedBasket {
+    /// Returns true if every leg was placed and none was rejected
+    pub fn is_complete(&self) -> bool {
+        self.legs
+            .iter()
+            .all(|leg| leg.result.is_ok() && !leg.is_rejected())
+    }
+
+    /// Returns the ids of the placed legs
+    pub fn order_ids(&self) -> Vec<&str> {
+        self.legs.iter().filter_map(BasketLeg::order_id).collect()
+    }
+
+    /// Polls the order book until no placed leg is pending or the timeout passes, the
+    /// states are stored on the legs
+    async fn confirm(&mut self, broker: &dyn OrderApi, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match broker.order_book().await {
+                Ok(orders) => {
+                    for leg in self.legs.iter_mut() {
+                        let Some(order_id) = leg.order_id() else {
+                            continue;
+                        };
+                        if let Some(order) = orders.iter().find(|o| o.order_id == order_id) {
+                            leg.state = OrderState::from_order_book(order);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to confirm the basket legs: {e}"),
+            }
+
+            let pending = self.legs.iter().any(|leg| {
+                leg.order_id().is_some()
+                    && leg.state.as_ref().is_none_or(|s| s == &OrderState::Pending)
+            });
+            if !pending || Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Cancels the live legs of the placed basket, the results are stored on the legs
+    pub async fn cancel_all(&mut self, broker: &dyn OrderApi) {
+        let limiter = RateLimiter::new(self.orders_per_second);
+
+        let cancellations = join_all(self.legs.iter_mut().filter(|leg| leg.is_live()).map(
+            |leg| async {
+                limiter.acquire().await;
+                let order_id = leg.order_id().unwrap_or_default().to_string();
+                let cancel_req = CancelOrderReq::new(leg.variety.clone(), &order_id);
+                let result = broker.cancel_order(&cancel_req).await;
+                if let Err(e) = &result {
+                    warn!("Failed to cancel basket leg {order_id}: {e}");
+                }
+                (leg, result)
+            },
+        ))
+        .await;
+
+        info!("Cancelled {} basket legs", cancellations.len());
+        for (leg, result) in cancellations {
+            leg.cancellation = Some(result);
+        }
+    }
+}
+
+impl SmartConnect {
+    /// Places the legs of the [`Basket`], see [`Basket::place`]
+    pub async fn place_basket(&self, basket: &Basket) -> PlacedBasket {
+        basket.place(self).await
+    }
+
+    /// Modifies the orders concurrently at the default basket rate
+    pub async fn modify_basket(&self, orders: &[ModifyOrderReq]) -> Vec<Result<ModifyOrderRes>> {
+        let limiter = RateLimiter::new(DEFAULT_ORDERS_PER_SECOND);
+
+        join_all(orders.iter().map(|order| async {
+            limiter.acquire().await;
+            self.modify_order(order).await
+        }))
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Mutex, MutexGuard},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+
+    use crate::{
+        broker::OrderApi,
+        order::{
+            CancelOrderReq, CancelOrderRes, IndividualOrderStatus, ModifyOrderReq, ModifyOrderRes,
+            Order, OrderBook, PlaceOrderReq, PlaceOrderRes, TradeBook,
+        },
+        types::{ExchangeType, OrderStatusKind},
+        Error, Result,
+    };
+
+    use super::Basket;
+
+    /// Broker accepting every order, the orders of `REJECT-EQ` are then rejected
+    #[derive(Default)]
+    struct Rejecting {
+        orders: Mutex<Vec<OrderBook>>,
+    }
+
+    impl Rejecting {
+        fn orders(&self) -> MutexGuard<'_, Vec<OrderBook>> {
+            self.orders.lock().unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl OrderApi for Rejecting {
+        async fn place_order(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderRes> {
+            let mut orders = self.orders();
+            let order_id = (orders.len() + 1).to_string();
+            let order_status = match order_req.inner.trading_symbol.as_str() {
+                "REJECT-EQ" => OrderStatusKind::Rejected,
+                _ => OrderStatusKind::Open,
+            };
+            orders.push(OrderBook {
+                order_id: order_id.clone(),
+                order_status,
+                ..Default::default()
+            });
+            Ok(PlaceOrderRes {
+                script: order_req.inner.trading_symbol.clone(),
+                order_id: Some(order_id),
+                unique_order_id: None,
+            })
+        }
+
+        async fn modify_order(&self, modify_req: &ModifyOrderReq) -> Result<ModifyOrderRes> {
+            Err(Error::OrderNotFound(modify_req.order_id.clone()))
+        }
+
+        async fn cancel_order(&self, cancel_req: &CancelOrderReq) -> Result<CancelOrderRes> {
+            let mut orders = self.orders();
+            let order = orders
+                .iter_mut()
+                .find(|o| o.order_id == cancel_req.order_id)
+                .ok_or_else(|| Error::OrderNotFound(cancel_req.order_id.clone()))?;
+            order.order_status = OrderStatusKind::Cancelled;
+            Ok(CancelOrderRes {
+                order_id: order.order_id.clone(),
+                unique_order_id: None,
+            })
+        }
+
+        async fn order_book(&self) -> Result<Vec<OrderBook>> {
+            Ok(self.orders().clone())
+        }
+
+        async fn order_status(&self, unique_order_id: &str) -> Result<IndividualOrderStatus> {
+            Err(Error::OrderNotFound(unique_order_id.to_string()))
+        }
+
+        async fn trade_book(&self) -> Result<Vec<TradeBook>> {
+            Ok(vec![])
+        }
+    }
+
+    fn leg(symbol: &str) -> PlaceOrderReq {
+        Order::limit(100.0)
+            .instrument(ExchangeType::NSE, symbol, "1")
+            .buy(1)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn rejected_legs_roll_back_the_basket() {
+        let broker = Rejecting::default();
+        let basket = Basket::new()
+            .leg(leg("INFY-EQ"))
+            .leg(leg("TCS-EQ"))
+            .confirm_timeout(Duration::ZERO);
+        let placed = basket.place(&broker).await;
+        assert!(placed.is_complete() && !placed.rolled_back);
+        assert!(placed.legs.iter().all(|leg| leg.is_live()));
+
+        // accepted by the api, then rejected in the order book
+        let placed = basket
+            .leg(leg("REJECT-EQ"))
+            .all_or_nothing(true)
+            .place(&broker)
+            .await;
+        assert!(!placed.is_complete() && placed.rolled_back);
+        assert!(matches!(placed.legs[0].cancellation, Some(Ok(_))));
+        assert!(placed.legs[2].is_rejected());
+        assert!(placed.legs[2].cancellation.is_none());
+        assert_eq!(broker.orders()[3].order_status, OrderStatusKind::Cancelled);
+    }
+}
@@ -1,4 +1,4 @@
-use dtcm_angel_utils::http::{EndPoint, HttpClient};
+use dtcm_angel_utils::http::{HttpClient, HttpSender};
 
 use std::fmt::Display;
 
@@ -6,6 +6,7 @@ use crate::{types::OrderVariety, Result};
 
 /// Cancel order request
 #[derive(Debug, Serialize)]
+#[api(POST, OrderCancel)]
 pub struct CancelOrderReq {
     /// Order variety
     pub variety: OrderVariety,
@@ -39,9 +40,6 @@ impl CancelOrderReq {
 
     /// Sends the [`CancelOrderReq`] to the API and returns [`CancelOrderRes`]
     pub async fn send(&self, http: &HttpClient) -> Result<CancelOrderRes> {
-        http.post(EndPoint::OrderModify, self)
-            .await
-            .and_then(|res| res.into_data())
-            .map_err(|e| e.into())
+        Ok(self.send_data(http).await?)
     }
 }
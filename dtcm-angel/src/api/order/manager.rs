@@ -0,0 +1,549 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use futures_util::{stream, Stream, StreamExt};
+use log::{debug, error, trace, warn};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    types::OrderStatusKind,
+    ws::{AngelOneWsOrderStatus, AngelOneWsOrderStatusEn as StatusEn, OrderStatus},
+    Error, Result, SmartConnect,
+};
+
+use super::{OrderBook, PlaceOrderReq};
+
+// Interval of the pings keeping the order update websocket alive
+const WS_PING_INTERVAL: Duration = Duration::from_secs(10);
+
+// Updates kept for the orders not tracked yet, the oldest are dropped beyond it
+const EARLY_UPDATES: usize = 64;
+
+/// Local state of a tracked order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OrderState {
+    /// Placed but not yet acknowledged by the exchange
+    #[default]
+    Pending,
+    /// Open at the exchange
+    Open,
+    /// Stop loss order waiting for its trigger
+    TriggerPending,
+    /// Part of the quantity is filled
+    PartiallyFilled,
+    /// Fully filled
+    Complete,
+    /// Cancelled
+    Cancelled,
+    /// Rejected
+    Rejected,
+}
+
+impl OrderState {
+    /// Returns true if the order can no longer change
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Complete | Self::Cancelled | Self::Rejected)
+    }
+
+    /// Progress of the state, states never move back to a lower rank
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Pending => 0,
+            Self::Open | Self::TriggerPending => 1,
+            Self::PartiallyFilled => 2,
+            Self::Complete | Self::Cancelled | Self::Rejected => 3,
+        }
+    }
+
+    /// Returns true if the order may move from this state to the next one
+    pub fn can_transition_to(&self, next: &Self) -> bool {
+        !self.is_terminal() && self != next && next.rank() >= self.rank()
+    }
+
+    /// Returns the state reported by the order book entry
+    pub fn from_order_book(order: &OrderBook) -> Option<Self> {
+        let state = match &order.order_status {
+            OrderStatusKind::Complete => Self::Complete,
+            OrderStatusKind::Cancelled => Self::Cancelled,
+            OrderStatusKind::Rejected => Self::Rejected,
+            OrderStatusKind::TriggerPending => Self::TriggerPending,
+            _ if order.filled_shares > 0 => Self::PartiallyFilled,
+            OrderStatusKind::Open
+            | OrderStatusKind::Modified
+            | OrderStatusKind::AfterMarketOrderReqReceived => Self::Open,
+            OrderStatusKind::OpenPending
+            | OrderStatusKind::ValidationPending
+            | OrderStatusKind::PutOrderReqReceived
+            | OrderStatusKind::ModifyPending
+            | OrderStatusKind::ModifyValidationPending => Self::Pending,
+            OrderStatusKind::Unknown(_) => return None,
+        };
+        Some(state)
+    }
+
+    /// Returns the state reported by the order update status code
+    fn from_status(status: &StatusEn) -> Option<Self> {
+        let state = match status {
+            StatusEn::Open | StatusEn::Modified | StatusEn::AfterMarketOrderReqReceived => {
+                Self::Open
+            }
+            StatusEn::TriggerPending => Self::TriggerPending,
+            StatusEn::Complete => Self::Complete,
+            StatusEn::Cancelled | StatusEn::CancelledAfterMarketOrder => Self::Cancelled,
+            StatusEn::Rejected => Self::Rejected,
+            StatusEn::OpenPending
+            | StatusEn::ModifyPending
+            | StatusEn::ModifyAfterMarketOrderReqReceived => Self::Pending,
+            StatusEn::AfterSuccessfulConnection | StatusEn::Unknown(_) => return None,
+        };
+        Some(state)
+    }
+}
+
+/// Latest known view of a tracked order
+#[derive(Debug, Clone, Default)]
+pub struct OrderSnapshot {
+    /// Order ID
+    pub order_id: String,
+    /// Unique order ID
+    pub unique_order_id: Option<String>,
+    /// Local state
+    pub state: OrderState,
+    /// Filled quantity
+    pub filled_quantity: u64,
+    /// Average fill price
+    pub average_price: f64,
+    /// Rejection or status text from the broker
+    pub text: String,
+    /// Latest order book entry for the order
+    pub order: Option<OrderBook>,
+}
+
+impl OrderSnapshot {
+    /// Applies the order book entry, returns true if the snapshot changed
+    fn apply(&mut self, order: &OrderBook, state: Option<OrderState>) -> bool {
+        let Some(state) = state.or_else(|| OrderState::from_order_book(order)) else {
+            return false;
+        };
+
+        if self.state.is_terminal() {
+            trace!("Update for terminal order {} ignored", self.order_id);
+            return false;
+        }
+        if self.state != state && !self.state.can_transition_to(&state) {
+            debug!(
+                "Order {} stale transition {:?} -> {state:?} ignored",
+                self.order_id, self.state
+            );
+            return false;
+        }
+
+        let changed = self.state != state
+            || self.filled_quantity != order.filled_shares
+            || self.order.as_ref() != Some(order);
+
+        self.state = state;
+        self.filled_quantity = order.filled_shares;
+        self.average_price = order.average_price;
+        self.text.clone_from(&order.text);
+        if let Some(unique_order_id) = order.unique_order_id.as_ref().filter(|u| !u.is_empty()) {
+            self.unique_order_id = Some(unique_order_id.clone());
+        }
+        self.order = Some(order.clone());
+
+        changed
+    }
+}
+
+/// Handle to an order tracked by the [`OrderManager`]
+#[derive(Debug, Clone)]
+pub struct OrderHandle {
+    order_id: String,
+    receiver: watch::Receiver<OrderSnapshot>,
+}
+
+impl OrderHandle {
+    /// Returns the order ID
+    pub fn order_id(&self) -> &str {
+        &self.order_id
+    }
+
+    /// Returns the latest [`OrderSnapshot`]
+    pub fn snapshot(&self) -> OrderSnapshot {
+        self.receiver.borrow().clone()
+    }
+
+    /// Returns the current [`OrderState`]
+    pub fn state(&self) -> OrderState {
+        self.receiver.borrow().state.clone()
+    }
+
+    /// Waits until the order reaches a terminal state
+    pub async fn await_terminal(&self) -> Result<OrderSnapshot> {
+        let mut receiver = self.receiver.clone();
+        let snapshot = receiver
+            .wait_for(|snapshot| snapshot.state.is_terminal())
+            .await
+            .map_err(|_| Error::OrderTrackingClosed(self.order_id.clone()))?;
+        Ok(snapshot.clone())
+    }
+
+    /// Waits until the order is completely filled, errors if it ends cancelled or rejected
+    pub async fn await_fill(&self) -> Result<OrderSnapshot> {
+        let snapshot = self.await_terminal().await?;
+        match snapshot.state {
+            OrderState::Complete => Ok(snapshot),
+            state => Err(Error::OrderNotFilled(self.order_id.clone(), state)),
+        }
+    }
+
+    /// Returns the stream of the state transitions starting with the current snapshot, it
+    /// ends after the terminal state. Intermediate snapshots may be coalesced.
+    pub fn transitions(&self) -> impl Stream<Item = OrderSnapshot> + Send + 'static {
+        let mut receiver = self.receiver.clone();
+        receiver.mark_changed();
+
+        stream::unfold(Some(receiver), |receiver| async move {
+            let mut receiver = receiver?;
+            receiver.changed().await.ok()?;
+
+            let snapshot = receiver.borrow_and_update().clone();
+            let next = (!snapshot.state.is_terminal()).then_some(receiver);
+            Some((snapshot, next))
+        })
+    }
+}
+
+/// Tracked orders with the updates received before their order was tracked
+#[derive(Debug, Default)]
+struct TrackedOrders {
+    senders: HashMap<String, watch::Sender<OrderSnapshot>>,
+    // the update of a new order may arrive before place_order returns its id
+    early: VecDeque<(OrderBook, Option<OrderState>)>,
+}
+
+impl TrackedOrders {
+    /// Tracks the order and replays the updates received for it before
+    fn track(
+        &mut self,
+        order_id: &str,
+        unique_order_id: Option<String>,
+    ) -> watch::Receiver<OrderSnapshot> {
+        let receiver = self
+            .senders
+            .entry(order_id.to_string())
+            .or_insert_with(|| {
+                watch::channel(OrderSnapshot {
+                    order_id: order_id.to_string(),
+                    unique_order_id,
+                    ..Default::default()
+                })
+                .0
+            })
+            .subscribe();
+
+        let (early, rest) = self
+            .early
+            .drain(..)
+            .partition::<Vec<_>, _>(|(order, _)| order.order_id == order_id);
+        self.early = rest.into();
+        for (order, state) in early {
+            trace!("Replaying early update of order {order_id}");
+            self.apply(&order, state);
+        }
+        receiver
+    }
+
+    /// Keeps the update of an order not tracked yet
+    fn buffer(&mut self, order: &OrderBook, state: Option<OrderState>) {
+        if self.early.len() == EARLY_UPDATES {
+            self.early.pop_front();
+        }
+        self.early.push_back((order.clone(), state));
+    }
+
+    fn apply(&mut self, order: &OrderBook, state: Option<OrderState>) -> bool {
+        let Some(sender) = self.senders.get(&order.order_id) else {
+            return false;
+        };
+        let changed = sender.send_if_modified(|snapshot| snapshot.apply(order, state));
+
+        // the handles keep the final snapshot, later events of the order are stale
+        if sender.borrow().state.is_terminal() {
+            trace!("Order {} terminal, no longer tracked", order.order_id);
+            self.senders.remove(&order.order_id);
+        }
+        changed
+    }
+}
+
+/// Places orders and tracks their state from the order update websocket, reconciling with
+/// the REST order status. Updates received before their order is tracked are replayed when
+/// it is.
+#[derive(Debug, Clone)]
+pub struct OrderManager {
+    smart_connect: Arc<SmartConnect>,
+    orders: Arc<Mutex<TrackedOrders>>,
+    reconcile_interval: Duration,
+}
+
+impl OrderManager {
+    /// Returns a new instance for [`OrderManager`]
+    pub fn new(smart_connect: Arc<SmartConnect>) -> Self {
+        Self {
+            smart_connect,
+            orders: Default::default(),
+            reconcile_interval: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets the interval of the REST reconciliation, 5 seconds by default
+    pub fn reconcile_interval(mut self, reconcile_interval: Duration) -> Self {
+        self.reconcile_interval = reconcile_interval;
+        self
+    }
+
+    fn orders(&self) -> MutexGuard<'_, TrackedOrders> {
+        self.orders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Places the order and returns its [`OrderHandle`]
+    pub async fn place(&self, order_req: &PlaceOrderReq) -> Result<OrderHandle> {
+        let res = self.smart_connect.place_order(order_req).await?;
+        let order_id = res.order_id.filter(|id| !id.is_empty()).ok_or_else(|| {
+            error!("Order for {} placed without an order id", res.script);
+            Error::OrderIdMissing
+        })?;
+
+        Ok(self.track(order_id, res.unique_order_id))
+    }
+
+    /// Tracks an already placed order
+    pub fn track<O>(&self, order_id: O, unique_order_id: Option<String>) -> OrderHandle
+    where
+        O: Into<String>,
+    {
+        let order_id = order_id.into();
+        let receiver = self.orders().track(&order_id, unique_order_id);
+
+        OrderHandle { order_id, receiver }
+    }
+
+    /// Returns the handle for the tracked order, none once the order is terminal
+    pub fn handle(&self, order_id: &str) -> Option<OrderHandle> {
+        self.orders()
+            .senders
+            .get(order_id)
+            .map(|sender| OrderHandle {
+                order_id: order_id.to_string(),
+                receiver: sender.subscribe(),
+            })
+    }
+
+    /// Stops tracking the order
+    pub fn untrack(&self, order_id: &str) {
+        self.orders().senders.remove(order_id);
+    }
+
+    /// Applies the order book entry to its tracked order, returns true if the state changed
+    pub fn apply_order_book(&self, order: &OrderBook) -> bool {
+        self.orders().apply(order, None)
+    }
+
+    /// Applies the order update websocket event, returns true if the state changed. The
+    /// event of an order not tracked yet is kept for [`OrderManager::track`].
+    pub fn apply_update(&self, update: &OrderStatus) -> bool {
+        let order = &update.order_data;
+        let state = OrderState::from_order_book(order)
+            .or_else(|| OrderState::from_status(&update.order_status));
+
+        let mut orders = self.orders();
+        match orders.senders.contains_key(&order.order_id) {
+            true => orders.apply(order, state),
+            false => {
+                orders.buffer(order, state);
+                false
+            }
+        }
+    }
+
+    /// Returns the ids of the orders that are not yet terminal
+    fn open_orders(&self) -> Vec<(String, Option<String>)> {
+        self.orders()
+            .senders
+            .values()
+            .filter_map(|sender| {
+                let snapshot = sender.borrow();
+                (!snapshot.state.is_terminal())
+                    .then(|| (snapshot.order_id.clone(), snapshot.unique_order_id.clone()))
+            })
+            .collect()
+    }
+
+    /// Reconciles the open orders with the REST order status, the order book is fetched for
+    /// orders without a unique order id
+    pub async fn reconcile(&self) -> Result<()> {
+        let open_orders = self.open_orders();
+        let mut needs_order_book = false;
+
+        for (order_id, unique_order_id) in open_orders {
+            let Some(unique_order_id) = unique_order_id else {
+                needs_order_book = true;
+                continue;
+            };
+            match self.smart_connect.order_status(unique_order_id).await {
+                Ok(status) => {
+                    self.apply_order_book(&status.order);
+                }
+                Err(e) => warn!("Failed to reconcile order {order_id}: {e}"),
+            }
+        }
+
+        if needs_order_book {
+            for order in self.smart_connect.order_book().await? {
+                self.apply_order_book(&order);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spawns a task reconciling the open orders at the configured interval
+    pub fn spawn_reconciler(&self) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(manager.reconcile_interval);
+            loop {
+                timer.tick().await;
+                if let Err(e) = manager.reconcile().await {
+                    error!("Order reconciliation failed: {e}");
+                }
+            }
+        })
+    }
+
+    /// Connects the order update websocket and spawns a task applying its events, the task
+    /// ends when the stream closes
+    pub async fn spawn_updates(&self, ws: &AngelOneWsOrderStatus) -> Result<JoinHandle<()>> {
+        let mut stream = ws.stream::<OrderStatus>().await?;
+
+        let manager = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut timer = tokio::time::interval(WS_PING_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = timer.tick() => {
+                        if let Err(e) = stream.send_text("ping").await {
+                            error!("Order update ping failed: {e}");
+                            break;
+                        }
+                    }
+                    update = stream.next() => match update {
+                        Some(Ok(update)) => {
+                            manager.apply_update(&update);
+                        }
+                        Some(Err(e)) => error!("Failed to read the order update: {e}"),
+                        None => break,
+                    }
+                }
+            }
+            debug!("Order update websocket stream closed");
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+
+    use crate::{order::OrderBook, types::OrderStatusKind};
+
+    use super::{OrderSnapshot, OrderState, TrackedOrders};
+
+    fn order(order_status: OrderStatusKind, filled_shares: u64) -> OrderBook {
+        OrderBook {
+            order_id: String::from("241226001121984"),
+            order_status,
+            filled_shares,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn order_state_machine() {
+        let (sender, receiver) = tokio::sync::watch::channel(OrderSnapshot::default());
+        let handle = super::OrderHandle {
+            order_id: String::from("241226001121984"),
+            receiver,
+        };
+        let transitions = handle.transitions();
+
+        for (status, filled) in [
+            (OrderStatusKind::Open, 0),
+            (OrderStatusKind::Open, 4),
+            // late open event does not move the order back
+            (OrderStatusKind::Open, 0),
+            (OrderStatusKind::Complete, 10),
+            (OrderStatusKind::Cancelled, 10),
+        ] {
+            sender.send_if_modified(|s| s.apply(&order(status, filled), None));
+            tokio::task::yield_now().await;
+        }
+
+        let snapshot = tokio::time::timeout(Duration::from_secs(1), handle.await_fill())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.filled_quantity, 10);
+
+        let states = transitions.map(|s| s.state).collect::<Vec<_>>().await;
+        assert_eq!(states.last(), Some(&OrderState::Complete));
+        assert!(!OrderState::PartiallyFilled.can_transition_to(&OrderState::Open));
+    }
+
+    #[tokio::test]
+    async fn terminal_snapshot_outlives_the_tracking() {
+        let (sender, receiver) = tokio::sync::watch::channel(OrderSnapshot::default());
+        let handle = super::OrderHandle {
+            order_id: String::from("241226001121984"),
+            receiver,
+        };
+        let transitions = handle.transitions();
+
+        sender.send_if_modified(|s| s.apply(&order(OrderStatusKind::Complete, 10), None));
+        // evicted by the manager after the final event
+        drop(sender);
+
+        let snapshot = handle.await_fill().await.unwrap();
+        assert_eq!(snapshot.filled_quantity, 10);
+        let states = transitions.map(|s| s.state).collect::<Vec<_>>().await;
+        assert_eq!(states, vec![OrderState::Complete]);
+    }
+
+    #[test]
+    fn early_updates_are_replayed_when_tracked() {
+        let mut orders = TrackedOrders::default();
+        let other = OrderBook {
+            order_id: String::from("241226001121985"),
+            ..order(OrderStatusKind::Open, 0)
+        };
+        // the fill arrives before place_order returns the order id
+        orders.buffer(&order(OrderStatusKind::Open, 0), None);
+        orders.buffer(&other, None);
+        orders.buffer(&order(OrderStatusKind::Complete, 10), None);
+
+        let receiver = orders.track("241226001121984", None);
+        let snapshot = receiver.borrow().clone();
+        assert_eq!(
+            (snapshot.state, snapshot.filled_quantity),
+            (OrderState::Complete, 10)
+        );
+        assert!(!orders.senders.contains_key("241226001121984"));
+        assert_eq!(orders.early.len(), 1);
+    }
+}
@@ -8,7 +8,7 @@ mod trade_book;
 pub use trade_book::TradeBook;
 
 mod order_inner;
-pub use order_inner::{OrderInner, OrderSetter};
+pub use order_inner::{unique_order_tag, OrderInner, OrderSetter};
 
 mod place_order;
 pub use place_order::{PlaceOrderReq, PlaceOrderRes};
@@ -18,3 +18,21 @@ pub use modify_order::{ModifyOrderReq, ModifyOrderRes};
 
 mod cancel_order;
 pub use cancel_order::{CancelOrderReq, CancelOrderRes};
+
+mod validation;
+pub use validation::OrderValidationError;
+
+mod typed_order;
+pub use typed_order::{
+    AmoKind, Bracket, Limit, Market, NoInstrument, NoSide, Order, OrderKind, Ready, StopLossLimit,
+    StopLossMarket,
+};
+
+mod manager;
+pub use manager::{OrderHandle, OrderManager, OrderSnapshot, OrderState};
+
+mod rate_limiter;
+pub use rate_limiter::RateLimiter;
+
+mod basket;
+pub use basket::{Basket, BasketLeg, PlacedBasket};
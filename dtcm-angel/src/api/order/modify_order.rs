@@ -1,4 +1,4 @@
-use dtcm_angel_utils::http::{EndPoint, HttpClient};
+use dtcm_angel_utils::http::{HttpClient, HttpSender};
 
 use std::fmt::Display;
 
@@ -8,6 +8,7 @@ use super::{OrderInner, OrderSetter};
 
 /// Modify order request
 #[derive(Debug, Serialize)]
+#[api(POST, OrderModify)]
 pub struct ModifyOrderReq {
     /// Order ID
     #[serde(rename = "orderid")]
@@ -44,10 +45,7 @@ impl ModifyOrderReq {
 
     /// Sends the [`ModifyOrderReq`] to the API and returns [`ModifyOrderRes`]
     pub async fn send(&self, http: &HttpClient) -> Result<ModifyOrderRes> {
-        http.post(EndPoint::OrderModify, self)
-            .await
-            .and_then(|res| res.into_data())
-            .map_err(|e| e.into())
+        Ok(self.send_data(http).await?)
     }
 }
 
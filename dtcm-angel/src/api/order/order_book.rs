@@ -1,12 +1,21 @@
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use dtcm_angel_utils::{
+    date::{from_dd_mmm_yyyy_hh_mm_ss, serialize_dd_mmm_yyyy_hh_mm_ss},
+    num::serde_number,
+};
+use log::warn;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::types::{
-    DurationType, ExchangeType, OrderType, OrderVariety, ProductType, TransactionType,
+    DurationType, ExchangeType, OrderStatusKind, OrderType, OrderVariety, ProductType,
+    TransactionType,
 };
 
-/// Placeholder for the order book
+/// Placeholder for the order book, a date time received in an unexpected format is left
+/// empty and kept as received in the raw field next to it
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize, Default, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Clone)]
+#[serde(remote = "Self")]
 #[api(GET, OrderBook)]
 pub struct OrderBook {
     pub variety: OrderVariety,
@@ -18,9 +27,10 @@ pub struct OrderBook {
     pub price: f64,
     #[serde(rename = "triggerprice")]
     pub trigger_price: f64,
-    pub quantity: String,
-    #[serde(rename = "disclosedquantity")]
-    pub disclosed_quantity: String,
+    #[serde(deserialize_with = "serde_number")]
+    pub quantity: u64,
+    #[serde(rename = "disclosedquantity", deserialize_with = "serde_number")]
+    pub disclosed_quantity: u64,
     #[serde(rename = "squareoff")]
     pub square_off: f64,
     #[serde(rename = "stoploss")]
@@ -44,30 +54,48 @@ pub struct OrderBook {
     pub option_type: String,
     #[serde(rename = "expirydate")]
     pub expiry_date: String,
-    #[serde(rename = "lotsize")]
-    pub lot_size: String,
-    #[serde(rename = "cancelsize")]
-    pub cancel_size: String,
+    #[serde(rename = "lotsize", deserialize_with = "serde_number")]
+    pub lot_size: u64,
+    #[serde(rename = "cancelsize", deserialize_with = "serde_number")]
+    pub cancel_size: u64,
     #[serde(rename = "averageprice")]
     pub average_price: f64,
-    #[serde(rename = "filledshares")]
-    pub filled_shares: String,
-    #[serde(rename = "unfilledshares")]
-    pub unfilled_shares: String,
+    #[serde(rename = "filledshares", deserialize_with = "serde_number")]
+    pub filled_shares: u64,
+    #[serde(rename = "unfilledshares", deserialize_with = "serde_number")]
+    pub unfilled_shares: u64,
     #[serde(rename = "orderid")]
     pub order_id: String,
     #[serde(rename = "uniqueorderid")]
     pub unique_order_id: Option<String>,
     pub text: String,
-    pub status: String,
+    pub status: OrderStatusKind,
     #[serde(rename = "orderstatus")]
-    pub order_status: String,
-    #[serde(rename = "updatetime")]
-    pub update_time: String,
-    #[serde(rename = "exchtime")]
-    pub exch_time: String,
-    #[serde(rename = "exchorderupdatetime")]
-    pub exch_order_update_time: String,
+    pub order_status: OrderStatusKind,
+    #[serde(
+        rename = "updatetime",
+        skip_deserializing,
+        serialize_with = "serialize_dd_mmm_yyyy_hh_mm_ss"
+    )]
+    pub update_time: Option<NaiveDateTime>,
+    #[serde(rename = "updatetime", default, skip_serializing)]
+    pub update_time_raw: Option<String>,
+    #[serde(
+        rename = "exchtime",
+        skip_deserializing,
+        serialize_with = "serialize_dd_mmm_yyyy_hh_mm_ss"
+    )]
+    pub exch_time: Option<NaiveDateTime>,
+    #[serde(rename = "exchtime", default, skip_serializing)]
+    pub exch_time_raw: Option<String>,
+    #[serde(
+        rename = "exchorderupdatetime",
+        skip_deserializing,
+        serialize_with = "serialize_dd_mmm_yyyy_hh_mm_ss"
+    )]
+    pub exch_order_update_time: Option<NaiveDateTime>,
+    #[serde(rename = "exchorderupdatetime", default, skip_serializing)]
+    pub exch_order_update_time_raw: Option<String>,
     #[serde(rename = "fillid")]
     pub fill_id: String,
     #[serde(rename = "filltime")]
@@ -76,6 +104,47 @@ pub struct OrderBook {
     pub parent_order_id: String,
 }
 
+impl Serialize for OrderBook {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderBook {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut order = Self::deserialize(deserializer)?;
+        for (parsed, raw) in [
+            (&mut order.update_time, &mut order.update_time_raw),
+            (&mut order.exch_time, &mut order.exch_time_raw),
+            (
+                &mut order.exch_order_update_time,
+                &mut order.exch_order_update_time_raw,
+            ),
+        ] {
+            *raw = raw.take().filter(|raw| !raw.trim().is_empty());
+            if let Some(value) = raw.as_deref() {
+                match from_dd_mmm_yyyy_hh_mm_ss(value) {
+                    Ok(date_time) => {
+                        *parsed = Some(date_time);
+                        *raw = None;
+                    }
+                    Err(e) => warn!(
+                        "Unexpected date time {value} of order {} kept raw: {e}",
+                        order.order_id
+                    ),
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,27 +165,41 @@ mod tests {
         book.order_type = OrderType::Market;
         book.product_type = ProductType::Delivery;
         book.duration = DurationType::Day;
-        book.quantity = "1".to_string();
-        book.disclosed_quantity = "0".to_string();
+        book.quantity = 1;
+        book.disclosed_quantity = 0;
         book.trading_symbol = "MOM30IETF-EQ".to_string();
         book.transaction_type = TransactionType::Buy;
         book.exchange = ExchangeType::NSE;
         book.symbol_token = "10585".to_string();
         book.strike_price = -1.0;
-        book.lot_size = "1".to_string();
-        book.cancel_size = "0".to_string();
-        book.filled_shares = "0".to_string();
-        book.unfilled_shares = "1".to_string();
+        book.lot_size = 1;
+        book.cancel_size = 0;
+        book.filled_shares = 0;
+        book.unfilled_shares = 1;
         book.order_id = "241226001121984".to_string();
-        book.status = "open".to_string();
-        book.order_status = "open".to_string();
-        book.update_time = "26-Dec-2024 15:55:01".to_string();
-        book.exch_time = "26-Dec-2024 15:55:01".to_string();
-        book.exch_order_update_time = "26-Dec-2024 15:55:01".to_string();
+        book.status = OrderStatusKind::Open;
+        book.order_status = OrderStatusKind::Open;
+        let update_time =
+            NaiveDateTime::parse_from_str("2024-12-26 15:55:01", "%Y-%m-%d %H:%M:%S").ok();
+        book.update_time = update_time;
+        book.exch_time = update_time;
+        book.exch_order_update_time = update_time;
 
         assert_eq!(parsed_book, book);
     }
 
+    #[test]
+    fn test_unexpected_date_time_is_kept_raw() {
+        let order = NORMAL_ORDER.replace("26-Dec-2024 15:55:01", "2024-12-26T15:55:01");
+        let book: OrderBook = serde_json::from_str(&order).unwrap();
+        assert_eq!(book.update_time, None);
+        assert_eq!(book.update_time_raw.as_deref(), Some("2024-12-26T15:55:01"));
+
+        let book: OrderBook = serde_json::from_str(NORMAL_ORDER).unwrap();
+        assert!(book.update_time.is_some());
+        assert_eq!(book.update_time_raw, None);
+    }
+
     const EMPTY_BOOK: &str = r#"{
         "variety": "",
         "ordertype": "",
@@ -1,5 +1,5 @@
 mod order_inner;
-pub use order_inner::OrderInner;
+pub use order_inner::{unique_order_tag, OrderInner};
 
 mod order_setter;
 pub use order_setter::OrderSetter;
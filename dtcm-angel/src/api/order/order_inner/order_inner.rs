@@ -1,3 +1,10 @@
+use std::{
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use chrono::Utc;
+
 use crate::types::{DurationType, ExchangeType, OrderType, OrderVariety, ProductType};
 
 use super::OrderSetter;
@@ -45,6 +52,9 @@ pub struct OrderInner {
     /// Quantity to transact
     #[serde(rename = "quantity")]
     pub quantity: String,
+    /// Client tag of the order, returned in the order book
+    #[serde(rename = "ordertag", skip_serializing_if = "Option::is_none")]
+    pub order_tag: Option<String>,
 }
 
 impl OrderInner {
@@ -71,12 +81,38 @@ impl OrderInner {
             trailing_stop_loss: None,
             disclosed_quantity: String::from("0"),
             quantity: String::from("0"),
+            order_tag: None,
         }
     }
 }
 
+/// Returns a new order tag unique across the processes of the client, within the 20
+/// characters accepted by the API
+pub fn unique_order_tag() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    let millis = Utc::now().timestamp_millis() & 0xfff_ffff_ffff;
+    format!("{millis:011x}{:04x}{count:04x}", process::id() & 0xffff)
+}
+
 impl OrderSetter for OrderInner {
     fn inner_mut(&mut self) -> &mut OrderInner {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::unique_order_tag;
+
+    #[test]
+    fn order_tags_are_unique() {
+        let tags = (0..1_000)
+            .map(|_| unique_order_tag())
+            .collect::<HashSet<_>>();
+        assert_eq!(tags.len(), 1_000);
+        assert!(tags.iter().all(|tag| tag.len() <= 20));
+    }
+}
@@ -126,4 +126,23 @@ pub trait OrderSetter {
         self.inner_mut().disclosed_quantity = quantity.to_string();
         self
     }
+
+    /// Sets the client tag of the order, at most 20 characters
+    fn order_tag<T>(mut self, order_tag: T) -> Self
+    where
+        T: Into<String>,
+        Self: Sized,
+    {
+        self.inner_mut().order_tag = Some(order_tag.into());
+        self
+    }
+
+    /// Sets a generated unique client tag, see [`super::unique_order_tag`]
+    fn unique_order_tag(mut self) -> Self
+    where
+        Self: Sized,
+    {
+        self.inner_mut().order_tag = Some(super::unique_order_tag());
+        self
+    }
 }
@@ -0,0 +1,50 @@
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::{sleep_until, Instant};
+
+/// Spaces out the requests so that at most `per_second` of them start every second
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Returns a new instance for [`RateLimiter`], a rate of zero is treated as one
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Waits for the next free slot
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot
+        };
+        sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+    use tokio::time::Instant;
+
+    use super::RateLimiter;
+
+    #[tokio::test]
+    async fn acquisitions_are_spaced() {
+        let limiter = RateLimiter::new(100);
+        let started = Instant::now();
+        join_all((0..5).map(|_| limiter.acquire())).await;
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+}
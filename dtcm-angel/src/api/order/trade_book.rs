@@ -1,8 +1,10 @@
+use dtcm_angel_utils::num::serde_number;
+
 use crate::types::{ExchangeType, ProductType, TransactionType};
 
 /// Placeholder for the trade book
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[api(GET, TradeBook)]
 pub struct TradeBook {
     pub exchange: ExchangeType,
@@ -14,24 +16,30 @@ pub struct TradeBook {
     pub instrument_type: String,
     #[serde(rename = "symbolgroup")]
     pub symbol_group: String,
-    #[serde(rename = "strikeprice")]
-    pub strike_price: String,
+    #[serde(rename = "strikeprice", deserialize_with = "serde_number")]
+    pub strike_price: f64,
     #[serde(rename = "optiontype")]
     pub option_type: String,
     #[serde(rename = "expirydate")]
     pub expiry_date: String,
-    #[serde(rename = "marketlot")]
-    pub market_lot: String,
-    pub precision: String,
-    pub multiplier: String,
-    #[serde(rename = "trade_value")]
-    pub trade_value: String,
+    #[serde(rename = "marketlot", deserialize_with = "serde_number")]
+    pub market_lot: u64,
+    #[serde(deserialize_with = "serde_number")]
+    pub precision: u32,
+    #[serde(deserialize_with = "serde_number")]
+    pub multiplier: f64,
+    #[serde(
+        rename = "tradevalue",
+        alias = "trade_value",
+        deserialize_with = "serde_number"
+    )]
+    pub trade_value: f64,
     #[serde(rename = "transactiontype")]
     pub transaction_type: TransactionType,
-    #[serde(rename = "fillprice")]
-    pub fill_price: String,
-    #[serde(rename = "fillsize")]
-    pub fill_size: String,
+    #[serde(rename = "fillprice", deserialize_with = "serde_number")]
+    pub fill_price: f64,
+    #[serde(rename = "fillsize", deserialize_with = "serde_number")]
+    pub fill_size: u64,
     #[serde(rename = "orderid")]
     pub order_id: String,
     #[serde(rename = "fillid")]
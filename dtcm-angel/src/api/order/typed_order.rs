@@ -0,0 +1,440 @@
+use std::{fmt::Display, marker::PhantomData};
+
+use crate::types::{
+    DurationType, ExchangeType, OrderType, OrderVariety, ProductType, TransactionType,
+};
+
+use super::{ModifyOrderReq, OrderInner, OrderValidationError, PlaceOrderReq};
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Kind of the [`Order`], decides the order type, variety and the prices sent
+pub trait OrderKind: private::Sealed {
+    /// Sets the order type, variety and prices on the [`OrderInner`]
+    fn apply(&self, inner: &mut OrderInner);
+
+    /// Returns the default [`ProductType`] for the kind
+    fn default_product_type(&self) -> ProductType {
+        ProductType::IntraDay
+    }
+}
+
+/// Kinds which can be placed as after market orders
+pub trait AmoKind: OrderKind {}
+
+/// Market order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Market;
+
+/// Limit order at the price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    /// Limit price
+    pub price: f64,
+}
+
+/// Stop loss order placing a limit order at the price once the trigger price is hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StopLossLimit {
+    /// Limit price
+    pub price: f64,
+    /// Trigger price
+    pub trigger_price: f64,
+}
+
+/// Stop loss order placing a market order once the trigger price is hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StopLossMarket {
+    /// Trigger price
+    pub trigger_price: f64,
+}
+
+/// Bracket (ROBO) limit order with target and stop loss legs, in absolute points from the price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bracket {
+    /// Limit price
+    pub price: f64,
+    /// Target points from the price
+    pub square_off: f64,
+    /// Stop loss points from the price
+    pub stop_loss: f64,
+    /// Trailing stop loss points
+    pub trailing_stop_loss: Option<f64>,
+}
+
+impl private::Sealed for Market {}
+impl private::Sealed for Limit {}
+impl private::Sealed for StopLossLimit {}
+impl private::Sealed for StopLossMarket {}
+impl private::Sealed for Bracket {}
+
+impl AmoKind for Market {}
+impl AmoKind for Limit {}
+
+impl OrderKind for Market {
+    fn apply(&self, inner: &mut OrderInner) {
+        inner.variety = OrderVariety::Normal;
+        inner.order_type = OrderType::Market;
+    }
+}
+
+impl OrderKind for Limit {
+    fn apply(&self, inner: &mut OrderInner) {
+        inner.variety = OrderVariety::Normal;
+        inner.order_type = OrderType::Limit;
+        inner.price = self.price.to_string();
+    }
+}
+
+impl OrderKind for StopLossLimit {
+    fn apply(&self, inner: &mut OrderInner) {
+        inner.variety = OrderVariety::StopLoss;
+        inner.order_type = OrderType::StopLossLimit;
+        inner.price = self.price.to_string();
+        inner.trigger_price = Some(self.trigger_price.to_string());
+    }
+}
+
+impl OrderKind for StopLossMarket {
+    fn apply(&self, inner: &mut OrderInner) {
+        inner.variety = OrderVariety::StopLoss;
+        inner.order_type = OrderType::StopLossMarket;
+        inner.trigger_price = Some(self.trigger_price.to_string());
+    }
+}
+
+impl OrderKind for Bracket {
+    fn apply(&self, inner: &mut OrderInner) {
+        inner.variety = OrderVariety::Robo;
+        inner.order_type = OrderType::Limit;
+        inner.price = self.price.to_string();
+        inner.square_off = Some(self.square_off.to_string());
+        inner.stop_loss = Some(self.stop_loss.to_string());
+        inner.trailing_stop_loss = self.trailing_stop_loss.map(|t| t.to_string());
+    }
+
+    fn default_product_type(&self) -> ProductType {
+        ProductType::Bo
+    }
+}
+
+/// [`Order`] state before the instrument is set
+#[derive(Debug, Clone, Copy)]
+pub struct NoInstrument;
+
+/// [`Order`] state before the side and quantity are set
+#[derive(Debug, Clone, Copy)]
+pub struct NoSide;
+
+/// [`Order`] state ready to be converted into a request
+#[derive(Debug, Clone, Copy)]
+pub struct Ready;
+
+/// Typed order builder, the kind fixes the prices that can be set and the state makes sure
+/// the instrument, side and quantity are set before a request can be built
+#[derive(Debug, Clone)]
+pub struct Order<K, S = Ready> {
+    kind: K,
+    exchange: ExchangeType,
+    trading_symbol: String,
+    symbol_token: String,
+    transaction_type: TransactionType,
+    quantity: u64,
+    disclosed_quantity: Option<u64>,
+    product_type: Option<ProductType>,
+    duration: DurationType,
+    amo: bool,
+    order_tag: Option<String>,
+    state: PhantomData<S>,
+}
+
+impl<K: OrderKind> Order<K, NoInstrument> {
+    fn with_kind(kind: K) -> Self {
+        Self {
+            kind,
+            exchange: Default::default(),
+            trading_symbol: String::new(),
+            symbol_token: String::new(),
+            transaction_type: Default::default(),
+            quantity: 0,
+            disclosed_quantity: None,
+            product_type: None,
+            duration: DurationType::Day,
+            amo: false,
+            order_tag: None,
+            state: PhantomData,
+        }
+    }
+
+    /// Sets the instrument of the order
+    pub fn instrument<S, T>(
+        self,
+        exchange: ExchangeType,
+        trading_symbol: S,
+        symbol_token: T,
+    ) -> Order<K, NoSide>
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        Order {
+            exchange,
+            trading_symbol: trading_symbol.into(),
+            symbol_token: symbol_token.into(),
+            ..self.into_state()
+        }
+    }
+}
+
+impl Order<Market, NoInstrument> {
+    /// Returns a market order
+    pub fn market() -> Self {
+        Self::with_kind(Market)
+    }
+}
+
+impl Order<Limit, NoInstrument> {
+    /// Returns a limit order at the price
+    pub fn limit(price: f64) -> Self {
+        Self::with_kind(Limit { price })
+    }
+}
+
+impl Order<StopLossLimit, NoInstrument> {
+    /// Returns a stop loss limit order at the price, triggered at the trigger price
+    pub fn stop_loss_limit(price: f64, trigger_price: f64) -> Self {
+        Self::with_kind(StopLossLimit {
+            price,
+            trigger_price,
+        })
+    }
+}
+
+impl Order<StopLossMarket, NoInstrument> {
+    /// Returns a stop loss market order triggered at the trigger price
+    pub fn stop_loss_market(trigger_price: f64) -> Self {
+        Self::with_kind(StopLossMarket { trigger_price })
+    }
+}
+
+impl Order<Bracket, NoInstrument> {
+    /// Returns a bracket order at the price with the target, stop loss and optional trailing
+    /// stop loss in points
+    pub fn bracket(
+        price: f64,
+        square_off: f64,
+        stop_loss: f64,
+        trailing_stop_loss: Option<f64>,
+    ) -> Self {
+        Self::with_kind(Bracket {
+            price,
+            square_off,
+            stop_loss,
+            trailing_stop_loss,
+        })
+    }
+}
+
+impl<K: OrderKind> Order<K, NoSide> {
+    /// Buys the quantity
+    pub fn buy(self, quantity: u64) -> Order<K, Ready> {
+        self.side(TransactionType::Buy, quantity)
+    }
+
+    /// Sells the quantity
+    pub fn sell(self, quantity: u64) -> Order<K, Ready> {
+        self.side(TransactionType::Sell, quantity)
+    }
+
+    fn side(self, transaction_type: TransactionType, quantity: u64) -> Order<K, Ready> {
+        Order {
+            transaction_type,
+            quantity,
+            ..self.into_state()
+        }
+    }
+}
+
+impl<K: OrderKind, S> Order<K, S> {
+    fn into_state<T>(self) -> Order<K, T> {
+        Order {
+            kind: self.kind,
+            exchange: self.exchange,
+            trading_symbol: self.trading_symbol,
+            symbol_token: self.symbol_token,
+            transaction_type: self.transaction_type,
+            quantity: self.quantity,
+            disclosed_quantity: self.disclosed_quantity,
+            product_type: self.product_type,
+            duration: self.duration,
+            amo: self.amo,
+            order_tag: self.order_tag,
+            state: PhantomData,
+        }
+    }
+
+    /// Returns the kind of the order
+    pub fn kind(&self) -> &K {
+        &self.kind
+    }
+
+    /// Sets the [`ProductType`], intraday by default and BO for bracket orders
+    pub fn product_type(mut self, product_type: ProductType) -> Self {
+        self.product_type = Some(product_type);
+        self
+    }
+
+    /// Sets the [`DurationType`], day by default
+    pub fn duration(mut self, duration: DurationType) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets the client tag of the order, at most 20 characters
+    pub fn order_tag<T>(mut self, order_tag: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.order_tag = Some(order_tag.into());
+        self
+    }
+
+    /// Sets the quantity disclosed to the market, the full quantity by default
+    pub fn disclosed_quantity(mut self, disclosed_quantity: u64) -> Self {
+        self.disclosed_quantity = Some(disclosed_quantity);
+        self
+    }
+}
+
+impl<K: AmoKind, S> Order<K, S> {
+    /// Places the order as an after market order
+    pub fn amo(mut self) -> Self {
+        self.amo = true;
+        self
+    }
+}
+
+impl<K: OrderKind> Order<K, Ready> {
+    /// Returns the [`OrderInner`] for the order
+    fn inner(&self) -> OrderInner {
+        let mut inner = OrderInner::new(&self.trading_symbol, &self.symbol_token);
+        self.kind.apply(&mut inner);
+        if self.amo {
+            inner.variety = OrderVariety::Amo;
+        }
+
+        inner.exchange = self.exchange.clone();
+        inner.product_type = self
+            .product_type
+            .clone()
+            .unwrap_or_else(|| self.kind.default_product_type());
+        inner.duration = self.duration.clone();
+        inner.order_tag = self.order_tag.clone();
+        inner.quantity = self.quantity.to_string();
+        inner.disclosed_quantity = self.disclosed_quantity.unwrap_or(self.quantity).to_string();
+        inner
+    }
+
+    /// Validates the order, the disclosed quantity can not exceed the quantity
+    pub fn validate(&self) -> Result<(), OrderValidationError> {
+        let disclosed_quantity = self.disclosed_quantity.unwrap_or(self.quantity);
+        if disclosed_quantity > self.quantity {
+            return Err(OrderValidationError::DisclosedQuantity {
+                quantity: self.quantity,
+                disclosed_quantity,
+            });
+        }
+
+        self.inner().validate()
+    }
+
+    /// Returns the [`PlaceOrderReq`] for the order
+    pub fn to_place_order(&self) -> PlaceOrderReq {
+        PlaceOrderReq {
+            transaction_type: self.transaction_type.clone(),
+            inner: self.inner(),
+        }
+    }
+
+    /// Returns the [`ModifyOrderReq`] changing the order with the id to this order
+    pub fn to_modify_order<O>(&self, order_id: O) -> ModifyOrderReq
+    where
+        O: Display,
+    {
+        ModifyOrderReq {
+            order_id: order_id.to_string(),
+            inner: self.inner(),
+        }
+    }
+}
+
+impl<K: OrderKind> From<Order<K, Ready>> for PlaceOrderReq {
+    fn from(order: Order<K, Ready>) -> Self {
+        order.to_place_order()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        order::{OrderValidationError, PlaceOrderReq},
+        types::{ExchangeType, OrderType, OrderVariety, ProductType, TransactionType},
+    };
+
+    use super::Order;
+
+    #[test]
+    fn typed_orders_build_valid_requests() {
+        let limit: PlaceOrderReq = Order::limit(3_500.5)
+            .disclosed_quantity(10)
+            .instrument(ExchangeType::NSE, "INFY-EQ", "1594")
+            .buy(100)
+            .into();
+        assert_eq!(limit.transaction_type, TransactionType::Buy);
+        assert_eq!(limit.inner.order_type, OrderType::Limit);
+        assert_eq!(limit.inner.price, "3500.5");
+        assert_eq!(
+            (
+                limit.inner.quantity.as_str(),
+                limit.inner.disclosed_quantity.as_str()
+            ),
+            ("100", "10")
+        );
+        assert_eq!(limit.validate(), Ok(()));
+
+        let bracket = Order::bracket(3_500.0, 20.0, 10.0, Some(2.0))
+            .instrument(ExchangeType::NSE, "INFY-EQ", "1594")
+            .sell(5)
+            .to_place_order();
+        assert_eq!(bracket.inner.variety, OrderVariety::Robo);
+        assert_eq!(bracket.inner.product_type, ProductType::Bo);
+        assert_eq!(bracket.validate(), Ok(()));
+
+        let amo = Order::market()
+            .amo()
+            .instrument(ExchangeType::NSE, "INFY-EQ", "1594")
+            .buy(1)
+            .to_modify_order("240101000000001");
+        assert_eq!(amo.inner.variety, OrderVariety::Amo);
+        assert_eq!(amo.order_id, "240101000000001");
+    }
+
+    #[test]
+    fn disclosed_quantity_above_the_quantity_is_rejected() {
+        let order = Order::limit(3_500.0)
+            .instrument(ExchangeType::NSE, "INFY-EQ", "1594")
+            .buy(10);
+        assert_eq!(order.validate(), Ok(()));
+
+        let order = order.disclosed_quantity(11);
+        assert_eq!(
+            order.validate(),
+            Err(OrderValidationError::DisclosedQuantity {
+                quantity: 10,
+                disclosed_quantity: 11
+            })
+        );
+    }
+}
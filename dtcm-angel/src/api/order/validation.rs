@@ -0,0 +1,374 @@
+use thiserror::Error as ThisError;
+
+use crate::{
+    market::Instrument,
+    types::{DurationType, ExchangeType, OrderType, OrderVariety, ProductType, TransactionType},
+};
+
+use super::{OrderInner, PlaceOrderReq};
+
+// Tolerance for the floating point tick size checks
+const TICK_EPSILON: f64 = 1e-6;
+
+/// Reasons an order is rejected before being sent to the broker
+#[derive(Debug, Clone, PartialEq, ThisError)]
+pub enum OrderValidationError {
+    /// required field is empty or unknown
+    #[error("{0} is required")]
+    MissingField(&'static str),
+    /// field is not a valid number
+    #[error("{field} has invalid value {value:?}")]
+    InvalidNumber {
+        /// Name of the field
+        field: &'static str,
+        /// Value of the field
+        value: String,
+    },
+    /// quantity is zero
+    #[error("quantity must be greater than zero")]
+    ZeroQuantity,
+    /// quantity is not a multiple of the lot size
+    #[error("quantity {quantity} is not a multiple of the lot size {lot_size}")]
+    LotSize {
+        /// Order quantity
+        quantity: u64,
+        /// Lot size of the instrument
+        lot_size: u64,
+    },
+    /// freeze quantity is smaller than a single lot
+    #[error("freeze quantity {freeze_quantity} is below the lot size {lot_size}")]
+    FreezeQuantity {
+        /// Freeze quantity of the instrument
+        freeze_quantity: u64,
+        /// Lot size of the instrument
+        lot_size: u64,
+    },
+    /// disclosed quantity is larger than the quantity
+    #[error("disclosed quantity {disclosed_quantity} exceeds the quantity {quantity}")]
+    DisclosedQuantity {
+        /// Order quantity
+        quantity: u64,
+        /// Disclosed quantity
+        disclosed_quantity: u64,
+    },
+    /// limit price is missing
+    #[error("price is required for {0:?} orders")]
+    MissingPrice(OrderType),
+    /// price is not a multiple of the tick size
+    #[error("{field} {price} is not a multiple of the tick size {tick_size}")]
+    TickSize {
+        /// Name of the field
+        field: &'static str,
+        /// Price of the field
+        price: f64,
+        /// Tick size of the instrument in rupees
+        tick_size: f64,
+    },
+    /// trigger price is missing for a stop loss order
+    #[error("trigger price is required for {0:?} orders")]
+    MissingTriggerPrice(OrderType),
+    /// trigger price is on the wrong side of the limit price
+    #[error(
+        "trigger price {trigger_price} is invalid for a {transaction_type:?} order at {price}"
+    )]
+    TriggerPrice {
+        /// Transaction type of the order
+        transaction_type: TransactionType,
+        /// Limit price
+        price: f64,
+        /// Trigger price
+        trigger_price: f64,
+    },
+    /// bracket order field is missing
+    #[error("{0} is required for ROBO orders")]
+    MissingRoboField(&'static str),
+    /// bracket order field set on another variety
+    #[error("{0} is only allowed for ROBO orders")]
+    RoboOnlyField(&'static str),
+    /// order does not match the instrument
+    #[error("order for {exchange:?} {token} does not match the instrument {instrument}")]
+    InstrumentMismatch {
+        /// Exchange of the order
+        exchange: ExchangeType,
+        /// Symbol token of the order
+        token: String,
+        /// Symbol of the instrument
+        instrument: String,
+    },
+}
+
+type ValidationResult<T> = Result<T, OrderValidationError>;
+
+/// Parses the numeric field, empty values are treated as missing
+fn parse<T>(field: &'static str, value: &str) -> ValidationResult<Option<T>>
+where
+    T: std::str::FromStr,
+{
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| OrderValidationError::InvalidNumber {
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Parses the optional numeric field
+fn parse_opt<T>(field: &'static str, value: &Option<String>) -> ValidationResult<Option<T>>
+where
+    T: std::str::FromStr,
+{
+    value
+        .as_deref()
+        .map_or(Ok(None), |value| parse(field, value))
+}
+
+/// Checks the price is a multiple of the tick size
+fn check_tick(field: &'static str, price: f64, tick_size: f64) -> ValidationResult<()> {
+    let ticks = price / tick_size;
+    match (ticks - ticks.round()).abs() < TICK_EPSILON {
+        true => Ok(()),
+        false => Err(OrderValidationError::TickSize {
+            field,
+            price,
+            tick_size,
+        }),
+    }
+}
+
+impl OrderInner {
+    /// Validates the order fields which do not depend on the instrument
+    pub fn validate(&self) -> ValidationResult<()> {
+        use OrderValidationError::*;
+
+        if self.trading_symbol.trim().is_empty() {
+            return Err(MissingField("trading_symbol"));
+        }
+        if self.symbol_token.trim().is_empty() {
+            return Err(MissingField("symbol_token"));
+        }
+        if matches!(self.variety, OrderVariety::Unknown(_)) {
+            return Err(MissingField("variety"));
+        }
+        if matches!(self.exchange, ExchangeType::Unknown(_)) {
+            return Err(MissingField("exchange"));
+        }
+        if matches!(self.order_type, OrderType::Unknown(_)) {
+            return Err(MissingField("order_type"));
+        }
+        if matches!(self.product_type, ProductType::Unknown(_)) {
+            return Err(MissingField("product_type"));
+        }
+        if matches!(self.duration, DurationType::Unknown(_)) {
+            return Err(MissingField("duration"));
+        }
+
+        let quantity = parse::<u64>("quantity", &self.quantity)?.unwrap_or_default();
+        if quantity == 0 {
+            return Err(ZeroQuantity);
+        }
+        let disclosed_quantity =
+            parse::<u64>("disclosed_quantity", &self.disclosed_quantity)?.unwrap_or_default();
+        if disclosed_quantity > quantity {
+            return Err(DisclosedQuantity {
+                quantity,
+                disclosed_quantity,
+            });
+        }
+
+        let price = parse::<f64>("price", &self.price)?.unwrap_or_default();
+        if price == 0.0 && matches!(self.order_type, OrderType::Limit | OrderType::StopLossLimit) {
+            return Err(MissingPrice(self.order_type.clone()));
+        }
+
+        let trigger_price = parse_opt::<f64>("trigger_price", &self.trigger_price)?;
+        if matches!(
+            self.order_type,
+            OrderType::StopLossLimit | OrderType::StopLossMarket
+        ) && trigger_price.is_none_or(|trigger_price| trigger_price == 0.0)
+        {
+            return Err(MissingTriggerPrice(self.order_type.clone()));
+        }
+
+        let square_off = parse_opt::<f64>("square_off", &self.square_off)?;
+        let stop_loss = parse_opt::<f64>("stop_loss", &self.stop_loss)?;
+        parse_opt::<f64>("trailing_stop_loss", &self.trailing_stop_loss)?;
+
+        match self.variety {
+            OrderVariety::Robo => {
+                if square_off.is_none_or(|v| v <= 0.0) {
+                    return Err(MissingRoboField("square_off"));
+                }
+                if stop_loss.is_none_or(|v| v <= 0.0) {
+                    return Err(MissingRoboField("stop_loss"));
+                }
+            }
+            _ => {
+                if self.square_off.is_some() {
+                    return Err(RoboOnlyField("square_off"));
+                }
+                if self.stop_loss.is_some() {
+                    return Err(RoboOnlyField("stop_loss"));
+                }
+                if self.trailing_stop_loss.is_some() {
+                    return Err(RoboOnlyField("trailing_stop_loss"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the order against the lot size and tick size of the [`Instrument`]
+    pub fn validate_with(&self, instrument: &Instrument) -> ValidationResult<()> {
+        self.validate()?;
+
+        if instrument.exch_seg != self.exchange || instrument.token != self.symbol_token {
+            return Err(OrderValidationError::InstrumentMismatch {
+                exchange: self.exchange.clone(),
+                token: self.symbol_token.clone(),
+                instrument: instrument.symbol.clone(),
+            });
+        }
+
+        let quantity = parse::<u64>("quantity", &self.quantity)?.unwrap_or_default();
+        if let Some(lot_size) = instrument.lot_size_value()
+            && quantity % lot_size != 0
+        {
+            return Err(OrderValidationError::LotSize { quantity, lot_size });
+        }
+
+        let Some(tick_size) = instrument.tick_size_value() else {
+            return Ok(());
+        };
+        let prices = [
+            ("price", Some(self.price.clone())),
+            ("trigger_price", self.trigger_price.clone()),
+            ("square_off", self.square_off.clone()),
+            ("stop_loss", self.stop_loss.clone()),
+        ];
+        for (field, value) in prices {
+            if let Some(price) = parse_opt::<f64>(field, &value)? {
+                check_tick(field, price, tick_size)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PlaceOrderReq {
+    /// Validates the order before it is placed, see [`OrderInner::validate`]
+    pub fn validate(&self) -> ValidationResult<()> {
+        if matches!(self.transaction_type, TransactionType::Unknown(_)) {
+            return Err(OrderValidationError::MissingField("transaction_type"));
+        }
+        self.inner.validate()?;
+        self.validate_trigger_price()
+    }
+
+    /// Validates the order including the lot size and tick size of the [`Instrument`]
+    pub fn validate_with(&self, instrument: &Instrument) -> ValidationResult<()> {
+        self.validate()?;
+        self.inner.validate_with(instrument)
+    }
+
+    /// A stop loss buy triggers at or below its limit price, a stop loss sell at or above it
+    fn validate_trigger_price(&self) -> ValidationResult<()> {
+        if self.inner.order_type != OrderType::StopLossLimit {
+            return Ok(());
+        }
+
+        let price = parse::<f64>("price", &self.inner.price)?.unwrap_or_default();
+        let trigger_price =
+            parse_opt::<f64>("trigger_price", &self.inner.trigger_price)?.unwrap_or_default();
+
+        let valid = match self.transaction_type {
+            TransactionType::Buy => trigger_price <= price,
+            TransactionType::Sell => trigger_price >= price,
+            TransactionType::Unknown(_) => true,
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(OrderValidationError::TriggerPrice {
+                transaction_type: self.transaction_type.clone(),
+                price,
+                trigger_price,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        market::Instrument,
+        order::{OrderSetter, PlaceOrderReq},
+        types::{
+            DurationType, ExchangeType, OrderType, OrderVariety, ProductType, TransactionType,
+        },
+    };
+
+    use super::OrderValidationError;
+
+    fn instrument() -> Instrument {
+        Instrument {
+            token: String::from("35003"),
+            symbol: String::from("NIFTY26DEC24FUT"),
+            name: String::from("NIFTY"),
+            expiry: None,
+            strike: String::from("-1.000000"),
+            lot_size: String::from("25"),
+            instrument_type: String::from("FUTIDX"),
+            exch_seg: ExchangeType::NFO,
+            tick_size: String::from("5.000000"),
+        }
+    }
+
+    fn order() -> PlaceOrderReq {
+        PlaceOrderReq::new("NIFTY26DEC24FUT", "35003", TransactionType::Sell)
+            .variety(OrderVariety::StopLoss)
+            .exchange(ExchangeType::NFO)
+            .order_type(OrderType::StopLossLimit)
+            .product_type(ProductType::IntraDay)
+            .duration(DurationType::Day)
+            .quantity(50)
+            .price(23_500.05)
+            .trigger_price(23_510)
+    }
+
+    #[test]
+    fn place_order_validation() {
+        let instrument = instrument();
+        assert_eq!(order().validate_with(&instrument), Ok(()));
+
+        assert_eq!(
+            order().quantity(30).validate_with(&instrument),
+            Err(OrderValidationError::LotSize {
+                quantity: 30,
+                lot_size: 25
+            })
+        );
+        assert!(matches!(
+            order().price(23_500.02).validate_with(&instrument),
+            Err(OrderValidationError::TickSize { field: "price", .. })
+        ));
+        assert!(matches!(
+            order().trigger_price(23_490).validate(),
+            Err(OrderValidationError::TriggerPrice { .. })
+        ));
+        assert_eq!(
+            order().stop_loss(20).validate(),
+            Err(OrderValidationError::RoboOnlyField("stop_loss"))
+        );
+        assert_eq!(
+            PlaceOrderReq::new("NIFTY26DEC24FUT", "35003", TransactionType::Buy).validate(),
+            Err(OrderValidationError::MissingField("variety"))
+        );
+    }
+}
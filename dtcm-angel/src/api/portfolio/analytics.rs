@@ -0,0 +1,316 @@
+use std::{collections::HashMap, io::Write};
+
+use crate::{market::InstrumentMaster, report::write_csv, types::ExchangeType, Result};
+
+use super::Holding;
+
+// Allocation key of the instruments missing from the master or the sector map
+const UNCLASSIFIED: &str = "UNCLASSIFIED";
+
+/// Holding with its settlement quantities and valuation
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HoldingSummary {
+    /// Trading symbol
+    pub trading_symbol: String,
+    /// Exchange of the holding
+    pub exchange: ExchangeType,
+    /// ISIN of the security
+    pub isin: String,
+    /// Symbol token
+    pub symbol_token: String,
+    /// Quantity held, settled and T1
+    pub quantity: u64,
+    /// Quantity bought on the previous day and not settled yet
+    pub t1_quantity: u64,
+    /// Settled quantity
+    pub settled_quantity: u64,
+    /// Quantity pledged as collateral
+    pub pledged_quantity: u64,
+    /// Settled quantity not pledged, free to sell
+    pub free_quantity: u64,
+    /// Average buy price
+    pub average_price: f64,
+    /// Last traded price
+    pub ltp: f64,
+    /// Previous close price
+    pub close: f64,
+    /// Value at the average buy price
+    pub invested: f64,
+    /// Value at the LTP
+    pub market_value: f64,
+    /// Change of the market value since the previous close
+    pub day_change: f64,
+    /// Day change in percent of the previous close value
+    pub day_change_percentage: f64,
+    /// Profit of the holding marked at the LTP
+    pub unrealized_pnl: f64,
+    /// Unrealized profit in percent of the invested value
+    pub unrealized_pnl_percentage: f64,
+    /// Share of the market value of the portfolio
+    pub weight: f64,
+}
+
+/// Returns the ratio in percent, zero for a zero base
+fn percentage(value: f64, base: f64) -> f64 {
+    if base == 0.0 {
+        0.0
+    } else {
+        value / base * 100.0
+    }
+}
+
+impl From<&Holding> for HoldingSummary {
+    fn from(holding: &Holding) -> Self {
+        let quantity = holding.total_quantity() as u64;
+        let settled_quantity = holding.realized_quantity as u64;
+        let pledged_quantity = holding.collateral_quantity.unwrap_or_default() as u64;
+        let invested = holding.average_price * quantity as f64;
+        let market_value = holding.ltp * quantity as f64;
+        let day_change = (holding.ltp - holding.close) * quantity as f64;
+
+        Self {
+            trading_symbol: holding.trading_symbol.clone(),
+            exchange: holding.exchange.clone(),
+            isin: holding.isin.clone(),
+            symbol_token: holding.symbol_token.clone(),
+            quantity,
+            t1_quantity: holding.t1quantity as u64,
+            settled_quantity,
+            pledged_quantity,
+            free_quantity: settled_quantity.saturating_sub(pledged_quantity),
+            average_price: holding.average_price,
+            ltp: holding.ltp,
+            close: holding.close,
+            invested,
+            market_value,
+            day_change,
+            day_change_percentage: percentage(day_change, holding.close * quantity as f64),
+            unrealized_pnl: market_value - invested,
+            unrealized_pnl_percentage: percentage(market_value - invested, invested),
+            weight: 0.0,
+        }
+    }
+}
+
+/// Holdings grouped under an allocation key
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Allocation {
+    /// Symbol, exchange, instrument type or sector of the group
+    pub key: String,
+    /// Number of holdings in the group
+    pub holdings: usize,
+    /// Value at the average buy price
+    pub invested: f64,
+    /// Value at the LTP
+    pub market_value: f64,
+    /// Change of the market value since the previous close
+    pub day_change: f64,
+    /// Profit marked at the LTP
+    pub unrealized_pnl: f64,
+    /// Share of the market value of the portfolio
+    pub weight: f64,
+}
+
+impl Allocation {
+    fn add(&mut self, holding: &HoldingSummary) {
+        self.holdings += 1;
+        self.invested += holding.invested;
+        self.market_value += holding.market_value;
+        self.day_change += holding.day_change;
+        self.unrealized_pnl += holding.unrealized_pnl;
+        self.weight += holding.weight;
+    }
+}
+
+/// Allocation and profit analytics over the holdings, exported as JSON or CSV
+#[derive(Debug, Clone, Serialize)]
+pub struct HoldingsAnalytics {
+    holdings: Vec<HoldingSummary>,
+    total: Allocation,
+}
+
+impl HoldingsAnalytics {
+    /// Returns the analytics of the holdings
+    pub fn new(holdings: &[Holding]) -> Self {
+        let mut holdings: Vec<HoldingSummary> = holdings.iter().map(Into::into).collect();
+        let market_value: f64 = holdings.iter().map(|h| h.market_value).sum();
+        for holding in holdings.iter_mut() {
+            holding.weight = if market_value == 0.0 {
+                0.0
+            } else {
+                holding.market_value / market_value
+            };
+        }
+
+        let mut total = Allocation {
+            key: String::from("TOTAL"),
+            ..Default::default()
+        };
+        holdings.iter().for_each(|h| total.add(h));
+        Self { holdings, total }
+    }
+
+    /// Returns the holdings with their valuation
+    pub fn holdings(&self) -> &[HoldingSummary] {
+        &self.holdings
+    }
+
+    /// Returns the totals of the portfolio
+    pub fn total(&self) -> &Allocation {
+        &self.total
+    }
+
+    /// Groups the holdings by the key, the largest market value first
+    pub fn allocation_by<F>(&self, key: F) -> Vec<Allocation>
+    where
+        F: Fn(&HoldingSummary) -> String,
+    {
+        let mut groups: HashMap<String, Allocation> = HashMap::new();
+        for holding in &self.holdings {
+            let key = key(holding);
+            groups
+                .entry(key.clone())
+                .or_insert_with(|| Allocation {
+                    key,
+                    ..Default::default()
+                })
+                .add(holding);
+        }
+
+        let mut allocations: Vec<_> = groups.into_values().collect();
+        allocations.sort_by(|a, b| {
+            b.market_value
+                .total_cmp(&a.market_value)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        allocations
+    }
+
+    /// Returns the allocation by trading symbol
+    pub fn by_symbol(&self) -> Vec<Allocation> {
+        self.allocation_by(|h| h.trading_symbol.clone())
+    }
+
+    /// Returns the allocation by exchange
+    pub fn by_exchange(&self) -> Vec<Allocation> {
+        self.allocation_by(|h| format!("{:?}", h.exchange))
+    }
+
+    /// Returns the allocation by the instrument type of the master, `EQ` for the cash
+    /// segment instruments listed without a type
+    pub fn by_instrument_type(&self, master: &InstrumentMaster) -> Vec<Allocation> {
+        self.allocation_by(|h| match master.get(&h.exchange, &h.symbol_token) {
+            Some(instrument) if instrument.instrument_type.is_empty() => String::from("EQ"),
+            Some(instrument) => instrument.instrument_type.clone(),
+            None => String::from(UNCLASSIFIED),
+        })
+    }
+
+    /// Returns the allocation by sector, the map gives the sector of the ISIN or of the
+    /// instrument name in the master
+    pub fn by_sector(
+        &self,
+        master: &InstrumentMaster,
+        sectors: &HashMap<String, String>,
+    ) -> Vec<Allocation> {
+        self.allocation_by(|h| {
+            let name = master
+                .get(&h.exchange, &h.symbol_token)
+                .map(|instrument| instrument.name.as_str());
+            sectors
+                .get(&h.isin)
+                .or_else(|| name.and_then(|name| sectors.get(name)))
+                .cloned()
+                .unwrap_or_else(|| String::from(UNCLASSIFIED))
+        })
+    }
+
+    /// Returns the holdings and the totals as JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes the holdings as CSV with a header row
+    pub fn write_csv<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        write_csv(wtr, &self.holdings)
+    }
+
+    /// Writes the allocations as CSV with a header row
+    pub fn write_allocation_csv<W>(wtr: W, allocations: &[Allocation]) -> Result<()>
+    where
+        W: Write,
+    {
+        write_csv(wtr, allocations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        market::{Instrument, InstrumentMaster},
+        portfolio::Holding,
+        types::{ExchangeType, ProductType},
+    };
+
+    use super::HoldingsAnalytics;
+
+    fn holding(symbol: &str, token: &str, quantity: usize, average: f64, ltp: f64) -> Holding {
+        Holding {
+            trading_symbol: symbol.to_string(),
+            exchange: ExchangeType::NSE,
+            isin: format!("INE-{symbol}"),
+            t1quantity: 2,
+            realized_quantity: quantity,
+            quantity,
+            authorized_quantity: 0,
+            product: ProductType::Delivery,
+            collateral_quantity: Some(1),
+            collateral_type: None,
+            haircut: 0.0,
+            average_price: average,
+            ltp,
+            symbol_token: token.to_string(),
+            close: average,
+            profit_and_loss: 0.0,
+            pnl_percentage: 0.0,
+        }
+    }
+
+    #[test]
+    fn holdings_allocation() {
+        let analytics = HoldingsAnalytics::new(&[
+            holding("SBIN-EQ", "3045", 8, 500.0, 600.0),
+            holding("INFY-EQ", "1594", 3, 1500.0, 1400.0),
+        ]);
+
+        let sbin = &analytics.holdings()[0];
+        assert_eq!((sbin.quantity, sbin.free_quantity), (10, 7));
+        assert_eq!(sbin.unrealized_pnl, 1000.0);
+        assert_eq!(analytics.total().market_value, 13000.0);
+        assert_eq!(analytics.total().unrealized_pnl, 500.0);
+
+        let master: InstrumentMaster = serde_json::from_str::<Vec<Instrument>>(
+            r#"[{"token":"3045","symbol":"SBIN-EQ","name":"SBIN","expiry":"","strike":"-1",
+                "lotsize":"1","instrumenttype":"","exch_seg":"NSE","tick_size":"5"}]"#,
+        )
+        .unwrap()
+        .into_iter()
+        .collect();
+        let sectors = HashMap::from([(String::from("SBIN"), String::from("Banks"))]);
+        let by_sector = analytics.by_sector(&master, &sectors);
+        assert_eq!(by_sector[0].key, "UNCLASSIFIED");
+        assert_eq!(by_sector[1].key, "Banks");
+        assert_eq!(by_sector[1].weight, 6000.0 / 13000.0);
+
+        let mut csv = vec![];
+        analytics.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("trading_symbol,exchange,isin,symbol_token,quantity"));
+        assert_eq!(csv.lines().count(), 3);
+    }
+}
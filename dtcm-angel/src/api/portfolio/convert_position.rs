@@ -1,4 +1,9 @@
-use crate::types::{DurationType, ExchangeType, ProductType, TransactionType};
+use crate::{
+    types::{DurationType, ExchangeType, ProductType, TransactionType},
+    Error, Result,
+};
+
+use super::Position;
 
 /// Convert or change a position's margin product
 #[allow(missing_docs)]
@@ -19,34 +24,8 @@ pub struct ConvertPositionReq {
     pub quantity: usize,
     #[serde(rename = "type")]
     pub duration: DurationType,
-    // #[serde(rename = "symboltoken")]
-    // pub symbol_token: String,
-    // #[serde(rename = "symbolname")]
-    // pub symbol_name: String,
-    // #[serde(rename = "instrumenttype")]
-    // pub instrument_type: String,
-    // #[serde(rename = "priceden")]
-    // pub price_den: String,
-    // #[serde(rename = "pricenum")]
-    // pub price_num: String,
-    // #[serde(rename = "genden")]
-    // pub gen_den: String,
-    // #[serde(rename = "gennum")]
-    // pub gen_num: String,
-    // #[serde(rename = "precision")]
-    // pub precision: String,
-    // #[serde(rename = "multiplier")]
-    // pub multiplier: String,
-    // #[serde(rename = "boardlotsize")]
-    // pub board_lot_size: String,
-    // #[serde(rename = "buyqty")]
-    // pub buy_qty: String,
-    // #[serde(rename = "sellqty")]
-    // pub sell_qty: String,
-    // #[serde(rename = "buyamount")]
-    // pub buy_amount: String,
-    // #[serde(rename = "sellamount")]
-    // pub sell_amount: String,
+    #[serde(rename = "symboltoken", skip_serializing_if = "String::is_empty")]
+    pub symbol_token: String,
 }
 
 impl ConvertPositionReq {
@@ -60,6 +39,7 @@ impl ConvertPositionReq {
             old_product_type: Default::default(),
             new_product_type: Default::default(),
             trading_symbol: trading_symbol.into(),
+            symbol_token: String::new(),
             transaction_type: Default::default(),
             quantity,
             duration: Default::default(),
@@ -91,4 +71,86 @@ impl ConvertPositionReq {
         self.duration = duration;
         self
     }
+    /// Sets the symbol_token for the convert position request
+    pub fn symbol_token<T>(mut self, symbol_token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.symbol_token = symbol_token.into();
+        self
+    }
+
+    /// Returns the request converting `quantity` of the open position to the new product type,
+    /// the exchange, token, old product and direction are taken from the position
+    pub fn from_position(
+        position: &Position,
+        new_product_type: ProductType,
+        quantity: usize,
+    ) -> Result<Self> {
+        let symbol = &position.trading_symbol;
+        let transaction_type = match position.net_qty {
+            0 => return Err(Error::PositionNotFound(symbol.clone())),
+            net_qty if net_qty > 0 => TransactionType::Buy,
+            _ => TransactionType::Sell,
+        };
+
+        let open = position.net_qty.unsigned_abs();
+        if quantity == 0 || quantity as u64 > open {
+            return Err(Error::InvalidConversion(
+                symbol.clone(),
+                format!("quantity {quantity} exceeds the open quantity {open}"),
+            ));
+        }
+        if position.product_type == new_product_type {
+            return Err(Error::InvalidConversion(
+                symbol.clone(),
+                format!("already {:?}", new_product_type),
+            ));
+        }
+
+        Ok(Self::new(symbol.as_str(), quantity)
+            .exchange(position.exchange.clone())
+            .symbol_token(position.symbol_token.as_str())
+            .old_product_type(position.product_type.clone())
+            .new_product_type(new_product_type)
+            .transaction_type(transaction_type)
+            .duration(DurationType::Day))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        portfolio::Position,
+        types::{ExchangeType, ProductType, TransactionType},
+        Error,
+    };
+
+    use super::ConvertPositionReq;
+
+    #[test]
+    fn convert_request_from_position() {
+        let position = Position {
+            exchange: ExchangeType::NSE,
+            symbol_token: String::from("3045"),
+            product_type: ProductType::IntraDay,
+            trading_symbol: String::from("SBIN-EQ"),
+            net_qty: -10,
+            ..Default::default()
+        };
+
+        let req = ConvertPositionReq::from_position(&position, ProductType::Delivery, 4).unwrap();
+        assert_eq!(req.symbol_token, "3045");
+        assert_eq!(req.old_product_type, ProductType::IntraDay);
+        assert_eq!(req.transaction_type, TransactionType::Sell);
+
+        assert!(matches!(
+            ConvertPositionReq::from_position(&position, ProductType::Delivery, 11),
+            Err(Error::InvalidConversion(..))
+        ));
+        assert!(matches!(
+            ConvertPositionReq::from_position(&position, ProductType::IntraDay, 10),
+            Err(Error::InvalidConversion(..))
+        ));
+    }
 }
@@ -2,7 +2,7 @@ use crate::types::{ExchangeType, ProductType};
 
 /// Placeholder containing holding information
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[api(GET, Holding)]
 pub struct Holding {
     #[serde(rename = "tradingsymbol")]
@@ -32,3 +32,10 @@ pub struct Holding {
     #[serde(rename = "pnlpercentage")]
     pub pnl_percentage: f64,
 }
+
+impl Holding {
+    /// Returns the quantity held, including the T1 quantity not settled yet
+    pub fn total_quantity(&self) -> usize {
+        self.quantity + self.t1quantity
+    }
+}
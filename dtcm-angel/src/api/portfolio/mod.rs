@@ -9,3 +9,9 @@ pub use convert_position::ConvertPositionReq;
 
 mod all_holdings;
 pub use all_holdings::{AllHoldings, TotalHolding};
+
+mod tracker;
+pub use tracker::{Pnl, PnlUpdate, PortfolioTracker, SymbolPnl};
+
+mod analytics;
+pub use analytics::{Allocation, HoldingSummary, HoldingsAnalytics};
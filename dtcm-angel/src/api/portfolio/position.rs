@@ -1,8 +1,10 @@
+use dtcm_angel_utils::num::serde_number;
+
 use crate::types::{ExchangeType, ProductType};
 
 /// Placeholder containing Position information
 #[allow(missing_docs)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[api(GET, Position)]
 pub struct Position {
     pub exchange: ExchangeType,
@@ -16,68 +18,78 @@ pub struct Position {
     pub symbol_name: String,
     #[serde(rename = "instrumenttype")]
     pub instrument_type: String,
-    #[serde(rename = "priceden")]
-    pub price_den: String,
-    #[serde(rename = "pricenum")]
-    pub price_num: String,
-    #[serde(rename = "genden")]
-    pub gen_den: String,
-    #[serde(rename = "gennum")]
-    pub gen_num: String,
-    #[serde(rename = "precision")]
-    pub precision: String,
-    #[serde(rename = "multiplier")]
-    pub multiplier: String,
-    #[serde(rename = "boardlotsize")]
-    pub board_lot_size: String,
-    #[serde(rename = "buyquantity")]
-    pub buy_quantity: String,
-    #[serde(rename = "sellquantity")]
-    pub sell_quantity: String,
-    #[serde(rename = "buyamount")]
-    pub buy_amount: String,
-    #[serde(rename = "sellamount")]
-    pub sell_amount: String,
+    #[serde(rename = "priceden", deserialize_with = "serde_number")]
+    pub price_den: f64,
+    #[serde(rename = "pricenum", deserialize_with = "serde_number")]
+    pub price_num: f64,
+    #[serde(rename = "genden", deserialize_with = "serde_number")]
+    pub gen_den: f64,
+    #[serde(rename = "gennum", deserialize_with = "serde_number")]
+    pub gen_num: f64,
+    #[serde(rename = "precision", deserialize_with = "serde_number")]
+    pub precision: u32,
+    #[serde(rename = "multiplier", deserialize_with = "serde_number")]
+    pub multiplier: f64,
+    #[serde(rename = "boardlotsize", deserialize_with = "serde_number")]
+    pub board_lot_size: u64,
+    #[serde(rename = "buyqty", alias = "buyquantity", deserialize_with = "serde_number")]
+    pub buy_quantity: u64,
+    #[serde(rename = "sellqty", alias = "sellquantity", deserialize_with = "serde_number")]
+    pub sell_quantity: u64,
+    #[serde(rename = "buyamount", deserialize_with = "serde_number")]
+    pub buy_amount: f64,
+    #[serde(rename = "sellamount", deserialize_with = "serde_number")]
+    pub sell_amount: f64,
     #[serde(rename = "symbolgroup")]
     pub symbol_group: String,
-    #[serde(rename = "strikeprice")]
-    pub strike_price: String,
+    #[serde(rename = "strikeprice", deserialize_with = "serde_number")]
+    pub strike_price: f64,
     #[serde(rename = "optiontype")]
     pub option_type: String,
     #[serde(rename = "expirydate")]
     pub expiry_date: String,
-    #[serde(rename = "lotsize")]
-    pub lot_size: String,
-    #[serde(rename = "cfbuyqty")]
-    pub cf_buy_qty: String,
-    #[serde(rename = "cfsellqty")]
-    pub cf_sell_qty: String,
-    #[serde(rename = "cfbuyamount")]
-    pub cf_buy_amount: String,
-    #[serde(rename = "cfsellamount")]
-    pub cf_sell_amount: String,
-    #[serde(rename = "buyavgprice")]
-    pub buy_average_price: String,
-    #[serde(rename = "sellavgprice")]
-    pub sell_average_price: String,
-    #[serde(rename = "avgnetprice")]
-    pub average_net_price: String,
-    #[serde(rename = "netvalue")]
-    pub net_value: String,
-    #[serde(rename = "netqty")]
-    pub net_qty: String,
-    #[serde(rename = "totalbuyvalue")]
-    pub total_buy_value: String,
-    #[serde(rename = "totalsellvalue")]
-    pub total_sell_value: String,
-    #[serde(rename = "cfbuyavgprice")]
-    pub cf_buy_average_price: String,
-    #[serde(rename = "cfsellavgprice")]
-    pub cf_sell_average_price: String,
-    #[serde(rename = "totalbuyavgprice")]
-    pub total_buy_average_price: String,
-    #[serde(rename = "totalsellavgprice")]
-    pub total_sell_average_price: String,
-    #[serde(rename = "netprice")]
-    pub net_price: String,
+    #[serde(rename = "lotsize", deserialize_with = "serde_number")]
+    pub lot_size: u64,
+    #[serde(rename = "cfbuyqty", deserialize_with = "serde_number")]
+    pub cf_buy_qty: u64,
+    #[serde(rename = "cfsellqty", deserialize_with = "serde_number")]
+    pub cf_sell_qty: u64,
+    #[serde(rename = "cfbuyamount", deserialize_with = "serde_number")]
+    pub cf_buy_amount: f64,
+    #[serde(rename = "cfsellamount", deserialize_with = "serde_number")]
+    pub cf_sell_amount: f64,
+    #[serde(rename = "buyavgprice", deserialize_with = "serde_number")]
+    pub buy_average_price: f64,
+    #[serde(rename = "sellavgprice", deserialize_with = "serde_number")]
+    pub sell_average_price: f64,
+    #[serde(rename = "avgnetprice", deserialize_with = "serde_number")]
+    pub average_net_price: f64,
+    #[serde(rename = "netvalue", deserialize_with = "serde_number")]
+    pub net_value: f64,
+    #[serde(rename = "netqty", deserialize_with = "serde_number")]
+    pub net_qty: i64,
+    #[serde(rename = "totalbuyvalue", deserialize_with = "serde_number")]
+    pub total_buy_value: f64,
+    #[serde(rename = "totalsellvalue", deserialize_with = "serde_number")]
+    pub total_sell_value: f64,
+    #[serde(rename = "cfbuyavgprice", deserialize_with = "serde_number")]
+    pub cf_buy_average_price: f64,
+    #[serde(rename = "cfsellavgprice", deserialize_with = "serde_number")]
+    pub cf_sell_average_price: f64,
+    #[serde(rename = "totalbuyavgprice", deserialize_with = "serde_number")]
+    pub total_buy_average_price: f64,
+    #[serde(rename = "totalsellavgprice", deserialize_with = "serde_number")]
+    pub total_sell_average_price: f64,
+    #[serde(rename = "netprice", deserialize_with = "serde_number")]
+    pub net_price: f64,
+}
+
+impl Position {
+    /// Returns the realized and unrealized profit of the position with the open quantity
+    /// marked at the LTP
+    pub fn pnl(&self, ltp: f64) -> f64 {
+        let sold = self.sell_amount + self.cf_sell_amount;
+        let bought = self.buy_amount + self.cf_buy_amount;
+        sold - bought + self.net_qty as f64 * ltp
+    }
 }
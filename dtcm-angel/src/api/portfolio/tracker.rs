@@ -0,0 +1,550 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use tokio::sync::broadcast;
+
+use crate::{
+    broker::Broker,
+    order::{OrderBook, TradeBook},
+    types::{ExchangeType, ProductType, TransactionType},
+    ws::{Message, SubscriptionExchange},
+    Result,
+};
+
+use super::{Holding, Position};
+
+// P&L updates buffered for the slowest subscriber
+const UPDATES_CAPACITY: usize = 1024;
+
+/// Realized, unrealized and mark to market profit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pnl {
+    /// Profit booked by the closed quantity
+    pub realized: f64,
+    /// Profit of the open quantity marked at the LTP
+    pub unrealized: f64,
+    /// Realized and unrealized profit
+    pub m2m: f64,
+}
+
+impl Pnl {
+    fn new(realized: f64, unrealized: f64) -> Self {
+        Self {
+            realized,
+            unrealized,
+            m2m: realized + unrealized,
+        }
+    }
+}
+
+/// Open quantity and profit of a symbol and product
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolPnl {
+    /// Exchange of the symbol
+    pub exchange: ExchangeType,
+    /// Trading symbol
+    pub trading_symbol: String,
+    /// Symbol token matched against the ticks, empty until known
+    pub symbol_token: String,
+    /// Product of the position
+    pub product_type: ProductType,
+    /// Open quantity, negative when short
+    pub net_qty: i64,
+    /// Average price of the open quantity
+    pub average_price: f64,
+    /// Last traded price, none until marked
+    pub ltp: Option<f64>,
+    /// Profit of the symbol
+    pub pnl: Pnl,
+}
+
+impl SymbolPnl {
+    fn matches(
+        &self,
+        exchange: &ExchangeType,
+        trading_symbol: &str,
+        product: &ProductType,
+    ) -> bool {
+        &self.exchange == exchange
+            && self.trading_symbol == trading_symbol
+            && &self.product_type == product
+    }
+
+    /// Applies a fill with the average cost method, signed quantity is negative for sells
+    fn fill(&mut self, quantity: i64, price: f64) {
+        let mut realized = self.pnl.realized;
+        if self.net_qty == 0 || self.net_qty.signum() == quantity.signum() {
+            let open = self.net_qty.abs() as f64;
+            self.average_price = (self.average_price * open + price * quantity.abs() as f64)
+                / (open + quantity.abs() as f64);
+        } else {
+            let closed = quantity.abs().min(self.net_qty.abs());
+            realized += closed as f64 * (price - self.average_price) * self.net_qty.signum() as f64;
+            if quantity.abs() > self.net_qty.abs() {
+                // reversed to the other side at the fill price
+                self.average_price = price;
+            }
+        }
+        self.net_qty += quantity;
+        if self.net_qty == 0 {
+            self.average_price = 0.0;
+        }
+        self.mark(realized);
+    }
+
+    /// Recomputes the profit with the realized part and the current LTP
+    fn mark(&mut self, realized: f64) {
+        let unrealized = self
+            .ltp
+            .map_or(0.0, |ltp| self.net_qty as f64 * (ltp - self.average_price));
+        self.pnl = Pnl::new(realized, unrealized);
+    }
+}
+
+/// Change of the profit of a symbol with the new totals
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnlUpdate {
+    /// Symbol whose profit changed
+    pub symbol: SymbolPnl,
+    /// Profit of the whole portfolio
+    pub total: Pnl,
+}
+
+/// Portfolio tracker computing the profit per symbol from positions, holdings and fills,
+/// marked to market with the websocket LTPs.
+///
+/// Fills are applied from the trade book, the order updates or both, a quantity is counted
+/// once per order whichever source reports it first. Fills already in the seeded positions
+/// are skipped.
+#[derive(Debug)]
+pub struct PortfolioTracker {
+    state: Mutex<TrackerState>,
+    updates: broadcast::Sender<PnlUpdate>,
+    price_divisor: f64,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    symbols: Vec<SymbolPnl>,
+    fill_ids: HashSet<String>,
+    orders: HashMap<String, OrderFills>,
+    seeds: HashMap<(ExchangeType, String, ProductType), Seed>,
+}
+
+/// Position of the day and holding seeded for a symbol and product, the delivery positions
+/// share the key of the holdings
+#[derive(Debug, Clone, Copy, Default)]
+struct Seed {
+    position_qty: i64,
+    position_price: f64,
+    position_realized: f64,
+    holding_qty: i64,
+    holding_price: f64,
+}
+
+/// Fills of an order seen from the trades and from the order updates
+#[derive(Debug, Default)]
+struct OrderFills {
+    // quantity applied to the positions and its value
+    applied: u64,
+    value: f64,
+    // quantity of the trades seen
+    traded: u64,
+}
+
+impl TrackerState {
+    fn symbol(
+        &mut self,
+        exchange: &ExchangeType,
+        trading_symbol: &str,
+        product: &ProductType,
+    ) -> &mut SymbolPnl {
+        let index = match self
+            .symbols
+            .iter()
+            .position(|s| s.matches(exchange, trading_symbol, product))
+        {
+            Some(index) => index,
+            None => {
+                self.symbols.push(SymbolPnl {
+                    exchange: exchange.clone(),
+                    trading_symbol: trading_symbol.to_string(),
+                    product_type: product.clone(),
+                    ..Default::default()
+                });
+                self.symbols.len() - 1
+            }
+        };
+        &mut self.symbols[index]
+    }
+
+    /// Rebuilds the symbol from its seeds, the position of the day is applied as a fill on
+    /// top of the holding so that its sales realize against the holding cost
+    fn reseed(
+        &mut self,
+        exchange: &ExchangeType,
+        trading_symbol: &str,
+        product: &ProductType,
+        update: impl FnOnce(&mut Seed),
+    ) -> &mut SymbolPnl {
+        let key = (
+            exchange.clone(),
+            trading_symbol.to_string(),
+            product.clone(),
+        );
+        let seed = self.seeds.entry(key).or_default();
+        update(seed);
+        let seed = *seed;
+
+        let symbol = self.symbol(exchange, trading_symbol, product);
+        symbol.net_qty = seed.holding_qty;
+        symbol.average_price = seed.holding_price;
+        symbol.pnl = Pnl::default();
+        if seed.position_qty != 0 {
+            symbol.fill(seed.position_qty, seed.position_price);
+        }
+        symbol.mark(symbol.pnl.realized + seed.position_realized);
+        symbol
+    }
+
+    fn total(&self) -> Pnl {
+        let (realized, unrealized) = self.symbols.iter().fold((0.0, 0.0), |(r, u), s| {
+            (r + s.pnl.realized, u + s.pnl.unrealized)
+        });
+        Pnl::new(realized, unrealized)
+    }
+}
+
+/// Returns the signed quantity of the fill, none for unknown sides
+fn signed(transaction_type: &TransactionType, quantity: u64) -> Option<i64> {
+    match transaction_type {
+        TransactionType::Buy => Some(quantity as i64),
+        TransactionType::Sell => Some(-(quantity as i64)),
+        TransactionType::Unknown(_) => None,
+    }
+}
+
+impl Default for PortfolioTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortfolioTracker {
+    /// Returns a new empty tracker
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::default(),
+            updates: broadcast::channel(UPDATES_CAPACITY).0,
+            price_divisor: 100.0,
+        }
+    }
+
+    /// Sets the divisor converting the feed prices to rupees, 100 (paise) by default
+    pub fn price_divisor(mut self, price_divisor: f64) -> Self {
+        self.price_divisor = price_divisor;
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, TrackerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns a receiver of the P&L updates
+    pub fn subscribe(&self) -> broadcast::Receiver<PnlUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Seeds the positions and the holdings of the broker, the fills of the order book and
+    /// the trade book are taken as already applied
+    pub async fn seed(&self, broker: &dyn Broker) -> Result<()> {
+        let positions = broker.positions().await?;
+        let holdings = broker.holdings().await?;
+        let orders = broker.order_book().await?;
+        let trades = broker.trade_book().await?;
+
+        positions.iter().for_each(|p| self.seed_position(p));
+        holdings.iter().for_each(|h| self.seed_holding(h));
+
+        let mut state = self.state();
+        for order in orders {
+            let fills = state.orders.entry(order.order_id).or_default();
+            fills.applied = order.filled_shares;
+            fills.value = order.average_price * order.filled_shares as f64;
+        }
+        for trade in trades {
+            state.fill_ids.insert(trade.fill_id);
+            let fills = state.orders.entry(trade.order_id).or_default();
+            fills.traded += trade.fill_size;
+            fills.applied = fills.applied.max(fills.traded);
+        }
+        Ok(())
+    }
+
+    /// Seeds the position of the day, replacing the tracked one. A delivery position is
+    /// combined with the seeded holding of the symbol.
+    pub fn seed_position(&self, position: &Position) {
+        // realized part of Position::pnl, the open quantity is marked at the average price
+        let realized = position.sell_amount + position.cf_sell_amount
+            - position.buy_amount
+            - position.cf_buy_amount
+            + position.net_qty as f64 * position.average_net_price;
+
+        let mut state = self.state();
+        let symbol = state.reseed(
+            &position.exchange,
+            &position.trading_symbol,
+            &position.product_type,
+            |seed| {
+                seed.position_qty = position.net_qty;
+                seed.position_price = position.average_net_price;
+                seed.position_realized = realized;
+            },
+        );
+        symbol.symbol_token = position.symbol_token.clone();
+    }
+
+    /// Seeds the holding, replacing the tracked one. The holding is combined with the seeded
+    /// delivery position of the day of the symbol.
+    pub fn seed_holding(&self, holding: &Holding) {
+        let mut state = self.state();
+        let symbol = state.symbol(&holding.exchange, &holding.trading_symbol, &holding.product);
+        symbol.ltp = Some(holding.ltp);
+        let symbol = state.reseed(
+            &holding.exchange,
+            &holding.trading_symbol,
+            &holding.product,
+            |seed| {
+                seed.holding_qty = holding.total_quantity() as i64;
+                seed.holding_price = holding.average_price;
+            },
+        );
+        symbol.symbol_token = holding.symbol_token.clone();
+    }
+
+    /// Applies the trade unless its fill was already applied
+    pub fn apply_trade(&self, trade: &TradeBook) -> Option<PnlUpdate> {
+        signed(&trade.transaction_type, trade.fill_size)?;
+        let mut state = self.state();
+        if !state.fill_ids.insert(trade.fill_id.clone()) {
+            return None;
+        }
+
+        let fills = state.orders.entry(trade.order_id.clone()).or_default();
+        fills.traded += trade.fill_size;
+        if fills.traded <= fills.applied {
+            // already applied from the order updates
+            return None;
+        }
+        let size = (fills.traded - fills.applied).min(trade.fill_size);
+        fills.applied = fills.traded;
+        fills.value += trade.fill_price * size as f64;
+        let quantity = signed(&trade.transaction_type, size)?;
+
+        let symbol = state.symbol(&trade.exchange, &trade.trading_symbol, &trade.product_type);
+        symbol.fill(quantity, trade.fill_price);
+        let symbol = symbol.clone();
+        Some(self.publish(&state, symbol))
+    }
+
+    /// Applies the quantity filled since the last update of the order
+    pub fn apply_order_update(&self, order: &OrderBook) -> Option<PnlUpdate> {
+        signed(&order.transaction_type, order.filled_shares)?;
+        let mut state = self.state();
+        let fills = state.orders.entry(order.order_id.clone()).or_default();
+        if order.filled_shares <= fills.applied {
+            return None;
+        }
+
+        let quantity = order.filled_shares - fills.applied;
+        let total_value = order.average_price * order.filled_shares as f64;
+        let price = (total_value - fills.value) / quantity as f64;
+        fills.applied = order.filled_shares;
+        fills.value = total_value;
+        let quantity = signed(&order.transaction_type, quantity)?;
+
+        let symbol = state.symbol(&order.exchange, &order.trading_symbol, &order.product_type);
+        if symbol.symbol_token.is_empty() {
+            symbol.symbol_token = order.symbol_token.clone();
+        }
+        symbol.fill(quantity, price);
+        let symbol = symbol.clone();
+        Some(self.publish(&state, symbol))
+    }
+
+    /// Marks the symbols of the token at the LTP
+    pub fn update_price(
+        &self,
+        exchange: SubscriptionExchange,
+        token: &str,
+        ltp: f64,
+    ) -> Vec<PnlUpdate> {
+        let mut state = self.state();
+        let mut marked = vec![];
+        for symbol in state.symbols.iter_mut() {
+            if symbol.symbol_token == token
+                && SubscriptionExchange::try_from(&symbol.exchange).ok() == Some(exchange)
+                && symbol.ltp != Some(ltp)
+            {
+                symbol.ltp = Some(ltp);
+                symbol.mark(symbol.pnl.realized);
+                marked.push(symbol.clone());
+            }
+        }
+        marked
+            .into_iter()
+            .map(|symbol| self.publish(&state, symbol))
+            .collect()
+    }
+
+    /// Marks the symbols of the tick at its LTP
+    pub fn on_tick(&self, message: &Message) -> Vec<PnlUpdate> {
+        let ltp = message.last_traded_price as f64 / self.price_divisor;
+        self.update_price(message.exchange, message.token.as_str(), ltp)
+    }
+
+    /// Returns the profit of the tracked symbols
+    pub fn symbols(&self) -> Vec<SymbolPnl> {
+        self.state().symbols.clone()
+    }
+
+    /// Returns the profit of the symbol summed over its products
+    pub fn symbol(&self, exchange: &ExchangeType, trading_symbol: &str) -> Pnl {
+        let state = self.state();
+        let (realized, unrealized) = state
+            .symbols
+            .iter()
+            .filter(|s| &s.exchange == exchange && s.trading_symbol == trading_symbol)
+            .fold((0.0, 0.0), |(r, u), s| {
+                (r + s.pnl.realized, u + s.pnl.unrealized)
+            });
+        Pnl::new(realized, unrealized)
+    }
+
+    /// Returns the profit of the whole portfolio
+    pub fn total(&self) -> Pnl {
+        self.state().total()
+    }
+
+    fn publish(&self, state: &TrackerState, symbol: SymbolPnl) -> PnlUpdate {
+        let update = PnlUpdate {
+            symbol,
+            total: state.total(),
+        };
+        // no subscriber is not an error
+        let _ = self.updates.send(update.clone());
+        update
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        order::{OrderBook, TradeBook},
+        portfolio::{Holding, Position},
+        types::{ExchangeType, ProductType, TransactionType},
+        ws::SubscriptionExchange,
+    };
+
+    use super::{Pnl, PortfolioTracker};
+
+    fn trade(fill_id: &str, transaction_type: TransactionType, size: u64, price: f64) -> TradeBook {
+        TradeBook {
+            exchange: ExchangeType::NSE,
+            product_type: ProductType::IntraDay,
+            trading_symbol: String::from("SBIN-EQ"),
+            transaction_type,
+            fill_price: price,
+            fill_size: size,
+            order_id: format!("order-{fill_id}"),
+            fill_id: String::from(fill_id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tracks_realized_and_unrealized_pnl() {
+        let tracker = PortfolioTracker::new();
+        let mut updates = tracker.subscribe();
+        tracker.seed_position(&Position {
+            exchange: ExchangeType::NSE,
+            symbol_token: String::from("3045"),
+            product_type: ProductType::IntraDay,
+            trading_symbol: String::from("SBIN-EQ"),
+            ..Default::default()
+        });
+
+        tracker.apply_trade(&trade("1", TransactionType::Buy, 10, 500.0));
+        tracker.apply_trade(&trade("2", TransactionType::Buy, 10, 510.0));
+        // replayed fill
+        assert!(tracker
+            .apply_trade(&trade("2", TransactionType::Buy, 10, 510.0))
+            .is_none());
+        tracker.apply_trade(&trade("3", TransactionType::Sell, 5, 520.0));
+
+        let update = tracker.update_price(SubscriptionExchange::NSECM, "3045", 515.0);
+        assert_eq!(update.len(), 1);
+        assert_eq!(update[0].symbol.net_qty, 15);
+        assert_eq!(update[0].total, Pnl::new(75.0, 150.0));
+
+        // order update carrying the cumulative fill of a new sell order
+        let order = OrderBook {
+            order_id: String::from("order-4"),
+            exchange: ExchangeType::NSE,
+            product_type: ProductType::IntraDay,
+            trading_symbol: String::from("SBIN-EQ"),
+            transaction_type: TransactionType::Sell,
+            filled_shares: 15,
+            average_price: 516.0,
+            ..Default::default()
+        };
+        assert!(tracker.apply_order_update(&order).is_some());
+        assert!(tracker.apply_order_update(&order).is_none());
+        // trade of the same fill reported after the order update
+        let mut late = trade("4", TransactionType::Sell, 15, 516.0);
+        late.order_id = String::from("order-4");
+        assert!(tracker.apply_trade(&late).is_none());
+        assert_eq!(tracker.total(), Pnl::new(240.0, 0.0));
+        assert_eq!(updates.try_recv().unwrap().total, Pnl::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn delivery_positions_combine_with_holdings() {
+        let tracker = PortfolioTracker::new();
+        let position = |net_qty: i64, price: f64| Position {
+            exchange: ExchangeType::NSE,
+            symbol_token: String::from("3045"),
+            product_type: ProductType::Delivery,
+            trading_symbol: String::from("SBIN-EQ"),
+            net_qty,
+            average_net_price: price,
+            buy_amount: net_qty.max(0) as f64 * price,
+            sell_amount: -net_qty.min(0) as f64 * price,
+            ..Default::default()
+        };
+        tracker.seed_position(&position(10, 600.0));
+        tracker.seed_holding(&Holding {
+            trading_symbol: String::from("SBIN-EQ"),
+            exchange: ExchangeType::NSE,
+            symbol_token: String::from("3045"),
+            product: ProductType::Delivery,
+            quantity: 8,
+            t1quantity: 2,
+            average_price: 500.0,
+            ltp: 620.0,
+            ..Default::default()
+        });
+
+        let symbols = tracker.symbols();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].net_qty, 20);
+        assert_eq!(tracker.total(), Pnl::new(0.0, 1_400.0));
+
+        // selling part of the holding realizes against its cost
+        tracker.seed_position(&position(-4, 650.0));
+        assert_eq!(tracker.symbols()[0].net_qty, 6);
+        assert_eq!(tracker.total(), Pnl::new(600.0, 720.0));
+    }
+}
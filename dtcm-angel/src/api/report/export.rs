@@ -0,0 +1,17 @@
+use std::io::Write;
+
+use crate::Result;
+
+/// Writes the rows as CSV with a header row, shared by the reports
+pub(crate) fn write_csv<W, T>(wtr: W, rows: &[T]) -> Result<()>
+where
+    W: Write,
+    T: serde::Serialize,
+{
+    let mut wtr = csv::Writer::from_writer(wtr);
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
@@ -0,0 +1,5 @@
+mod export;
+pub(crate) use export::write_csv;
+
+mod snapshot;
+pub use snapshot::{DailySnapshot, PositionChange, SnapshotDiff, SNAPSHOT_VERSION};
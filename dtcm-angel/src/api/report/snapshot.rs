@@ -0,0 +1,345 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use dtcm_angel_utils::date::ist;
+use log::info;
+
+use crate::{
+    broker::Broker,
+    funds::Rms,
+    order::{OrderBook, TradeBook},
+    portfolio::{Holding, Position},
+    types::{ExchangeType, ProductType},
+    Error, Result,
+};
+
+use super::write_csv;
+
+/// Version of the snapshot layout, bumped on incompatible changes
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// File of the snapshot metadata in a CSV bundle
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// End of day export of the order book, trade book, positions, holdings and funds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySnapshot {
+    /// Version of the snapshot layout
+    pub version: u32,
+    /// Trading date the snapshot belongs to
+    pub trading_date: NaiveDate,
+    /// Exchange time the snapshot was collected at
+    pub taken_at: NaiveDateTime,
+    /// Orders of the day
+    pub orders: Vec<OrderBook>,
+    /// Trades of the day
+    pub trades: Vec<TradeBook>,
+    /// Positions of the day
+    pub positions: Vec<Position>,
+    /// Holdings
+    pub holdings: Vec<Holding>,
+    /// Funds and margins
+    pub rms: Rms,
+}
+
+/// Metadata of the snapshot written next to the CSV files
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    trading_date: NaiveDate,
+    taken_at: NaiveDateTime,
+}
+
+/// Net quantity change of a position between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionChange {
+    /// Exchange of the position
+    pub exchange: ExchangeType,
+    /// Trading symbol
+    pub trading_symbol: String,
+    /// Product of the position
+    pub product_type: ProductType,
+    /// Net quantity in the older snapshot, zero if the position was missing
+    pub old_net_qty: i64,
+    /// Net quantity in the newer snapshot, zero if the position was closed
+    pub new_net_qty: i64,
+}
+
+/// Difference between two snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    /// Trades of the newer snapshot missing from the older one
+    pub new_trades: Vec<TradeBook>,
+    /// Positions whose net quantity changed
+    pub changed_positions: Vec<PositionChange>,
+    /// Change of the available cash
+    pub cash_movement: f64,
+    /// Change of the net funds
+    pub net_movement: f64,
+}
+
+impl DailySnapshot {
+    /// Collects the five reports of the broker concurrently. The trading date is the date of
+    /// the latest order timestamp, or the current IST date when there is no order, see
+    /// [`DailySnapshot::collect_for`] to set it.
+    pub async fn collect(broker: &dyn Broker) -> Result<Self> {
+        Self::collect_with(broker, None).await
+    }
+
+    /// Collects the five reports of the broker concurrently for the trading date, e.g. when
+    /// the snapshot of the day is taken after midnight
+    pub async fn collect_for(broker: &dyn Broker, trading_date: NaiveDate) -> Result<Self> {
+        Self::collect_with(broker, Some(trading_date)).await
+    }
+
+    async fn collect_with(broker: &dyn Broker, trading_date: Option<NaiveDate>) -> Result<Self> {
+        let (orders, trades, positions, holdings, rms) = tokio::try_join!(
+            broker.order_book(),
+            broker.trade_book(),
+            broker.positions(),
+            broker.holdings(),
+            broker.rms_limit(),
+        )?;
+
+        let taken_at = Utc::now().with_timezone(&ist()).naive_local();
+        let trading_date = trading_date
+            .or_else(|| orders_date(&orders))
+            .unwrap_or(taken_at.date());
+        info!(
+            "Collected the snapshot of {trading_date} with {} orders and {} trades",
+            orders.len(),
+            trades.len()
+        );
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            trading_date,
+            taken_at,
+            orders,
+            trades,
+            positions,
+            holdings,
+            rms,
+        })
+    }
+
+    /// Writes the snapshot as JSON
+    pub fn write_json<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        Ok(serde_json::to_writer_pretty(wtr, self)?)
+    }
+
+    /// Reads a snapshot written by [`DailySnapshot::write_json`]
+    pub fn read_json<R>(rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let snapshot: Self = serde_json::from_reader(rdr)?;
+        check_version(snapshot.version)?;
+        Ok(snapshot)
+    }
+
+    /// Writes the snapshot as a directory of CSV files, one per report, with a JSON manifest
+    pub fn write_csv_bundle<P>(&self, dir: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let manifest = Manifest {
+            version: self.version,
+            trading_date: self.trading_date,
+            taken_at: self.taken_at,
+        };
+        serde_json::to_writer_pretty(File::create(dir.join(MANIFEST_FILE))?, &manifest)?;
+
+        let create = |file: &str| File::create(dir.join(file)).map(BufWriter::new);
+        write_csv(create("orders.csv")?, &self.orders)?;
+        write_csv(create("trades.csv")?, &self.trades)?;
+        write_csv(create("positions.csv")?, &self.positions)?;
+        write_csv(create("holdings.csv")?, &self.holdings)?;
+        write_csv(create("rms.csv")?, std::slice::from_ref(&self.rms))
+    }
+
+    /// Reads a snapshot written by [`DailySnapshot::write_csv_bundle`]
+    pub fn read_csv_bundle<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let manifest: Manifest =
+            serde_json::from_reader(BufReader::new(File::open(dir.join(MANIFEST_FILE))?))?;
+        check_version(manifest.version)?;
+
+        let rms = read_csv(dir.join("rms.csv"))?
+            .pop()
+            .ok_or_else(|| std::io::Error::other("rms.csv is empty"))?;
+        Ok(Self {
+            version: manifest.version,
+            trading_date: manifest.trading_date,
+            taken_at: manifest.taken_at,
+            orders: read_csv(dir.join("orders.csv"))?,
+            trades: read_csv(dir.join("trades.csv"))?,
+            positions: read_csv(dir.join("positions.csv"))?,
+            holdings: read_csv(dir.join("holdings.csv"))?,
+            rms,
+        })
+    }
+
+    /// Returns the changes from this snapshot to the newer one
+    pub fn diff(&self, newer: &DailySnapshot) -> SnapshotDiff {
+        let fill_ids: HashSet<&str> = self.trades.iter().map(|t| t.fill_id.as_str()).collect();
+        let new_trades = newer
+            .trades
+            .iter()
+            .filter(|t| !fill_ids.contains(t.fill_id.as_str()))
+            .cloned()
+            .collect();
+
+        // net quantity keyed by exchange, symbol and product, in the order first seen
+        let mut keys = vec![];
+        let mut quantities: HashMap<_, (i64, i64)> = HashMap::new();
+        for (position, newer) in self
+            .positions
+            .iter()
+            .map(|p| (p, false))
+            .chain(newer.positions.iter().map(|p| (p, true)))
+        {
+            let key = (
+                position.exchange.clone(),
+                position.trading_symbol.clone(),
+                position.product_type.clone(),
+            );
+            let entry = quantities.entry(key.clone()).or_insert_with(|| {
+                keys.push(key);
+                (0, 0)
+            });
+            if newer {
+                entry.1 += position.net_qty;
+            } else {
+                entry.0 += position.net_qty;
+            }
+        }
+
+        let changed_positions = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (old_net_qty, new_net_qty) = quantities[&key];
+                (old_net_qty != new_net_qty).then_some(PositionChange {
+                    exchange: key.0,
+                    trading_symbol: key.1,
+                    product_type: key.2,
+                    old_net_qty,
+                    new_net_qty,
+                })
+            })
+            .collect();
+
+        SnapshotDiff {
+            new_trades,
+            changed_positions,
+            cash_movement: newer.rms.available_cash - self.rms.available_cash,
+            net_movement: newer.rms.net - self.rms.net,
+        }
+    }
+}
+
+/// Rejects snapshots written by a newer version of the crate
+fn check_version(version: u32) -> Result<()> {
+    if version > SNAPSHOT_VERSION {
+        return Err(Error::UnsupportedSnapshotVersion(version));
+    }
+    Ok(())
+}
+
+/// Returns the date of the latest exchange or update time of the orders, the trades only
+/// carry the time of their fill
+fn orders_date(orders: &[OrderBook]) -> Option<NaiveDate> {
+    orders
+        .iter()
+        .flat_map(|order| [order.exch_time, order.update_time])
+        .flatten()
+        .max()
+        .map(|time| time.date())
+}
+
+fn read_csv<P, T>(path: P) -> Result<Vec<T>>
+where
+    P: AsRef<Path>,
+    T: serde::de::DeserializeOwned,
+{
+    let mut rdr = csv::Reader::from_path(path)?;
+    let rows = rdr.deserialize().collect::<csv::Result<Vec<T>>>()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use chrono::NaiveDate;
+
+    use crate::{
+        broker::{OrderApi, PaperBroker},
+        order::{Order, OrderBook},
+        types::ExchangeType,
+        ws::SubscriptionExchange,
+    };
+
+    use super::{orders_date, DailySnapshot};
+
+    #[tokio::test]
+    async fn snapshot_round_trip_and_diff() {
+        let broker = PaperBroker::new(10_000.0);
+        broker.update_price(SubscriptionExchange::NSECM, "3045", 500.0);
+        let before = DailySnapshot::collect(&broker).await.unwrap();
+
+        let order = Order::market()
+            .instrument(ExchangeType::NSE, "SBIN-EQ", "3045")
+            .buy(4);
+        broker.place_order(&order.into()).await.unwrap();
+        broker.update_price(SubscriptionExchange::NSECM, "3045", 501.0);
+        let after = DailySnapshot::collect(&broker).await.unwrap();
+
+        let mut json = vec![];
+        after.write_json(&mut json).unwrap();
+        let after = DailySnapshot::read_json(json.as_slice()).unwrap();
+
+        let dir = env::temp_dir().join(format!("dtcm-snapshot-{}", std::process::id()));
+        after.write_csv_bundle(&dir).unwrap();
+        let after = DailySnapshot::read_csv_bundle(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.new_trades.len(), 1);
+        assert_eq!(diff.changed_positions.len(), 1);
+        assert_eq!(diff.changed_positions[0].new_net_qty, 4);
+        assert_eq!(diff.cash_movement, -2000.0);
+        assert_eq!(after.orders.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot_trading_date() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 12, d).unwrap();
+        let order = |d| OrderBook {
+            update_time: day(d).and_hms_opt(15, 20, 0),
+            ..Default::default()
+        };
+        assert_eq!(
+            orders_date(&[order(26), order(27), OrderBook::default()]),
+            Some(day(27))
+        );
+        assert_eq!(orders_date(&[]), None);
+
+        let broker = PaperBroker::new(10_000.0);
+        let snapshot = DailySnapshot::collect_for(&broker, day(27)).await.unwrap();
+        assert_eq!(snapshot.trading_date, day(27));
+    }
+}
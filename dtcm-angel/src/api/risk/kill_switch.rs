@@ -0,0 +1,469 @@
+use futures_util::future::join_all;
+use log::{error, info, warn};
+
+use crate::{
+    order::{
+        CancelOrderReq, CancelOrderRes, Order, OrderBook, OrderState, PlaceOrderReq, PlaceOrderRes,
+        RateLimiter,
+    },
+    portfolio::Position,
+    types::{ExchangeType, OrderVariety, ProductType},
+    Error, Result, SmartConnect,
+};
+
+// Default number of requests sent per second, half of the broker limit
+const DEFAULT_ORDERS_PER_SECOND: u32 = 10;
+
+// Retries of an ambiguous square off order, see `SmartConnect::place_order_once`
+const SQUARE_OFF_RETRIES: usize = 2;
+
+/// Emergency stop cancelling the open orders and squaring off the positions, optionally
+/// restricted to some exchanges, product types or symbols
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    /// Exchanges to act on, all when empty
+    pub exchanges: Vec<ExchangeType>,
+    /// Product types to act on, all when empty
+    pub product_types: Vec<ProductType>,
+    /// Trading symbols to act on, all when empty
+    pub symbols: Vec<String>,
+    /// Number of requests sent per second
+    pub orders_per_second: u32,
+}
+
+impl Default for KillSwitch {
+    fn default() -> Self {
+        Self {
+            exchanges: vec![],
+            product_types: vec![],
+            symbols: vec![],
+            orders_per_second: DEFAULT_ORDERS_PER_SECOND,
+        }
+    }
+}
+
+impl KillSwitch {
+    /// Returns a new instance for [`KillSwitch`] acting on every order and position
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the kill switch to the exchange, can be called multiple times
+    pub fn exchange(mut self, exchange: ExchangeType) -> Self {
+        self.exchanges.push(exchange);
+        self
+    }
+
+    /// Restricts the kill switch to the product type, can be called multiple times
+    pub fn product_type(mut self, product_type: ProductType) -> Self {
+        self.product_types.push(product_type);
+        self
+    }
+
+    /// Restricts the kill switch to the trading symbol, can be called multiple times
+    pub fn symbol<S>(mut self, trading_symbol: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.symbols.push(trading_symbol.into());
+        self
+    }
+
+    /// Sets the number of requests sent per second
+    pub fn orders_per_second(mut self, orders_per_second: u32) -> Self {
+        self.orders_per_second = orders_per_second;
+        self
+    }
+
+    /// Returns true if the instrument passes the filters
+    pub fn matches(
+        &self,
+        exchange: &ExchangeType,
+        product_type: &ProductType,
+        trading_symbol: &str,
+    ) -> bool {
+        (self.exchanges.is_empty() || self.exchanges.contains(exchange))
+            && (self.product_types.is_empty() || self.product_types.contains(product_type))
+            && (self.symbols.is_empty() || self.symbols.iter().any(|s| s == trading_symbol))
+    }
+
+    /// Returns true if the order book entry is still working and passes the filters. The
+    /// legs of bracket and cover orders are left to the exit of their position.
+    fn cancels(&self, order: &OrderBook) -> bool {
+        is_working(order)
+            && order.parent_order_id.is_empty()
+            && self.matches(&order.exchange, &order.product_type, &order.trading_symbol)
+    }
+}
+
+/// Returns true if the order book entry is still working
+fn is_working(order: &OrderBook) -> bool {
+    OrderState::from_order_book(order).is_some_and(|state| !state.is_terminal())
+}
+
+/// How the [`KillSwitch`] closes a position
+#[derive(Debug)]
+enum Exit {
+    /// Market order closing the net quantity
+    Order(Box<PlaceOrderReq>),
+    /// Exit of the bracket or cover orders, as variety and parent order ID
+    Parents(Vec<(OrderVariety, String)>),
+}
+
+/// Returns how the position is closed, none for a flat position. Positions with working
+/// bracket or cover order legs are closed by exiting their parent orders, as the broker
+/// does not accept a regular order against them.
+fn position_exit(position: &Position, orders: &[OrderBook]) -> Option<Exit> {
+    if position.net_qty == 0 {
+        return None;
+    }
+
+    let mut parents: Vec<(OrderVariety, String)> = vec![];
+    let legs = orders.iter().filter(|order| {
+        is_working(order)
+            && !order.parent_order_id.is_empty()
+            && order.exchange == position.exchange
+            && order.product_type == position.product_type
+            && order.trading_symbol == position.trading_symbol
+    });
+    for leg in legs {
+        let parent = (leg.variety.clone(), leg.parent_order_id.clone());
+        if !parents.contains(&parent) {
+            parents.push(parent);
+        }
+    }
+    if !parents.is_empty() || position.product_type == ProductType::Bo {
+        return Some(Exit::Parents(parents));
+    }
+
+    square_off_order(
+        &position.exchange,
+        &position.product_type,
+        &position.trading_symbol,
+        &position.symbol_token,
+        position.net_qty,
+    )
+    .map(|order| Exit::Order(Box::new(order)))
+}
+
+/// Returns the market order closing the net quantity, none for a flat position
+fn square_off_order(
+    exchange: &ExchangeType,
+    product_type: &ProductType,
+    trading_symbol: &str,
+    symbol_token: &str,
+    net_qty: i64,
+) -> Option<PlaceOrderReq> {
+    let order = Order::market()
+        .product_type(product_type.clone())
+        .instrument(exchange.clone(), trading_symbol, symbol_token);
+
+    match net_qty {
+        0 => None,
+        qty if qty > 0 => Some(order.sell(qty.unsigned_abs()).into()),
+        qty => Some(order.buy(qty.unsigned_abs()).into()),
+    }
+}
+
+/// Cancellation of an open order by the [`KillSwitch`]
+#[derive(Debug)]
+pub struct OrderCancellation {
+    /// Order ID
+    pub order_id: String,
+    /// Order variety
+    pub variety: OrderVariety,
+    /// Trading symbol of the order
+    pub trading_symbol: String,
+    /// Cancellation result
+    pub result: Result<CancelOrderRes>,
+}
+
+/// Square off of a position by the [`KillSwitch`]
+#[derive(Debug)]
+pub struct PositionSquareOff {
+    /// Exchange of the position
+    pub exchange: ExchangeType,
+    /// Product type of the position
+    pub product_type: ProductType,
+    /// Trading symbol of the position
+    pub trading_symbol: String,
+    /// Net quantity squared off, positive for a long position
+    pub net_qty: i64,
+    /// Placement result of the closing order, or exit result of the bracket or cover order
+    pub result: Result<PlaceOrderRes>,
+}
+
+/// Outcome of the [`KillSwitch`]
+#[derive(Debug, Default)]
+pub struct KillSwitchReport {
+    /// Failure to fetch the order book, no order was cancelled
+    pub order_book_error: Option<Error>,
+    /// Failure to fetch the positions, no position was squared off
+    pub positions_error: Option<Error>,
+    /// Cancelled orders
+    pub cancellations: Vec<OrderCancellation>,
+    /// Squared off positions
+    pub square_offs: Vec<PositionSquareOff>,
+}
+
+impl KillSwitchReport {
+    /// Returns true if every order was cancelled and every position squared off
+    pub fn is_success(&self) -> bool {
+        self.order_book_error.is_none()
+            && self.positions_error.is_none()
+            && self.cancellations.iter().all(|c| c.result.is_ok())
+            && self.square_offs.iter().all(|s| s.result.is_ok())
+    }
+
+    /// Returns the cancellations which failed
+    pub fn failed_cancellations(&self) -> impl Iterator<Item = &OrderCancellation> {
+        self.cancellations.iter().filter(|c| c.result.is_err())
+    }
+
+    /// Returns the square offs which failed
+    pub fn failed_square_offs(&self) -> impl Iterator<Item = &PositionSquareOff> {
+        self.square_offs.iter().filter(|s| s.result.is_err())
+    }
+}
+
+impl SmartConnect {
+    /// Cancels every open and trigger pending order, then squares off every open position
+    /// with market orders, both restricted by the filters of the [`KillSwitch`]. Positions
+    /// of bracket and cover orders are squared off by exiting their parent orders. The
+    /// exits bypass the checks of the [`RiskManager`](crate::risk::RiskManager).
+    pub async fn kill_switch(&self, kill_switch: &KillSwitch) -> KillSwitchReport {
+        let limiter = RateLimiter::new(kill_switch.orders_per_second);
+        let mut report = KillSwitchReport::default();
+
+        warn!("Kill switch triggered: {kill_switch:?}");
+
+        let orders = match self.order_book().await {
+            Ok(orders) => {
+                report.cancellations = join_all(
+                    orders
+                        .iter()
+                        .filter(|order| kill_switch.cancels(order))
+                        .map(|order| self.kill_order(&limiter, order.clone())),
+                )
+                .await;
+                orders
+            }
+            Err(e) => {
+                error!("Kill switch failed to fetch the order book: {e}");
+                report.order_book_error = Some(e);
+                vec![]
+            }
+        };
+
+        match self.positions().await {
+            Ok(positions) => {
+                report.square_offs = join_all(
+                    positions
+                        .iter()
+                        .filter(|position| {
+                            kill_switch.matches(
+                                &position.exchange,
+                                &position.product_type,
+                                &position.trading_symbol,
+                            )
+                        })
+                        .filter_map(|position| {
+                            position_exit(position, &orders)
+                                .map(|exit| self.square_off(&limiter, position, exit))
+                        }),
+                )
+                .await;
+            }
+            Err(e) => {
+                error!("Kill switch failed to fetch the positions: {e}");
+                report.positions_error = Some(e);
+            }
+        }
+
+        info!(
+            "Kill switch cancelled {} orders and squared off {} positions, {} failed",
+            report.cancellations.len(),
+            report.square_offs.len(),
+            report.failed_cancellations().count() + report.failed_square_offs().count()
+        );
+
+        report
+    }
+
+    async fn kill_order(&self, limiter: &RateLimiter, order: OrderBook) -> OrderCancellation {
+        limiter.acquire().await;
+        let cancel_req = CancelOrderReq::new(order.variety.clone(), &order.order_id);
+        let result = self.cancel_order(&cancel_req).await;
+        if let Err(e) = &result {
+            error!("Kill switch failed to cancel order {}: {e}", order.order_id);
+        }
+
+        OrderCancellation {
+            order_id: order.order_id,
+            variety: order.variety,
+            trading_symbol: order.trading_symbol,
+            result,
+        }
+    }
+
+    async fn square_off(
+        &self,
+        limiter: &RateLimiter,
+        position: &Position,
+        exit: Exit,
+    ) -> PositionSquareOff {
+        let result = match exit {
+            Exit::Order(order) => {
+                limiter.acquire().await;
+                self.place_order_once_with(&order, SQUARE_OFF_RETRIES, false)
+                    .await
+            }
+            Exit::Parents(parents) => self.exit_parents(limiter, position, parents).await,
+        };
+        if let Err(e) = &result {
+            error!(
+                "Kill switch failed to square off {} {}: {e}",
+                position.net_qty, position.trading_symbol
+            );
+        }
+
+        PositionSquareOff {
+            exchange: position.exchange.clone(),
+            product_type: position.product_type.clone(),
+            trading_symbol: position.trading_symbol.clone(),
+            net_qty: position.net_qty,
+            result,
+        }
+    }
+
+    /// Exits the bracket or cover orders of the position, returns the first failure or the
+    /// last exit
+    async fn exit_parents(
+        &self,
+        limiter: &RateLimiter,
+        position: &Position,
+        parents: Vec<(OrderVariety, String)>,
+    ) -> Result<PlaceOrderRes> {
+        let mut exits = vec![];
+        for (variety, parent_order_id) in parents {
+            limiter.acquire().await;
+            let cancel_req = CancelOrderReq::new(variety, &parent_order_id);
+            let exit = self.cancel_order(&cancel_req).await;
+            if let Err(e) = &exit {
+                error!("Kill switch failed to exit order {parent_order_id}: {e}");
+            }
+            exits.push(exit.map(|res| PlaceOrderRes {
+                script: position.trading_symbol.clone(),
+                order_id: Some(res.order_id),
+                unique_order_id: res.unique_order_id,
+            }));
+        }
+
+        match exits.iter().position(Result::is_err) {
+            Some(failed) => exits.swap_remove(failed),
+            None => exits
+                .pop()
+                .unwrap_or_else(|| Err(Error::BracketNotFound(position.trading_symbol.clone()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        order::OrderBook,
+        portfolio::Position,
+        types::{
+            ExchangeType, OrderStatusKind, OrderType, OrderVariety, ProductType, TransactionType,
+        },
+    };
+
+    use super::{position_exit, square_off_order, Exit, KillSwitch};
+
+    fn order(order_status: OrderStatusKind, exchange: ExchangeType) -> OrderBook {
+        OrderBook {
+            order_id: String::from("240101000000001"),
+            trading_symbol: String::from("INFY-EQ"),
+            product_type: ProductType::IntraDay,
+            exchange,
+            order_status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn kill_switch_selects_orders_and_positions() {
+        let kill_switch = KillSwitch::new().exchange(ExchangeType::NSE);
+
+        assert!(kill_switch.cancels(&order(OrderStatusKind::Open, ExchangeType::NSE)));
+        assert!(kill_switch.cancels(&order(OrderStatusKind::TriggerPending, ExchangeType::NSE)));
+        assert!(!kill_switch.cancels(&order(OrderStatusKind::Complete, ExchangeType::NSE)));
+        assert!(!kill_switch.cancels(&order(OrderStatusKind::Open, ExchangeType::BSE)));
+        assert!(!kill_switch
+            .clone()
+            .symbol("TCS-EQ")
+            .cancels(&order(OrderStatusKind::Open, ExchangeType::NSE)));
+
+        let close = |net_qty| {
+            square_off_order(
+                &ExchangeType::NSE,
+                &ProductType::Delivery,
+                "INFY-EQ",
+                "1594",
+                net_qty,
+            )
+        };
+        let sell = close(25).unwrap();
+        assert_eq!(sell.transaction_type, TransactionType::Sell);
+        assert_eq!(sell.inner.order_type, OrderType::Market);
+        assert_eq!(sell.inner.product_type, ProductType::Delivery);
+        assert_eq!(sell.inner.quantity, "25");
+        let buy = close(-10).unwrap();
+        assert_eq!(buy.transaction_type, TransactionType::Buy);
+        assert_eq!(buy.inner.quantity, "10");
+        assert!(close(0).is_none());
+    }
+
+    #[test]
+    fn kill_switch_exits_bracket_orders_through_their_parent() {
+        let leg = |order_id: &str, order_status| OrderBook {
+            order_id: String::from(order_id),
+            parent_order_id: String::from("240101000000001"),
+            variety: OrderVariety::Robo,
+            product_type: ProductType::Bo,
+            ..order(order_status, ExchangeType::NSE)
+        };
+        let orders = vec![
+            leg("240101000000002", OrderStatusKind::TriggerPending),
+            leg("240101000000003", OrderStatusKind::Open),
+            leg("240101000000004", OrderStatusKind::Cancelled),
+        ];
+        assert!(!KillSwitch::new().cancels(&orders[0]));
+
+        let position = |product_type| Position {
+            exchange: ExchangeType::NSE,
+            product_type,
+            trading_symbol: String::from("INFY-EQ"),
+            symbol_token: String::from("1594"),
+            net_qty: 25,
+            ..Default::default()
+        };
+        let Some(Exit::Parents(parents)) = position_exit(&position(ProductType::Bo), &orders)
+        else {
+            panic!("bracket position not exited through its parent");
+        };
+        assert_eq!(
+            parents,
+            vec![(OrderVariety::Robo, String::from("240101000000001"))]
+        );
+
+        assert!(matches!(
+            position_exit(&position(ProductType::Bo), &[]),
+            Some(Exit::Parents(parents)) if parents.is_empty()
+        ));
+        assert!(matches!(
+            position_exit(&position(ProductType::IntraDay), &orders),
+            Some(Exit::Order(_))
+        ));
+    }
+}
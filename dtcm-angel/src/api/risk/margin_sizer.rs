@@ -0,0 +1,292 @@
+use log::debug;
+
+use crate::{
+    broker::FundsApi,
+    funds::MarginCalculatorPosition,
+    market::InstrumentMaster,
+    order::{OrderSetter, OrderValidationError, PlaceOrderReq},
+    Error, Result,
+};
+
+// Margin calculator queries made while scaling an order down before it is rejected
+const MAX_ATTEMPTS: usize = 5;
+
+/// Quantities of the orders affordable with the available margin
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginSizing {
+    /// Margin available, the net of the RMS limits
+    pub available: f64,
+    /// Margin required by the requested quantities
+    pub requested_margin: f64,
+    /// Margin required by the affordable quantities
+    pub required: f64,
+    /// Requested quantity of every order
+    pub requested_quantities: Vec<u64>,
+    /// Affordable quantity of every order in whole lots, at most the requested quantity
+    pub quantities: Vec<u64>,
+}
+
+impl MarginSizing {
+    /// Returns true if the requested quantities are affordable as they are
+    pub fn is_full_size(&self) -> bool {
+        self.quantities == self.requested_quantities
+    }
+}
+
+/// Sizes orders and baskets against the margin calculator and the available margin, so
+/// that the orders placed never fail for insufficient margin
+#[derive(Clone, Copy)]
+pub struct MarginSizer<'a> {
+    funds: &'a dyn FundsApi,
+    instruments: Option<&'a InstrumentMaster>,
+}
+
+impl<'a> MarginSizer<'a> {
+    /// Returns a new sizer for the broker, quantities are rounded to lots of one unit
+    pub fn new(funds: &'a dyn FundsApi) -> Self {
+        Self {
+            funds,
+            instruments: None,
+        }
+    }
+
+    /// Sets the instrument master the lot sizes are taken from
+    pub fn instruments(mut self, instruments: &'a InstrumentMaster) -> Self {
+        self.instruments = Some(instruments);
+        self
+    }
+
+    /// Returns the lot size of the instrument of the order
+    fn lot_size(&self, order_req: &PlaceOrderReq) -> u64 {
+        self.instruments
+            .and_then(|instruments| {
+                instruments.get(&order_req.inner.exchange, &order_req.inner.symbol_token)
+            })
+            .and_then(|instrument| instrument.lot_size_value())
+            .unwrap_or(1)
+            .max(1)
+    }
+
+    async fn margin(&self, orders: &[PlaceOrderReq], quantities: &[u64]) -> Result<f64> {
+        let positions: Vec<MarginCalculatorPosition> = orders
+            .iter()
+            .zip(quantities)
+            .map(|(order_req, &quantity)| MarginCalculatorPosition {
+                quantity: quantity as usize,
+                ..order_req.into()
+            })
+            .collect();
+        Ok(self
+            .funds
+            .calculate_margin(&positions)
+            .await?
+            .total_margin_required)
+    }
+
+    /// Returns the largest quantities of the orders, scaled down together in whole lots,
+    /// whose margin is covered by the available margin, rejects if a lot of every order is
+    /// not affordable or a quantity is not a whole number of lots
+    pub async fn size_basket(&self, orders: &[PlaceOrderReq]) -> Result<MarginSizing> {
+        let lot_sizes: Vec<u64> = orders.iter().map(|o| self.lot_size(o)).collect();
+        let requested_lots = orders
+            .iter()
+            .zip(&lot_sizes)
+            .map(|(o, &lot_size)| requested_lots(o, lot_size))
+            .collect::<Result<Vec<u64>>>()?;
+        let available = self.funds.rms_limit().await?.net;
+        let total_lots = requested_lots.iter().sum::<u64>() as f64;
+
+        let quantities = |lots: &[u64]| -> Vec<u64> {
+            lots.iter().zip(&lot_sizes).map(|(l, s)| l * s).collect()
+        };
+        let requested_quantities = quantities(&requested_lots);
+        let mut lots = requested_lots.clone();
+        let requested_margin = self.margin(orders, &quantities(&lots)).await?;
+        let mut required = requested_margin;
+        // share of the requested lots and its margin, of the last query and the one before
+        let mut share = 1.0;
+        let mut previous: Option<(f64, f64)> = None;
+
+        for attempt in 0..=MAX_ATTEMPTS {
+            if !lots.contains(&0) && required <= available {
+                return Ok(MarginSizing {
+                    available,
+                    requested_margin,
+                    required,
+                    requested_quantities,
+                    quantities: quantities(&lots),
+                });
+            }
+            if attempt == MAX_ATTEMPTS || lots.contains(&0) {
+                break;
+            }
+
+            // margins are close to affine in the quantity, the line through the last two
+            // queries estimates the share that fits, proportionally on the first attempt
+            let estimate = match previous {
+                Some((previous_share, previous_required)) if previous_required > required => {
+                    share
+                        + (available - required) * (previous_share - share)
+                            / (previous_required - required)
+                }
+                _ => share * available / required,
+            };
+            let scaled: Vec<u64> = requested_lots
+                .iter()
+                .zip(&lots)
+                .map(|(&r, &l)| ((r as f64 * estimate).floor() as u64).min(l))
+                .collect();
+            lots = if scaled == lots {
+                lots.iter().map(|l| l - 1).collect()
+            } else {
+                scaled
+            };
+            debug!(
+                "Margin {required:.2} over {available:.2}, attempt {attempt} with {lots:?} lots"
+            );
+            if !lots.contains(&0) {
+                previous = Some((share, required));
+                share = lots.iter().sum::<u64>() as f64 / total_lots;
+                required = self.margin(orders, &quantities(&lots)).await?;
+            }
+        }
+
+        Err(Error::InsufficientMargin {
+            required,
+            available,
+        })
+    }
+
+    /// Returns the sizing of the order
+    pub async fn size(&self, order_req: &PlaceOrderReq) -> Result<MarginSizing> {
+        self.size_basket(std::slice::from_ref(order_req)).await
+    }
+
+    /// Returns the order with its quantity cut to the affordable whole lots, rejects if a
+    /// single lot is not affordable
+    pub async fn fit(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderReq> {
+        let sizing = self.size(order_req).await?;
+        Ok(order_req.clone().quantity(sizing.quantities[0]))
+    }
+}
+
+/// Returns the requested quantity of the order in lots, rejects quantities that are not a
+/// positive whole number of lots
+fn requested_lots(order_req: &PlaceOrderReq, lot_size: u64) -> Result<u64> {
+    let quantity = order_req.inner.quantity.trim();
+    let quantity = quantity
+        .parse::<u64>()
+        .map_err(|_| OrderValidationError::InvalidNumber {
+            field: "quantity",
+            value: quantity.to_string(),
+        })?;
+    if quantity == 0 {
+        return Err(OrderValidationError::ZeroQuantity.into());
+    }
+    if !quantity.is_multiple_of(lot_size) {
+        return Err(OrderValidationError::LotSize { quantity, lot_size }.into());
+    }
+    Ok(quantity / lot_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use crate::{
+        broker::{FundsApi, PaperBroker},
+        funds::{MarginCalculatorPosition, MarginCalculatorRes, Rms},
+        market::{Instrument, InstrumentMaster},
+        order::{Order, PlaceOrderReq},
+        types::ExchangeType,
+        Error, Result,
+    };
+
+    use super::MarginSizer;
+
+    /// Paper funds charging a fixed margin on top of the order value
+    struct FixedCharge(PaperBroker, f64);
+
+    #[async_trait]
+    impl FundsApi for FixedCharge {
+        async fn rms_limit(&self) -> Result<Rms> {
+            self.0.rms_limit().await
+        }
+
+        async fn calculate_margin(
+            &self,
+            positions: &[MarginCalculatorPosition],
+        ) -> Result<MarginCalculatorRes> {
+            let mut margin = self.0.calculate_margin(positions).await?;
+            margin.total_margin_required += self.1;
+            Ok(margin)
+        }
+    }
+
+    fn order(symbol: &str, token: &str, quantity: u64, price: f64) -> PlaceOrderReq {
+        Order::limit(price)
+            .instrument(ExchangeType::NSE, symbol, token)
+            .buy(quantity)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn orders_are_cut_to_affordable_lots() {
+        let broker = PaperBroker::new(10_000.0);
+        let master: InstrumentMaster = serde_json::from_str::<Vec<Instrument>>(
+            r#"[{"token":"3045","symbol":"SBIN-EQ","name":"SBIN","expiry":"","strike":"-1",
+                "lotsize":"5","instrumenttype":"","exch_seg":"NSE","tick_size":"5"}]"#,
+        )
+        .unwrap()
+        .into_iter()
+        .collect();
+        let sizer = MarginSizer::new(&broker).instruments(&master);
+
+        let fitted = sizer
+            .fit(&order("SBIN-EQ", "3045", 50, 600.0))
+            .await
+            .unwrap();
+        assert_eq!(fitted.inner.quantity, "15");
+
+        let sizing = sizer
+            .size(&order("SBIN-EQ", "3045", 10, 600.0))
+            .await
+            .unwrap();
+        assert!(sizing.is_full_size());
+
+        let basket = [
+            order("SBIN-EQ", "3045", 20, 600.0),
+            order("INFY-EQ", "1594", 10, 1_000.0),
+        ];
+        let sizing = sizer.size_basket(&basket).await.unwrap();
+        assert_eq!(sizing.quantities, [5, 4]);
+        assert!(sizing.required <= 10_000.0);
+        assert!(!sizing.is_full_size());
+
+        // quantities that are not whole lots are rejected before any margin query
+        for quantity in ["7", "2", "ten"] {
+            let mut sub_lot = order("SBIN-EQ", "3045", 5, 600.0);
+            sub_lot.inner.quantity = quantity.to_string();
+            assert!(matches!(
+                sizer.size(&sub_lot).await,
+                Err(Error::OrderValidation(_))
+            ));
+        }
+
+        assert!(matches!(
+            sizer.fit(&order("SBIN-EQ", "3045", 5, 2_500.0)).await,
+            Err(Error::InsufficientMargin { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn large_orders_with_a_fixed_margin_are_sized() {
+        let funds = FixedCharge(PaperBroker::new(10_000.0), 5_000.0);
+        let sizing = MarginSizer::new(&funds)
+            .size(&order("IDEA-EQ", "14366", 10_000, 10.0))
+            .await
+            .unwrap();
+        assert_eq!(sizing.quantities, [500]);
+        assert_eq!(sizing.required, 10_000.0);
+    }
+}
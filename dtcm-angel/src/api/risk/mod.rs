@@ -0,0 +1,8 @@
+mod kill_switch;
+pub use kill_switch::{KillSwitch, KillSwitchReport, OrderCancellation, PositionSquareOff};
+
+mod risk_manager;
+pub use risk_manager::{RiskManager, RiskViolation};
+
+mod margin_sizer;
+pub use margin_sizer::{MarginSizer, MarginSizing};
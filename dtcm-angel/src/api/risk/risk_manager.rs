@@ -0,0 +1,696 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use log::{debug, error};
+use thiserror::Error as ThisError;
+
+use crate::{
+    market::{LtpDataReq, MarketDataReq},
+    order::{ModifyOrderReq, OrderBook, OrderInner, OrderState, PlaceOrderReq},
+    portfolio::Position,
+    types::{ExchangeType, MarketMode, OrderType, TransactionType},
+    Result, SmartConnect,
+};
+
+/// Reasons an order is rejected by the [`RiskManager`]
+#[derive(Debug, Clone, PartialEq, ThisError)]
+pub enum RiskViolation {
+    /// order value exceeds the maximum notional per order
+    #[error("order value {value:.2} exceeds the limit {limit:.2}")]
+    OrderValue {
+        /// Value of the order
+        value: f64,
+        /// Maximum order value
+        limit: f64,
+    },
+    /// resulting position exceeds the quantity cap of the symbol
+    #[error("position {position} in {trading_symbol} would exceed the limit {limit}")]
+    PositionLimit {
+        /// Trading symbol of the order
+        trading_symbol: String,
+        /// Net position after the order
+        position: i64,
+        /// Maximum absolute net position
+        limit: u64,
+    },
+    /// too many open orders
+    #[error("{open} open orders reach the limit {limit}")]
+    OpenOrders {
+        /// Open orders
+        open: usize,
+        /// Maximum open orders
+        limit: usize,
+    },
+    /// price is too far from the LTP
+    #[error("price {price} is more than {band:.2}% away from the LTP {ltp}")]
+    PriceBand {
+        /// Price of the order
+        price: f64,
+        /// Last traded price
+        ltp: f64,
+        /// Allowed distance from the LTP in percent
+        band: f64,
+    },
+    /// daily loss limit reached
+    #[error("loss {loss:.2} reaches the daily limit {limit:.2}")]
+    DailyLoss {
+        /// Current loss
+        loss: f64,
+        /// Maximum daily loss
+        limit: f64,
+    },
+    /// LTP needed for a check is unavailable
+    #[error("LTP of {0} is unavailable")]
+    LtpUnavailable(String),
+}
+
+type RiskResult<T> = std::result::Result<T, RiskViolation>;
+
+// Default time the fetched account state and LTPs are reused by the following checks
+const DEFAULT_SNAPSHOT_TTL: Duration = Duration::from_secs(2);
+
+/// Account state fetched for the checks, reused within the snapshot TTL
+#[derive(Debug, Clone)]
+struct AccountSnapshot {
+    fetched_at: Instant,
+    positions: Vec<Position>,
+    open_orders: Vec<OrderBook>,
+    pnl: f64,
+    /// Signed quantities of the orders passed since the fetch, by exchange and token
+    passed_quantities: Vec<(ExchangeType, String, i64)>,
+    /// Orders passed since the fetch, not in the order book yet
+    passed_orders: usize,
+}
+
+impl AccountSnapshot {
+    /// Returns the account state the order of the instrument is checked against
+    fn context(&self, inner: &OrderInner, ltp: Option<f64>) -> RiskContext {
+        let matches = |exchange: &ExchangeType, token: &str| {
+            *exchange == inner.exchange && token == inner.symbol_token
+        };
+        let position = self
+            .positions
+            .iter()
+            .filter(|p| matches(&p.exchange, &p.symbol_token))
+            .map(|p| p.net_qty)
+            .chain(
+                self.passed_quantities
+                    .iter()
+                    .filter(|(exchange, token, _)| matches(exchange, token))
+                    .map(|(_, _, quantity)| *quantity),
+            )
+            .sum();
+        let (working_buy, working_sell) = self
+            .open_orders
+            .iter()
+            .filter(|o| matches(&o.exchange, &o.symbol_token))
+            .fold((0, 0), |(buy, sell), o| {
+                let working = o.quantity.saturating_sub(o.filled_shares);
+                match o.transaction_type {
+                    TransactionType::Sell => (buy, sell + working),
+                    _ => (buy + working, sell),
+                }
+            });
+
+        RiskContext {
+            ltp,
+            position,
+            working_buy,
+            working_sell,
+            open_orders: self.open_orders.len() + self.passed_orders,
+            pnl: self.pnl,
+        }
+    }
+}
+
+/// Account snapshot and LTPs shared by the clones of a [`RiskManager`]
+#[derive(Debug, Default)]
+struct RiskCache {
+    account: Option<AccountSnapshot>,
+    ltps: HashMap<(ExchangeType, String), (Instant, f64)>,
+}
+
+/// Account state an order is checked against
+#[derive(Debug, Clone, Default)]
+struct RiskContext {
+    /// LTP of the instrument of the order
+    ltp: Option<f64>,
+    /// Net position in the instrument of the order
+    position: i64,
+    /// Quantity still to fill of the open buy orders in the instrument
+    working_buy: u64,
+    /// Quantity still to fill of the open sell orders in the instrument
+    working_sell: u64,
+    /// Open orders in the order book
+    open_orders: usize,
+    /// Realized and unrealized profit of the day
+    pnl: f64,
+}
+
+/// Hard pre-trade limits enforced by [`SmartConnect::place_order`] and
+/// [`SmartConnect::modify_order`]. Orders reducing a position without reversing it are only
+/// checked against the price band so that positions can always be exited, a modification
+/// raising the quantity is checked like an order of the added quantity. The position cap
+/// counts the open orders of the symbol on the side of the order as filled.
+///
+/// The positions, order book, P&L and LTPs are reused for the snapshot TTL so that bursts of
+/// orders do not multiply the REST calls, the orders passed within it are added to the
+/// cached positions and open orders.
+#[derive(Debug, Clone)]
+pub struct RiskManager {
+    /// Maximum notional value of an order in rupees
+    pub max_order_value: Option<f64>,
+    /// Maximum absolute net quantity per trading symbol
+    pub position_limits: HashMap<String, u64>,
+    /// Maximum absolute net quantity for the symbols without their own limit
+    pub default_position_limit: Option<u64>,
+    /// Maximum number of open orders
+    pub max_open_orders: Option<usize>,
+    /// Maximum distance of the order price from the LTP in percent
+    pub price_band: Option<f64>,
+    /// Maximum realized and unrealized loss of the day in rupees
+    pub max_daily_loss: Option<f64>,
+    /// Time the account state and the LTPs are reused for, zero to fetch them for every check
+    pub snapshot_ttl: Duration,
+    cache: Arc<Mutex<RiskCache>>,
+}
+
+impl Default for RiskManager {
+    fn default() -> Self {
+        Self {
+            max_order_value: None,
+            position_limits: HashMap::new(),
+            default_position_limit: None,
+            max_open_orders: None,
+            price_band: None,
+            max_daily_loss: None,
+            snapshot_ttl: DEFAULT_SNAPSHOT_TTL,
+            cache: Default::default(),
+        }
+    }
+}
+
+impl RiskManager {
+    /// Returns a new instance for [`RiskManager`] without any limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum notional value of an order
+    pub fn max_order_value(mut self, max_order_value: f64) -> Self {
+        self.max_order_value = Some(max_order_value);
+        self
+    }
+
+    /// Caps the absolute net quantity of the trading symbol
+    pub fn position_limit<S>(mut self, trading_symbol: S, quantity: u64) -> Self
+    where
+        S: Into<String>,
+    {
+        self.position_limits.insert(trading_symbol.into(), quantity);
+        self
+    }
+
+    /// Caps the absolute net quantity of the symbols without their own limit
+    pub fn default_position_limit(mut self, quantity: u64) -> Self {
+        self.default_position_limit = Some(quantity);
+        self
+    }
+
+    /// Sets the maximum number of open orders
+    pub fn max_open_orders(mut self, max_open_orders: usize) -> Self {
+        self.max_open_orders = Some(max_open_orders);
+        self
+    }
+
+    /// Sets the maximum distance of the order price from the LTP in percent
+    pub fn price_band(mut self, percent: f64) -> Self {
+        self.price_band = Some(percent);
+        self
+    }
+
+    /// Sets the maximum realized and unrealized loss of the day
+    pub fn max_daily_loss(mut self, max_daily_loss: f64) -> Self {
+        self.max_daily_loss = Some(max_daily_loss);
+        self
+    }
+
+    /// Sets the time the account state and the LTPs are reused for
+    pub fn snapshot_ttl(mut self, snapshot_ttl: Duration) -> Self {
+        self.snapshot_ttl = snapshot_ttl;
+        self
+    }
+
+    /// Drops the cached account state and LTPs, the next check fetches them
+    pub fn invalidate(&self) {
+        *self.lock() = RiskCache::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RiskCache> {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns true if a limit needs the positions and the order book
+    fn needs_account(&self) -> bool {
+        self.max_order_value.is_some()
+            || !self.position_limits.is_empty()
+            || self.default_position_limit.is_some()
+            || self.max_open_orders.is_some()
+            || self.max_daily_loss.is_some()
+    }
+
+    /// Checks the order to be placed against the limits
+    pub async fn check_order(
+        &self,
+        smart_connect: &SmartConnect,
+        order_req: &PlaceOrderReq,
+    ) -> Result<()> {
+        let inner = &order_req.inner;
+        let ltp = self.ltp(smart_connect, inner).await?;
+        let context = match self.needs_account() {
+            true => self
+                .account(smart_connect, false)
+                .await?
+                .context(inner, ltp),
+            false => RiskContext {
+                ltp,
+                ..Default::default()
+            },
+        };
+
+        self.check_with(order_req, &context).map_err(|e| {
+            error!("Risk rejected order for {}: {e}", inner.trading_symbol);
+            e
+        })?;
+
+        let quantity = number(&inner.quantity) as i64;
+        self.record(inner, signed(&order_req.transaction_type, quantity), 1);
+        Ok(())
+    }
+
+    /// Checks the order modification against the order value and the price band, and the
+    /// added quantity against the other limits
+    pub async fn check_modify(
+        &self,
+        smart_connect: &SmartConnect,
+        modify_req: &ModifyOrderReq,
+    ) -> Result<()> {
+        let inner = &modify_req.inner;
+        let reject = |e: RiskViolation| {
+            error!("Risk rejected modification of {}: {e}", modify_req.order_id);
+            e
+        };
+
+        let ltp = self.ltp(smart_connect, inner).await?;
+        self.check_band(inner, ltp)
+            .and_then(|_| self.check_value(inner, ltp))
+            .map_err(reject)?;
+        if !self.needs_account() {
+            return Ok(());
+        }
+
+        // the order book gives the side and the quantity before the modification, an order
+        // placed after the cached fetch needs a new one, unknown orders are left to the broker
+        let find = |account: &AccountSnapshot| {
+            account
+                .open_orders
+                .iter()
+                .find(|o| o.order_id == modify_req.order_id)
+                .map(|o| (o.transaction_type.clone(), o.quantity))
+        };
+        let mut account = self.account(smart_connect, false).await?;
+        if find(&account).is_none() {
+            account = self.account(smart_connect, true).await?;
+        }
+        let Some((transaction_type, quantity)) = find(&account) else {
+            return Ok(());
+        };
+        let added = number(&inner.quantity) as i64 - quantity as i64;
+        if added <= 0 {
+            return Ok(());
+        }
+
+        // the modified order is already open, the added quantity counts as one more order
+        let mut context = account.context(inner, ltp);
+        context.open_orders -= 1;
+        let added = signed(&transaction_type, added);
+        self.check_quantity(inner, added, &context)
+            .map_err(reject)?;
+
+        self.record(inner, added, 0);
+        Ok(())
+    }
+
+    /// Returns the cached account state, fetched again once older than the TTL or if forced
+    async fn account(&self, smart_connect: &SmartConnect, force: bool) -> Result<AccountSnapshot> {
+        if !force
+            && let Some(account) = &self.lock().account
+            && account.fetched_at.elapsed() < self.snapshot_ttl
+        {
+            return Ok(account.clone());
+        }
+
+        let (positions, orders) =
+            tokio::try_join!(smart_connect.positions(), smart_connect.order_book())?;
+        let pnl = match self.max_daily_loss {
+            Some(_) => pnl(smart_connect, &positions).await?,
+            None => 0.0,
+        };
+        let account = AccountSnapshot {
+            fetched_at: Instant::now(),
+            positions,
+            open_orders: orders
+                .into_iter()
+                .filter(|o| OrderState::from_order_book(o).is_some_and(|s| !s.is_terminal()))
+                .collect(),
+            pnl,
+            passed_quantities: vec![],
+            passed_orders: 0,
+        };
+        self.lock().account = Some(account.clone());
+        Ok(account)
+    }
+
+    /// Adds the quantity and the orders passed to the cached account state
+    fn record(&self, inner: &OrderInner, quantity: i64, orders: usize) {
+        if let Some(account) = &mut self.lock().account {
+            account.passed_quantities.push((
+                inner.exchange.clone(),
+                inner.symbol_token.clone(),
+                quantity,
+            ));
+            account.passed_orders += orders;
+        }
+    }
+
+    /// Fetches the LTP of the instrument if a check needs it, reused within the TTL
+    async fn ltp(&self, smart_connect: &SmartConnect, inner: &OrderInner) -> Result<Option<f64>> {
+        let needs_value = self.max_order_value.is_some() && inner.order_type == OrderType::Market;
+        if self.price_band.is_none() && !needs_value {
+            return Ok(None);
+        }
+
+        let key = (inner.exchange.clone(), inner.symbol_token.clone());
+        if let Some((fetched_at, ltp)) = self.lock().ltps.get(&key)
+            && fetched_at.elapsed() < self.snapshot_ttl
+        {
+            return Ok(Some(*ltp));
+        }
+
+        let ltp_req = LtpDataReq::new(
+            inner.exchange.clone(),
+            &inner.trading_symbol,
+            &inner.symbol_token,
+        );
+        let ltp = smart_connect.ltp_data(&ltp_req).await?.ltp;
+        debug!("Risk LTP of {} is {ltp}", inner.trading_symbol);
+        self.lock().ltps.insert(key, (Instant::now(), ltp));
+        Ok(Some(ltp))
+    }
+
+    /// Checks the order against the account state
+    fn check_with(&self, order_req: &PlaceOrderReq, context: &RiskContext) -> RiskResult<()> {
+        let inner = &order_req.inner;
+        self.check_band(inner, context.ltp)?;
+
+        let quantity = number(&inner.quantity) as i64;
+        self.check_quantity(
+            inner,
+            signed(&order_req.transaction_type, quantity),
+            context,
+        )
+    }
+
+    /// Checks the signed quantity added to the position against the account state
+    fn check_quantity(
+        &self,
+        inner: &OrderInner,
+        signed: i64,
+        context: &RiskContext,
+    ) -> RiskResult<()> {
+        let position = context.position + signed;
+        let reducing = position == 0
+            || (position.signum() == context.position.signum()
+                && position.abs() < context.position.abs());
+        if reducing {
+            return Ok(());
+        }
+        self.check_value(inner, context.ltp)?;
+
+        // the open orders on the same side may fill before this one
+        let position = match signed > 0 {
+            true => position + context.working_buy as i64,
+            false => position - context.working_sell as i64,
+        };
+
+        let limit = self
+            .position_limits
+            .get(&inner.trading_symbol)
+            .or(self.default_position_limit.as_ref());
+        if let Some(&limit) = limit
+            && position.unsigned_abs() > limit
+        {
+            return Err(RiskViolation::PositionLimit {
+                trading_symbol: inner.trading_symbol.clone(),
+                position,
+                limit,
+            });
+        }
+
+        if let Some(limit) = self.max_open_orders
+            && context.open_orders >= limit
+        {
+            return Err(RiskViolation::OpenOrders {
+                open: context.open_orders,
+                limit,
+            });
+        }
+
+        if let Some(limit) = self.max_daily_loss
+            && -context.pnl >= limit
+        {
+            return Err(RiskViolation::DailyLoss {
+                loss: -context.pnl,
+                limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the price of the order, none for market orders
+    fn price(inner: &OrderInner) -> Option<f64> {
+        match inner.order_type {
+            OrderType::Market => None,
+            OrderType::StopLossMarket => inner.trigger_price.as_deref().map(number),
+            _ => Some(number(&inner.price)),
+        }
+    }
+
+    /// Checks the distance of the order price from the LTP
+    fn check_band(&self, inner: &OrderInner, ltp: Option<f64>) -> RiskResult<()> {
+        let (Some(band), Some(price)) = (self.price_band, Self::price(inner)) else {
+            return Ok(());
+        };
+        let ltp = ltp.ok_or_else(|| RiskViolation::LtpUnavailable(inner.trading_symbol.clone()))?;
+        match ltp > 0.0 && (price - ltp).abs() / ltp * 100.0 > band {
+            true => Err(RiskViolation::PriceBand { price, ltp, band }),
+            false => Ok(()),
+        }
+    }
+
+    /// Checks the notional value of the order, market orders are valued at the LTP
+    fn check_value(&self, inner: &OrderInner, ltp: Option<f64>) -> RiskResult<()> {
+        let Some(limit) = self.max_order_value else {
+            return Ok(());
+        };
+        let price = Self::price(inner)
+            .or(ltp)
+            .ok_or_else(|| RiskViolation::LtpUnavailable(inner.trading_symbol.clone()))?;
+        let value = price * number(&inner.quantity);
+        match value > limit {
+            true => Err(RiskViolation::OrderValue { value, limit }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Parses the numeric order field, invalid values are left to the order validation
+fn number(value: &str) -> f64 {
+    value.trim().parse().unwrap_or_default()
+}
+
+/// Returns the quantity negated for the sell orders
+fn signed(transaction_type: &TransactionType, quantity: i64) -> i64 {
+    match transaction_type {
+        TransactionType::Sell => -quantity,
+        _ => quantity,
+    }
+}
+
+/// Returns the realized and unrealized profit of the positions marked at their LTP
+async fn pnl(smart_connect: &SmartConnect, positions: &[Position]) -> Result<f64> {
+    let mut exchange_tokens: HashMap<ExchangeType, Vec<String>> = HashMap::new();
+    for position in positions.iter().filter(|p| p.net_qty != 0) {
+        exchange_tokens
+            .entry(position.exchange.clone())
+            .or_default()
+            .push(position.symbol_token.clone());
+    }
+
+    let mut ltps = HashMap::new();
+    if !exchange_tokens.is_empty() {
+        let market_data_req = MarketDataReq::new(MarketMode::Ltp, exchange_tokens);
+        for data in smart_connect.market_data(&market_data_req).await?.fetched {
+            if let Ok(exchange) = serde_json::from_value::<ExchangeType>(data.exchange.into()) {
+                ltps.insert((exchange, data.symbol_token), data.ltp);
+            }
+        }
+    }
+
+    // positions without an LTP are marked at their net price, without unrealized profit
+    Ok(positions
+        .iter()
+        .map(|p| {
+            let key = (p.exchange.clone(), p.symbol_token.clone());
+            p.pnl(ltps.get(&key).copied().unwrap_or(p.net_price))
+        })
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        order::{OrderBook, OrderSetter, PlaceOrderReq},
+        portfolio::Position,
+        types::{ExchangeType, OrderType, TransactionType},
+    };
+
+    use super::{AccountSnapshot, RiskContext, RiskManager, RiskViolation};
+
+    fn order(transaction_type: TransactionType, quantity: u64, price: f64) -> PlaceOrderReq {
+        PlaceOrderReq::new("INFY-EQ", "1594", transaction_type)
+            .exchange(ExchangeType::NSE)
+            .order_type(OrderType::Limit)
+            .quantity(quantity)
+            .price(price)
+    }
+
+    #[test]
+    fn risk_limits() {
+        let risk = RiskManager::new()
+            .max_order_value(500_000.0)
+            .position_limit("INFY-EQ", 300)
+            .max_open_orders(5)
+            .price_band(5.0)
+            .max_daily_loss(10_000.0);
+        let context = RiskContext {
+            ltp: Some(1_500.0),
+            position: 200,
+            open_orders: 2,
+            pnl: -2_000.0,
+            ..Default::default()
+        };
+        let check = |order: PlaceOrderReq, context: &RiskContext| risk.check_with(&order, context);
+
+        assert_eq!(
+            check(order(TransactionType::Buy, 100, 1_500.0), &context),
+            Ok(())
+        );
+        assert!(matches!(
+            check(order(TransactionType::Buy, 100, 1_700.0), &context),
+            Err(RiskViolation::PriceBand { .. })
+        ));
+        assert!(matches!(
+            check(order(TransactionType::Buy, 400, 1_500.0), &context),
+            Err(RiskViolation::OrderValue { .. })
+        ));
+        assert_eq!(
+            check(order(TransactionType::Buy, 200, 1_500.0), &context),
+            Err(RiskViolation::PositionLimit {
+                trading_symbol: String::from("INFY-EQ"),
+                position: 400,
+                limit: 300
+            })
+        );
+
+        // raising a buy order from 100 to 250 adds 150 to the position
+        assert!(matches!(
+            risk.check_quantity(
+                &order(TransactionType::Buy, 250, 1_500.0).inner,
+                150,
+                &context
+            ),
+            Err(RiskViolation::PositionLimit { position: 350, .. })
+        ));
+
+        let losing = RiskContext {
+            pnl: -12_000.0,
+            ..context
+        };
+        assert!(matches!(
+            check(order(TransactionType::Buy, 10, 1_500.0), &losing),
+            Err(RiskViolation::DailyLoss { .. })
+        ));
+        // exits are allowed past the loss limit
+        assert_eq!(
+            check(order(TransactionType::Sell, 200, 1_500.0), &losing),
+            Ok(())
+        );
+        // reversing the position is checked like a new one
+        assert!(matches!(
+            check(order(TransactionType::Sell, 350, 1_500.0), &losing),
+            Err(RiskViolation::OrderValue { .. })
+        ));
+        let short = RiskContext {
+            position: -100,
+            working_buy: 200,
+            ..context.clone()
+        };
+        assert!(matches!(
+            check(order(TransactionType::Buy, 300, 1_500.0), &short),
+            Err(RiskViolation::PositionLimit { position: 400, .. })
+        ));
+    }
+
+    #[test]
+    fn cached_account_counts_passed_orders() {
+        let risk = RiskManager::new().position_limit("INFY-EQ", 300);
+        risk.lock().account = Some(AccountSnapshot {
+            fetched_at: Instant::now(),
+            positions: vec![Position {
+                exchange: ExchangeType::NSE,
+                symbol_token: String::from("1594"),
+                net_qty: 200,
+                ..Default::default()
+            }],
+            open_orders: vec![OrderBook {
+                exchange: ExchangeType::NSE,
+                symbol_token: String::from("1594"),
+                transaction_type: TransactionType::Buy,
+                quantity: 30,
+                filled_shares: 10,
+                ..Default::default()
+            }],
+            pnl: 0.0,
+            passed_quantities: vec![],
+            passed_orders: 0,
+        });
+
+        let inner = order(TransactionType::Buy, 60, 1_500.0).inner;
+        risk.record(&inner, 60, 1);
+        let context = risk.lock().account.as_ref().unwrap().context(&inner, None);
+        assert_eq!((context.position, context.open_orders), (260, 2));
+        assert_eq!((context.working_buy, context.working_sell), (20, 0));
+        // a second order of 30 within the TTL breaches the cap with the open buy order
+        assert!(matches!(
+            risk.check_quantity(&inner, 30, &context),
+            Err(RiskViolation::PositionLimit { position: 310, .. })
+        ));
+    }
+}
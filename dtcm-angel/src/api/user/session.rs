@@ -16,7 +16,7 @@ pub struct SessionReq {
 }
 
 /// Session response received on calling the Login endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct SessionRes {
     /// JWT token
     #[serde(rename = "jwtToken")]
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeDelta};
+use log::trace;
+
+use crate::{market::CandleDataRes, types::Interval};
+
+use super::{Message, SubscriptionExchange};
+
+// Intraday candles are aligned to the start of the trading session at 09:15 IST
+const SESSION_START: NaiveTime = match NaiveTime::from_hms_opt(9, 15, 0) {
+    Some(time) => time,
+    None => unreachable!(),
+};
+
+/// OHLCV bar built from the websocket ticks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    /// Exchange of the token
+    pub exchange: SubscriptionExchange,
+    /// Symbol token
+    pub token: String,
+    /// Interval of the candle
+    pub interval: Interval,
+    /// Start time of the candle in IST
+    pub start: DateTime<FixedOffset>,
+    /// Open price
+    pub open: f64,
+    /// High price
+    pub high: f64,
+    /// Low price
+    pub low: f64,
+    /// Close price
+    pub close: f64,
+    /// Traded volume within the candle
+    pub volume: usize,
+}
+
+impl Candle {
+    /// Returns the candle in the tuple shape of [`CandleDataRes`]
+    pub fn to_tuple(&self) -> (String, f64, f64, f64, f64, usize) {
+        (
+            self.start.format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+        )
+    }
+
+    /// Returns true if the candle ends on or before the given time
+    pub fn is_complete_at(&self, time: DateTime<FixedOffset>) -> bool {
+        self.start + self.interval.duration() <= time
+    }
+
+    /// Folds the traded price and volume into the candle
+    fn apply(&mut self, price: f64, volume: usize) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += volume;
+    }
+}
+
+impl FromIterator<Candle> for CandleDataRes {
+    fn from_iter<I: IntoIterator<Item = Candle>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|c| c.to_tuple()).collect())
+    }
+}
+
+/// Candle under construction along with the cumulative volume seen for the token
+#[derive(Debug, Default)]
+struct TokenBars {
+    current: Option<Candle>,
+    day_volume: Option<i64>,
+}
+
+/// Builds live candles of an [`Interval`] from the websocket ticks, the volume is taken
+/// from the deltas of [`super::Quote::volume_trade_for_the_day`], so LTP mode ticks yield
+/// candles without volume
+#[derive(Debug)]
+pub struct CandleBuilder {
+    interval: Interval,
+    price_divisor: f64,
+    bars: HashMap<(SubscriptionExchange, String), TokenBars>,
+}
+
+impl CandleBuilder {
+    /// Returns a new instance for [`CandleBuilder`]
+    pub fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            price_divisor: 100.0,
+            bars: HashMap::new(),
+        }
+    }
+
+    /// Sets the divisor converting the feed prices to rupees, 100 (paise) by default
+    pub fn price_divisor(mut self, price_divisor: f64) -> Self {
+        self.price_divisor = price_divisor;
+        self
+    }
+
+    /// Returns the start of the candle containing the given time
+    pub fn candle_start(&self, time: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        let date = time.date_naive();
+        let origin = match self.interval {
+            Interval::_1d => date.and_time(NaiveTime::MIN),
+            _ => date.and_time(SESSION_START),
+        };
+        let origin = origin
+            .and_local_timezone(*time.offset())
+            .single()
+            .unwrap_or(time);
+
+        let length = self.interval.duration().num_seconds();
+        let elapsed = (time - origin).num_seconds();
+
+        origin + TimeDelta::seconds(elapsed.div_euclid(length) * length)
+    }
+
+    /// Applies the tick and returns the candle it completed, if any
+    pub fn update(&mut self, message: &Message) -> Option<Candle> {
+        let time = message.exchange_time()?;
+        let start = self.candle_start(time);
+        let price = message.last_traded_price as f64 / self.price_divisor;
+
+        let bars = self
+            .bars
+            .entry((message.exchange, message.token.clone()))
+            .or_default();
+
+        let volume = match &message.quote {
+            Some(quote) => {
+                let day_volume = quote.volume_trade_for_the_day;
+                let delta = match bars.day_volume {
+                    Some(last) if day_volume >= last => day_volume - last,
+                    Some(_) => day_volume,
+                    // the first tick only sets the baseline
+                    None => 0,
+                };
+                bars.day_volume = Some(day_volume);
+                delta as usize
+            }
+            None => 0,
+        };
+
+        match bars.current.as_mut() {
+            Some(candle) if candle.start == start => {
+                candle.apply(price, volume);
+                None
+            }
+            Some(candle) if candle.start > start => {
+                trace!("Late tick for {} at {time} ignored", message.token);
+                None
+            }
+            _ => bars.current.replace(Candle {
+                exchange: message.exchange,
+                token: message.token.clone(),
+                interval: self.interval,
+                start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume,
+            }),
+        }
+    }
+
+    /// Returns the candle under construction for the token
+    pub fn current(&self, exchange: SubscriptionExchange, token: &str) -> Option<&Candle> {
+        self.bars
+            .get(&(exchange, token.to_string()))
+            .and_then(|bars| bars.current.as_ref())
+    }
+
+    /// Completes and returns the candles ending on or before the given time, to be called
+    /// periodically so that quiet tokens still emit their candles
+    pub fn flush_until(&mut self, time: DateTime<FixedOffset>) -> Vec<Candle> {
+        self.bars
+            .values_mut()
+            .filter(|bars| {
+                bars.current
+                    .as_ref()
+                    .is_some_and(|candle| candle.is_complete_at(time))
+            })
+            .filter_map(|bars| bars.current.take())
+            .collect()
+    }
+
+    /// Completes and returns all the candles under construction
+    pub fn flush(&mut self) -> Vec<Candle> {
+        self.bars
+            .values_mut()
+            .filter_map(|bars| bars.current.take())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        types::Interval,
+        ws::{ltp, Message, Quote, SubscriptionExchange, SubscriptionMode},
+    };
+
+    use super::CandleBuilder;
+
+    // 2024-12-26 09:15:00 IST
+    const SESSION_OPEN_MILLIS: i64 = 1_735_184_700_000;
+
+    fn tick(offset_secs: i64, price: i64, day_volume: i64) -> Message {
+        let quote = Quote {
+            last_traded_quantity: 0,
+            average_traded_price: 0,
+            volume_trade_for_the_day: day_volume,
+            total_buy_quantity: 0.0,
+            total_sell_quantity: 0.0,
+            open_price_of_the_day: 0,
+            high_price_of_the_day: 0,
+            low_price_of_the_day: 0,
+            closed_price: 0,
+        };
+
+        Message {
+            mode: SubscriptionMode::Quote,
+            quote: Some(quote),
+            ..ltp(
+                "3045",
+                offset_secs,
+                SESSION_OPEN_MILLIS + offset_secs * 1000,
+                price,
+            )
+        }
+    }
+
+    #[test]
+    fn ticks_build_session_aligned_candles() {
+        let mut builder = CandleBuilder::new(Interval::_5m);
+
+        assert!(builder.update(&tick(10, 80_000, 1_000)).is_none());
+        assert!(builder.update(&tick(70, 80_250, 1_200)).is_none());
+        assert!(builder.update(&tick(200, 79_900, 1_500)).is_none());
+
+        let candle = builder.update(&tick(301, 80_100, 1_600)).unwrap();
+        assert_eq!(
+            candle.to_tuple(),
+            (
+                String::from("2024-12-26T09:15:00+05:30"),
+                800.0,
+                802.5,
+                799.0,
+                799.0,
+                500
+            )
+        );
+
+        let next = builder
+            .current(SubscriptionExchange::NSECM, "3045")
+            .unwrap();
+        assert_eq!(next.to_tuple().0, "2024-12-26T09:20:00+05:30");
+        assert_eq!(next.volume, 100);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use super::{BestFiveData, BestFiveFlag, Message, SubscriptionExchange};
+
+/// Single price level of the market depth, prices are in the feed units (paise for equity)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthLevel {
+    /// Price of the level
+    pub price: i64,
+    /// Total quantity at the level
+    pub quantity: i64,
+    /// Number of orders at the level
+    pub order_count: i16,
+}
+
+impl From<&BestFiveData> for DepthLevel {
+    fn from(data: &BestFiveData) -> Self {
+        Self {
+            price: data.price,
+            quantity: data.quantity,
+            order_count: data.order_count,
+        }
+    }
+}
+
+/// Latest bid/ask ladders of a token, built from the snap quote ticks
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookState {
+    /// Exchange of the token
+    pub exchange: SubscriptionExchange,
+    /// Symbol token
+    pub token: String,
+    /// Buy side levels, best (highest) price first
+    pub bids: Vec<DepthLevel>,
+    /// Sell side levels, best (lowest) price first
+    pub asks: Vec<DepthLevel>,
+    /// Last traded price
+    pub last_traded_price: i64,
+    /// Sequence number of the last applied tick
+    pub sequence_number: i64,
+    /// Exchange timestamp of the last applied tick
+    pub exchange_timestamp: i64,
+}
+
+impl OrderBookState {
+    /// Returns a new instance for [`OrderBookState`]
+    pub fn new<T>(exchange: SubscriptionExchange, token: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self {
+            exchange,
+            token: token.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies the [`Message`] to the state, the ladders are only replaced by snap quote ticks.
+    /// Returns true if the ladders were replaced.
+    pub fn update(&mut self, message: &Message) -> bool {
+        self.last_traded_price = message.last_traded_price;
+        self.sequence_number = message.sequence_number;
+        self.exchange_timestamp = message.exchange_timestamp;
+
+        let Some(snap_quote) = &message.snap_quote else {
+            return false;
+        };
+
+        let levels = |flag: BestFiveFlag| {
+            snap_quote
+                .best_five_data
+                .iter()
+                .filter(|d| d.flag == flag && (d.price != 0 || d.quantity != 0))
+                .map(DepthLevel::from)
+                .collect::<Vec<_>>()
+        };
+
+        self.bids = levels(BestFiveFlag::Buy);
+        self.bids.sort_by_key(|l| Reverse(l.price));
+
+        self.asks = levels(BestFiveFlag::Sell);
+        self.asks.sort_by_key(|l| l.price);
+
+        true
+    }
+
+    /// Returns the best bid level
+    pub fn best_bid(&self) -> Option<&DepthLevel> {
+        self.bids.first()
+    }
+
+    /// Returns the best ask level
+    pub fn best_ask(&self) -> Option<&DepthLevel> {
+        self.asks.first()
+    }
+
+    /// Returns the best bid and offer
+    pub fn bbo(&self) -> Option<(&DepthLevel, &DepthLevel)> {
+        self.best_bid().zip(self.best_ask())
+    }
+
+    /// Returns the difference between the best ask and the best bid
+    pub fn spread(&self) -> Option<i64> {
+        self.bbo().map(|(bid, ask)| ask.price - bid.price)
+    }
+
+    /// Returns the mid price between the best bid and the best ask
+    pub fn mid(&self) -> Option<f64> {
+        self.bbo()
+            .map(|(bid, ask)| (bid.price as f64 + ask.price as f64) / 2.0)
+    }
+
+    /// Returns the mid price weighted by the opposite side quantity at the top of the book
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid, ask) = self.bbo()?;
+        let total = (bid.quantity + ask.quantity) as f64;
+        if total == 0.0 {
+            return self.mid();
+        }
+
+        Some(
+            (bid.price as f64 * ask.quantity as f64 + ask.price as f64 * bid.quantity as f64)
+                / total,
+        )
+    }
+
+    /// Returns the depth imbalance over the given number of levels in the range -1 (all asks)
+    /// to 1 (all bids)
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid_qty: i64 = self.bids.iter().take(levels).map(|l| l.quantity).sum();
+        let ask_qty: i64 = self.asks.iter().take(levels).map(|l| l.quantity).sum();
+
+        let total = bid_qty + ask_qty;
+        if total == 0 {
+            return None;
+        }
+
+        Some((bid_qty - ask_qty) as f64 / total as f64)
+    }
+}
+
+/// Aggregates the [`OrderBookState`] for every token seen on the feed
+#[derive(Debug, Default)]
+pub struct MarketDepth {
+    books: HashMap<(SubscriptionExchange, String), OrderBookState>,
+}
+
+impl MarketDepth {
+    /// Returns a new instance for [`MarketDepth`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the [`Message`] to the book of its token and returns the updated book
+    pub fn update(&mut self, message: &Message) -> &OrderBookState {
+        let book = self
+            .books
+            .entry((message.exchange, message.token.clone()))
+            .or_insert_with(|| OrderBookState::new(message.exchange, &message.token));
+        book.update(message);
+        book
+    }
+
+    /// Returns the book for the token
+    pub fn get(&self, exchange: SubscriptionExchange, token: &str) -> Option<&OrderBookState> {
+        self.books.get(&(exchange, token.to_string()))
+    }
+
+    /// Removes the book for the token
+    pub fn remove(
+        &mut self,
+        exchange: SubscriptionExchange,
+        token: &str,
+    ) -> Option<OrderBookState> {
+        self.books.remove(&(exchange, token.to_string()))
+    }
+
+    /// Returns an iterator over all the books
+    pub fn iter(&self) -> impl Iterator<Item = &OrderBookState> {
+        self.books.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ws::{
+        BestFiveData, BestFiveFlag, Message, SnapQuote, SubscriptionExchange, SubscriptionMode,
+    };
+
+    use super::MarketDepth;
+
+    fn row(flag: BestFiveFlag, price: i64, quantity: i64) -> BestFiveData {
+        BestFiveData {
+            flag,
+            quantity,
+            price,
+            order_count: 1,
+        }
+    }
+
+    #[test]
+    fn snap_quote_builds_ladders() {
+        let mut best_five_data = vec![
+            row(BestFiveFlag::Buy, 10_000, 30),
+            row(BestFiveFlag::Buy, 10_005, 10),
+            row(BestFiveFlag::Sell, 10_015, 20),
+            row(BestFiveFlag::Sell, 10_010, 30),
+        ];
+        best_five_data.extend((0..6).map(|_| row(BestFiveFlag::Sell, 0, 0)));
+
+        let message = Message {
+            mode: SubscriptionMode::SnapQuote,
+            exchange: SubscriptionExchange::NSECM,
+            token: String::from("3045"),
+            sequence_number: 1,
+            exchange_timestamp: 0,
+            last_traded_price: 10_005,
+            quote: None,
+            snap_quote: Some(SnapQuote {
+                last_traded_timestamp: 0,
+                open_interest: 0,
+                open_interest_change_percentage: 0.0,
+                best_five_data,
+                upper_circuit_limit: 0,
+                lower_circuit_limit: 0,
+                week_52_high_price: 0,
+                week_52_low_price: 0,
+            }),
+        };
+
+        let mut depth = MarketDepth::new();
+        depth.update(&message);
+
+        let book = depth.get(SubscriptionExchange::NSECM, "3045").unwrap();
+        assert_eq!(book.best_bid().unwrap().price, 10_005);
+        assert_eq!(book.best_ask().unwrap().price, 10_010);
+        assert_eq!(book.asks.len(), 2);
+        assert_eq!(book.spread(), Some(5));
+        assert_eq!(book.mid(), Some(10_007.5));
+        assert_eq!(book.microprice(), Some(10_006.25));
+        assert_eq!(book.imbalance(5), Some(-10.0 / 90.0));
+    }
+}
@@ -2,14 +2,17 @@ use dtcm_angel_utils::UtilsError as Error;
 use serde_repr::Deserialize_repr;
 
 /// Buy/Sell Flag
-#[derive(Debug, Deserialize_repr)]
+#[derive(Debug, Deserialize_repr, PartialEq, Eq, Clone, Copy)]
 #[repr(i16)]
 pub enum Flag {
+    /// Sell side row
     Sell = 0,
+    /// Buy side row
     Buy = 1,
 }
 
-#[derive(Debug, Deserialize)]
+/// Row of the best five buy and sell orders
+#[derive(Debug, Deserialize, Clone)]
 pub struct BestFiveData {
     /// Buy/Sell Flag
     pub flag: Flag,
@@ -1,6 +1,8 @@
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
 
-use byteorder::{ReadBytesExt, LE};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use chrono::{DateTime, FixedOffset};
+use dtcm_angel_utils::date::from_epoch_millis_ist;
 use serde::Deserialize;
 
 use crate::ws::{SubscriptionExchange, SubscriptionMode};
@@ -10,7 +12,7 @@ use super::{Quote, SnapQuote};
 type Error = Box<dyn core::error::Error + Send + Sync>;
 
 /// Data response received from websocket server as binary message
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Message {
     /// Subscription Mode
     pub mode: SubscriptionMode,
@@ -73,6 +75,49 @@ impl TryFrom<&[u8]> for Message {
     }
 }
 
+impl Message {
+    /// Returns the exchange timestamp in IST
+    pub fn exchange_time(&self) -> Option<DateTime<FixedOffset>> {
+        from_epoch_millis_ist(self.exchange_timestamp)
+    }
+
+    /// Returns the [`Message`] encoded in the binary layout received from the websocket
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut wtr = vec![];
+        // writing to a vector can not fail
+        self.write_to(&mut wtr).expect("in-memory write");
+        wtr
+    }
+
+    /// Writes the [`Message`] in the binary layout received from the websocket
+    pub fn write_to<W>(&self, wtr: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        wtr.write_u8(self.mode as u8)?;
+        wtr.write_u8(self.exchange as u8)?;
+
+        let mut token = [0u8; 25];
+        let len = self.token.len().min(token.len());
+        token[..len].copy_from_slice(&self.token.as_bytes()[..len]);
+        wtr.write_all(&token)?;
+
+        wtr.write_i64::<LE>(self.sequence_number)?;
+        wtr.write_i64::<LE>(self.exchange_timestamp)?;
+        wtr.write_i64::<LE>(self.last_traded_price)?;
+
+        if let Some(quote) = &self.quote {
+            quote.write_to(wtr)?;
+        }
+
+        if let Some(snap_quote) = &self.snap_quote {
+            snap_quote.write_to(wtr)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl TryFrom<Vec<u8>> for Message {
     type Error = Error;
 
@@ -89,23 +134,7 @@ mod tests {
 
     #[test]
     fn deserialize_snap_quote_works() {
-        let bytes = [
-            3, 1, 49, 48, 54, 50, 54, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            227, 149, 233, 0, 0, 0, 0, 0, 125, 175, 44, 150, 138, 1, 0, 0, 136, 7, 2, 0, 0, 0, 0,
-            0, 5, 0, 0, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 160, 97, 64, 0, 0, 0, 0, 0, 0, 78, 64, 136, 7, 2, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0,
-            0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 184, 4, 2, 0, 0, 0, 0, 0, 208, 107, 160, 98, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 20, 0, 0, 0, 0, 0, 0, 0,
-            124, 9, 2, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0,
-            1, 0, 1, 0, 50, 0, 0, 0, 0, 0, 0, 0, 142, 168, 1, 0, 0, 0, 0, 0, 1, 0, 1, 0, 50, 0, 0,
-            0, 0, 0, 0, 0, 141, 168, 1, 0, 0, 0, 0, 0, 1, 0, 1, 0, 20, 0, 0, 0, 0, 0, 0, 0, 244,
-            165, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 91, 11, 2, 0, 0, 0, 0, 0,
-            1, 0, 0, 0, 50, 0, 0, 0, 0, 0, 0, 0, 92, 11, 2, 0, 0, 0, 0, 0, 1, 0, 0, 0, 54, 0, 0, 0,
-            0, 0, 0, 0, 92, 11, 2, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 112, 111,
-            2, 0, 0, 0, 0, 0, 160, 159, 1, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 44, 145, 1, 0,
-            0, 0, 0, 0,
-        ];
+        let bytes = SNAP_QUOTE;
 
         let m = Message::try_from(&bytes[..]).unwrap();
         assert_eq!(m.mode, SubscriptionMode::SnapQuote);
@@ -113,4 +142,28 @@ mod tests {
         assert_eq!(m.token, "10626");
         println!("{:?}", m);
     }
+
+    #[test]
+    fn encode_snap_quote_round_trips() {
+        let bytes = SNAP_QUOTE;
+        let m = Message::try_from(&bytes[..]).unwrap();
+        assert_eq!(m.to_bytes(), bytes.to_vec());
+    }
+
+    const SNAP_QUOTE: [u8; 379] = [
+        3, 1, 49, 48, 54, 50, 54, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 227,
+        149, 233, 0, 0, 0, 0, 0, 125, 175, 44, 150, 138, 1, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 5, 0,
+        0, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 160, 97,
+        64, 0, 0, 0, 0, 0, 0, 78, 64, 136, 7, 2, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 136, 7,
+        2, 0, 0, 0, 0, 0, 184, 4, 2, 0, 0, 0, 0, 0, 208, 107, 160, 98, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 20, 0, 0, 0, 0, 0, 0, 0, 124, 9, 2, 0, 0, 0, 0, 0,
+        1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 136, 7, 2, 0, 0, 0, 0, 0, 1, 0, 1, 0, 50, 0, 0, 0, 0,
+        0, 0, 0, 142, 168, 1, 0, 0, 0, 0, 0, 1, 0, 1, 0, 50, 0, 0, 0, 0, 0, 0, 0, 141, 168, 1, 0,
+        0, 0, 0, 0, 1, 0, 1, 0, 20, 0, 0, 0, 0, 0, 0, 0, 244, 165, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+        10, 0, 0, 0, 0, 0, 0, 0, 91, 11, 2, 0, 0, 0, 0, 0, 1, 0, 0, 0, 50, 0, 0, 0, 0, 0, 0, 0, 92,
+        11, 2, 0, 0, 0, 0, 0, 1, 0, 0, 0, 54, 0, 0, 0, 0, 0, 0, 0, 92, 11, 2, 0, 0, 0, 0, 0, 1, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 112, 111, 2, 0, 0, 0, 0, 0, 160, 159, 1, 0, 0, 0, 0, 0, 136,
+        7, 2, 0, 0, 0, 0, 0, 44, 145, 1, 0, 0, 0, 0, 0,
+    ];
 }
@@ -8,4 +8,4 @@ mod snap_quote;
 pub use snap_quote::SnapQuote;
 
 mod best_five_data;
-pub use best_five_data::BestFiveData;
+pub use best_five_data::{BestFiveData, Flag};
@@ -1,10 +1,10 @@
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
-use byteorder::{ReadBytesExt, LE};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use dtcm_angel_utils::UtilsError as Error;
 
 /// Response for quote subscription request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Quote {
     /// Last traded quantity
     pub last_traded_quantity: i64,
@@ -43,3 +43,21 @@ impl TryFrom<&mut Cursor<&[u8]>> for Quote {
         })
     }
 }
+
+impl Quote {
+    /// Writes the [`Quote`] in the binary layout received from the websocket
+    pub(crate) fn write_to<W>(&self, wtr: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        wtr.write_i64::<LE>(self.last_traded_quantity)?;
+        wtr.write_i64::<LE>(self.average_traded_price)?;
+        wtr.write_i64::<LE>(self.volume_trade_for_the_day)?;
+        wtr.write_f64::<LE>(self.total_buy_quantity)?;
+        wtr.write_f64::<LE>(self.total_sell_quantity)?;
+        wtr.write_i64::<LE>(self.open_price_of_the_day)?;
+        wtr.write_i64::<LE>(self.high_price_of_the_day)?;
+        wtr.write_i64::<LE>(self.low_price_of_the_day)?;
+        wtr.write_i64::<LE>(self.closed_price)
+    }
+}
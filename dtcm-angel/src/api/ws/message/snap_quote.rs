@@ -1,12 +1,13 @@
-use std::io::Cursor;
+use std::io::{Cursor, Write};
 
-use byteorder::{ReadBytesExt, LE};
-use dtcm_angel_utils::UtilsError;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use chrono::{DateTime, FixedOffset};
+use dtcm_angel_utils::{date::from_epoch_secs_ist, UtilsError};
 
 use super::{best_five_data::Flag, BestFiveData};
 
 /// Response for snap quote subscription request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct SnapQuote {
     /// Last traded timestamp
     pub last_traded_timestamp: i64,
@@ -56,3 +57,32 @@ impl TryFrom<&mut Cursor<&[u8]>> for SnapQuote {
         })
     }
 }
+
+impl SnapQuote {
+    /// Returns the last traded timestamp in IST
+    pub fn last_traded_time(&self) -> Option<DateTime<FixedOffset>> {
+        from_epoch_secs_ist(self.last_traded_timestamp)
+    }
+
+    /// Writes the [`SnapQuote`] in the binary layout received from the websocket
+    pub(crate) fn write_to<W>(&self, wtr: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        wtr.write_i64::<LE>(self.last_traded_timestamp)?;
+        wtr.write_i64::<LE>(self.open_interest)?;
+        wtr.write_f64::<LE>(self.open_interest_change_percentage)?;
+
+        for data in self.best_five_data.iter() {
+            wtr.write_i16::<LE>(data.flag as i16)?;
+            wtr.write_i64::<LE>(data.quantity)?;
+            wtr.write_i64::<LE>(data.price)?;
+            wtr.write_i16::<LE>(data.order_count)?;
+        }
+
+        wtr.write_i64::<LE>(self.upper_circuit_limit)?;
+        wtr.write_i64::<LE>(self.lower_circuit_limit)?;
+        wtr.write_i64::<LE>(self.week_52_high_price)?;
+        wtr.write_i64::<LE>(self.week_52_low_price)
+    }
+}
@@ -2,7 +2,24 @@ mod ws;
 pub use ws::AngelOneWs;
 
 mod message;
-pub use message::Message;
+pub use message::{BestFiveData, Flag as BestFiveFlag, Message, Quote, SnapQuote};
+
+mod candle_builder;
+pub use candle_builder::{Candle, CandleBuilder};
+
+mod market_depth;
+pub use market_depth::{DepthLevel, MarketDepth, OrderBookState};
+
+mod quote_board;
+pub use quote_board::{QuoteBoard, QuoteReceiver};
+
+mod record;
+pub use record::{
+    RecordedFrame, RecordingStream, ReplaySpeed, ReplayStream, TickRecorder, TickReplayer,
+};
+
+mod sequence;
+pub use sequence::{FeedMetrics, LatencyStats, SequenceEvent, SequenceTracker};
 
 mod subscription;
 pub use subscription::{
@@ -15,3 +32,26 @@ pub use ws_order_status::{
     AngelOneWsOrderStatus, ErrorCode as AngelOneWsOrderStatusErrorCode, OrderStatus, StatusCode_,
     StatusEn as AngelOneWsOrderStatusEn,
 };
+
+mod order_updates;
+pub use order_updates::{OrderUpdateClient, OrderUpdateEvent, OrderUpdateHandle};
+
+/// Builds an LTP mode NSE cash message for the ws tests
+#[cfg(test)]
+pub(crate) fn ltp(
+    token: &str,
+    sequence_number: i64,
+    exchange_timestamp: i64,
+    last_traded_price: i64,
+) -> Message {
+    Message {
+        mode: SubscriptionMode::Ltp,
+        exchange: SubscriptionExchange::NSECM,
+        token: token.to_string(),
+        sequence_number,
+        exchange_timestamp,
+        last_traded_price,
+        quote: None,
+        snap_quote: None,
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dtcm_angel_utils::ws::{WsError, WsStream};
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{order::OrderBook, types::OrderStatusKind, user::SessionRes, Result, SmartConnect};
+
+use super::{
+    AngelOneWsOrderStatus, AngelOneWsOrderStatusEn, AngelOneWsOrderStatusErrorCode, OrderStatus,
+};
+
+// Interval of the heartbeat pings sent on the order update websocket
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(10);
+
+// Backoff before the first reconnection attempt
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+// Upper bound of the reconnection backoff
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Session length after which the backoff is reset even without any order update
+const STABLE_SESSION: Duration = Duration::from_secs(60);
+
+// Backoff after the server refused the connection for too many open connections
+const CONNECTION_LIMIT_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Event emitted by the [`OrderUpdateClient`]
+#[derive(Debug, Clone)]
+pub enum OrderUpdateEvent {
+    /// Websocket connected, or reconnected
+    Connected,
+    /// Websocket dropped with the reason, it reconnects after the backoff
+    Disconnected(String),
+    /// Order update received on the websocket
+    Update(OrderStatus),
+    /// Order transition missed while disconnected, found in the order book
+    Reconciled(OrderBook),
+}
+
+/// Order update websocket client which reconnects with backoff, refreshes the auth token,
+/// drops replayed events and reconciles against the order book after a reconnection
+#[derive(Debug, Clone)]
+pub struct OrderUpdateClient {
+    smart_connect: Arc<SmartConnect>,
+    ping_interval: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl OrderUpdateClient {
+    /// Returns a new client for the session of the smart connect
+    pub fn new(smart_connect: Arc<SmartConnect>) -> Self {
+        Self {
+            smart_connect,
+            ping_interval: DEFAULT_PING_INTERVAL,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+
+    /// Sets the interval of the heartbeat pings
+    pub fn ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self
+    }
+
+    /// Sets the initial and the maximum reconnection backoff, doubled on every failed attempt
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Connects in the background and returns the handle streaming the events
+    pub fn start(self) -> Result<OrderUpdateHandle> {
+        let session = self.smart_connect.session()?.clone();
+        let (sender, events) = mpsc::unbounded_channel();
+
+        let runner = Runner {
+            backoff: Backoff::new(self.initial_backoff, self.max_backoff),
+            client: self,
+            session,
+            events: sender,
+            tracker: UpdateTracker::default(),
+            received_update: false,
+        };
+
+        Ok(OrderUpdateHandle {
+            events,
+            task: tokio::spawn(runner.run()),
+        })
+    }
+}
+
+/// Handle to a running [`OrderUpdateClient`]
+#[derive(Debug)]
+pub struct OrderUpdateHandle {
+    events: mpsc::UnboundedReceiver<OrderUpdateEvent>,
+    task: JoinHandle<()>,
+}
+
+impl OrderUpdateHandle {
+    /// Returns the next event, none once the client has stopped
+    pub async fn next_event(&mut self) -> Option<OrderUpdateEvent> {
+        self.events.recv().await
+    }
+
+    /// Closes the websocket and stops reconnecting
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for OrderUpdateHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Exponential reconnection backoff
+#[derive(Debug)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    /// Returns the delay before the next attempt and doubles the following one
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+/// Part of the order compared to tell a new transition from a replayed event
+#[derive(Debug, PartialEq)]
+struct Fingerprint {
+    order_status: OrderStatusKind,
+    filled_shares: u64,
+    quantity: u64,
+    price: f64,
+    trigger_price: f64,
+}
+
+impl From<&OrderBook> for Fingerprint {
+    fn from(order: &OrderBook) -> Self {
+        Self {
+            order_status: order.order_status.clone(),
+            filled_shares: order.filled_shares,
+            quantity: order.quantity,
+            price: order.price,
+            trigger_price: order.trigger_price,
+        }
+    }
+}
+
+/// Last state seen of every order
+#[derive(Debug, Default)]
+struct UpdateTracker {
+    seen: HashMap<String, Fingerprint>,
+}
+
+impl UpdateTracker {
+    /// Records the order and returns true if its state is newer than the one seen, updates
+    /// of a terminal order or with fewer filled shares are older events replayed late
+    fn observe(&mut self, order: &OrderBook) -> bool {
+        let fingerprint = Fingerprint::from(order);
+        if let Some(seen) = self.seen.get(&order.order_id)
+            && (*seen == fingerprint
+                || seen.order_status.is_terminal()
+                || fingerprint.filled_shares < seen.filled_shares)
+        {
+            return false;
+        }
+        self.seen.insert(order.order_id.clone(), fingerprint);
+        true
+    }
+}
+
+/// Why the websocket session ended
+#[derive(Debug)]
+enum Disconnect {
+    /// Auth token rejected, refreshed before reconnecting
+    Unauthorized(String),
+    /// Websocket closed or failed
+    Closed(String),
+    /// Too many connections open for the client, retried after a long backoff
+    LimitBreached(String),
+}
+
+/// Background task of an [`OrderUpdateClient`]
+struct Runner {
+    client: OrderUpdateClient,
+    session: SessionRes,
+    events: mpsc::UnboundedSender<OrderUpdateEvent>,
+    tracker: UpdateTracker,
+    backoff: Backoff,
+    received_update: bool,
+}
+
+impl Runner {
+    async fn run(mut self) {
+        let mut connected_once = false;
+
+        while !self.events.is_closed() {
+            let ws = AngelOneWsOrderStatus::new(
+                self.client.smart_connect.client_code.as_str(),
+                self.session.feed_token.as_str(),
+                self.session.jwt_token.as_str(),
+            );
+
+            let disconnect = match ws.stream::<OrderStatus>().await {
+                Ok(mut stream) => {
+                    info!("Order update websocket connected");
+                    self.emit(OrderUpdateEvent::Connected);
+                    self.reconcile(connected_once).await;
+                    connected_once = true;
+
+                    let connected_at = Instant::now();
+                    self.received_update = false;
+                    let disconnect = self.pump(&mut stream).await;
+                    // a session dropped right after connecting keeps backing off
+                    if self.received_update || connected_at.elapsed() >= STABLE_SESSION {
+                        self.backoff.reset();
+                    }
+                    disconnect
+                }
+                Err(e) if is_unauthorized(e.as_ref()) => Disconnect::Unauthorized(e.to_string()),
+                Err(e) => Disconnect::Closed(e.to_string()),
+            };
+
+            let delay = match disconnect {
+                Disconnect::Unauthorized(reason) => {
+                    warn!("Order update websocket unauthorized: {reason}");
+                    self.emit(OrderUpdateEvent::Disconnected(reason));
+                    self.refresh_token().await;
+                    self.backoff.next_delay()
+                }
+                Disconnect::Closed(reason) => {
+                    warn!("Order update websocket disconnected: {reason}");
+                    self.emit(OrderUpdateEvent::Disconnected(reason));
+                    self.backoff.next_delay()
+                }
+                Disconnect::LimitBreached(reason) => {
+                    warn!("Order update websocket connection limit breached: {reason}");
+                    self.emit(OrderUpdateEvent::Disconnected(reason));
+                    self.backoff.next_delay().max(CONNECTION_LIMIT_BACKOFF)
+                }
+            };
+
+            tokio::time::sleep(delay).await;
+        }
+        debug!("Order update client stopped");
+    }
+
+    /// Forwards the updates until the websocket ends
+    async fn pump(&mut self, stream: &mut WsStream<OrderStatus>) -> Disconnect {
+        let mut timer = tokio::time::interval(self.client.ping_interval);
+        loop {
+            tokio::select! {
+                _ = timer.tick() => {
+                    if let Err(e) = stream.send_text("ping").await {
+                        return Disconnect::Closed(format!("ping failed: {e}"));
+                    }
+                }
+                update = stream.next() => match update {
+                    Some(Ok(update)) => {
+                        if let Some(disconnect) = self.on_update(update) {
+                            return disconnect;
+                        }
+                    }
+                    Some(Err(e)) => error!("Failed to read the order update: {e}"),
+                    None => return Disconnect::Closed(String::from("stream closed")),
+                }
+            }
+        }
+    }
+
+    /// Emits the update unless it was replayed, ends the session on the auth error codes
+    fn on_update(&mut self, update: OrderStatus) -> Option<Disconnect> {
+        if let Some(disconnect) = disconnect(&update) {
+            return Some(disconnect);
+        }
+        if update.order_status == AngelOneWsOrderStatusEn::AfterSuccessfulConnection {
+            return None;
+        }
+        self.received_update = true;
+
+        if self.tracker.observe(&update.order_data) {
+            self.emit(OrderUpdateEvent::Update(update));
+        } else {
+            debug!("Dropped replayed update of {}", update.order_data.order_id);
+        }
+        None
+    }
+
+    /// Compares the order book with the seen states, the first call only seeds them
+    async fn reconcile(&mut self, emit: bool) {
+        let orders = match self.client.smart_connect.order_book().await {
+            Ok(orders) => orders,
+            Err(e) => {
+                error!("Failed to reconcile the order updates: {e}");
+                return;
+            }
+        };
+
+        for order in orders {
+            if self.tracker.observe(&order) && emit {
+                self.emit(OrderUpdateEvent::Reconciled(order));
+            }
+        }
+    }
+
+    /// Regenerates the jwt and feed tokens with the refresh token, the jwt token of the
+    /// shared client is replaced as well for the reconciliation
+    async fn refresh_token(&mut self) {
+        match self.client.smart_connect.refresh_token().await {
+            Ok(session) => {
+                info!("Order update auth token refreshed");
+                self.session = session;
+            }
+            Err(e) => error!("Failed to refresh the order update auth token: {e}"),
+        }
+    }
+
+    fn emit(&self, event: OrderUpdateEvent) {
+        // the receiver is gone once the handle is dropped, the loop then stops
+        let _ = self.events.send(event);
+    }
+}
+
+/// Returns why the session ends for the auth and connection limit error codes
+fn disconnect(update: &OrderStatus) -> Option<Disconnect> {
+    let reason = || format!("{:?}", update.error_message);
+    match &update.error_message {
+        AngelOneWsOrderStatusErrorCode::AuthorizationTokenInvalid
+        | AngelOneWsOrderStatusErrorCode::AuthorizationTokenExpired => {
+            Some(Disconnect::Unauthorized(reason()))
+        }
+        AngelOneWsOrderStatusErrorCode::ConnectionLimitBreached => {
+            Some(Disconnect::LimitBreached(reason()))
+        }
+        AngelOneWsOrderStatusErrorCode::NoError | AngelOneWsOrderStatusErrorCode::Unknown(_) => {
+            None
+        }
+    }
+}
+
+/// Returns true if the websocket handshake was refused with 401 or 403
+fn is_unauthorized(e: &(dyn core::error::Error + Send + Sync + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<WsError>(),
+        Some(WsError::Http(res)) if matches!(res.status().as_u16(), 401 | 403)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http_serde::http::StatusCode;
+
+    use crate::{
+        order::OrderBook,
+        types::OrderStatusKind,
+        ws::{
+            AngelOneWsOrderStatusEn, AngelOneWsOrderStatusErrorCode as ErrorCode, OrderStatus,
+            StatusCode_,
+        },
+    };
+
+    use super::{disconnect, Backoff, Disconnect, UpdateTracker};
+
+    fn order(order_status: OrderStatusKind, filled_shares: u64) -> OrderBook {
+        OrderBook {
+            order_id: String::from("241226001121984"),
+            order_status,
+            quantity: 10,
+            filled_shares,
+            ..Default::default()
+        }
+    }
+
+    fn update(error_message: ErrorCode) -> OrderStatus {
+        OrderStatus {
+            user_id: String::from("A123456"),
+            status_code: StatusCode_(StatusCode::OK),
+            order_status: AngelOneWsOrderStatusEn::Open,
+            error_message,
+            order_data: order(OrderStatusKind::Open, 0),
+        }
+    }
+
+    #[test]
+    fn replayed_updates_are_dropped() {
+        let mut tracker = UpdateTracker::default();
+        assert!(tracker.observe(&order(OrderStatusKind::Open, 0)));
+        // replayed after the reconnection
+        assert!(!tracker.observe(&order(OrderStatusKind::Open, 0)));
+        assert!(tracker.observe(&order(OrderStatusKind::Open, 4)));
+        // order book fetched after the reconnection shows the missed fill
+        assert!(tracker.observe(&order(OrderStatusKind::Complete, 10)));
+        assert!(!tracker.observe(&order(OrderStatusKind::Complete, 10)));
+    }
+
+    #[test]
+    fn stale_updates_are_dropped() {
+        let mut tracker = UpdateTracker::default();
+        assert!(tracker.observe(&order(OrderStatusKind::Open, 4)));
+        // an older partial fill replayed after a newer one
+        assert!(!tracker.observe(&order(OrderStatusKind::Open, 2)));
+        assert!(tracker.observe(&order(OrderStatusKind::Cancelled, 4)));
+        // the order can no longer change once terminal
+        assert!(!tracker.observe(&order(OrderStatusKind::Open, 4)));
+        assert!(!tracker.observe(&order(OrderStatusKind::Complete, 10)));
+    }
+
+    #[test]
+    fn reconnection_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        let delays: Vec<_> = (0..4).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn error_codes_end_the_session() {
+        assert!(matches!(
+            disconnect(&update(ErrorCode::AuthorizationTokenExpired)),
+            Some(Disconnect::Unauthorized(_))
+        ));
+        assert!(matches!(
+            disconnect(&update(ErrorCode::AuthorizationTokenInvalid)),
+            Some(Disconnect::Unauthorized(_))
+        ));
+        assert!(matches!(
+            disconnect(&update(ErrorCode::ConnectionLimitBreached)),
+            Some(Disconnect::LimitBreached(_))
+        ));
+        assert!(disconnect(&update(ErrorCode::NoError)).is_none());
+        assert!(disconnect(&update(ErrorCode::Unknown(String::from("420")))).is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use dtcm_angel_utils::date::ist;
+use futures_util::StreamExt;
+use log::{debug, error, warn};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    market::{MarketDataReq, MarketDataResInner, OrderDepthInner},
+    types::{ExchangeType, MarketMode},
+    Result, SmartConnect,
+};
+
+use super::{
+    AngelOneWs, BestFiveData, BestFiveFlag, Message, Quote, SnapQuote, SubscriptionExchange,
+    SubscriptionMode, SubscriptionRequest,
+};
+
+type Key = (SubscriptionExchange, String);
+
+/// Receiver notified whenever a newer [`Message`] is stored for the token
+pub type QuoteReceiver = watch::Receiver<Option<Message>>;
+
+/// Concurrent cache of the latest [`Message`] per token, fed by the websocket
+#[derive(Debug, Clone, Default)]
+pub struct QuoteBoard {
+    quotes: Arc<RwLock<HashMap<Key, watch::Sender<Option<Message>>>>>,
+}
+
+impl QuoteBoard {
+    /// Returns a new instance for [`QuoteBoard`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<Key, watch::Sender<Option<Message>>>> {
+        self.quotes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<Key, watch::Sender<Option<Message>>>> {
+        self.quotes.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Stores the [`Message`] unless a message with a later exchange timestamp is already held
+    pub fn update(&self, message: Message) {
+        let key = (message.exchange, message.token.clone());
+        let sender = self
+            .write()
+            .entry(key)
+            .or_insert_with(|| watch::channel(None).0)
+            .clone();
+
+        sender.send_if_modified(|latest| match latest {
+            Some(latest) if latest.exchange_timestamp > message.exchange_timestamp => false,
+            _ => {
+                *latest = Some(message);
+                true
+            }
+        });
+    }
+
+    /// Returns the latest [`Message`] for the token
+    pub fn get(&self, exchange: SubscriptionExchange, token: &str) -> Option<Message> {
+        self.read()
+            .get(&(exchange, token.to_string()))
+            .and_then(|sender| sender.borrow().clone())
+    }
+
+    /// Returns a receiver for the latest [`Message`] of the token, the token does not need
+    /// to have been seen yet
+    pub fn watch(&self, exchange: SubscriptionExchange, token: &str) -> QuoteReceiver {
+        self.write()
+            .entry((exchange, token.to_string()))
+            .or_insert_with(|| watch::channel(None).0)
+            .subscribe()
+    }
+
+    /// Returns the time elapsed since the exchange timestamp of the latest [`Message`]
+    pub fn age(&self, exchange: SubscriptionExchange, token: &str) -> Option<TimeDelta> {
+        self.get(exchange, token).map(|message| {
+            TimeDelta::milliseconds(Utc::now().timestamp_millis() - message.exchange_timestamp)
+        })
+    }
+
+    /// Returns true if the token has no [`Message`] or the latest one is older than `max_age`
+    pub fn is_stale(
+        &self,
+        exchange: SubscriptionExchange,
+        token: &str,
+        max_age: TimeDelta,
+    ) -> bool {
+        self.age(exchange, token).is_none_or(|age| age > max_age)
+    }
+
+    /// Returns the tokens whose latest [`Message`] is missing or older than `max_age`
+    pub fn stale(&self, max_age: TimeDelta) -> Vec<(SubscriptionExchange, String)> {
+        let now = Utc::now().timestamp_millis();
+        self.read()
+            .iter()
+            .filter(|(_, sender)| {
+                sender.borrow().as_ref().is_none_or(|message| {
+                    TimeDelta::milliseconds(now - message.exchange_timestamp) > max_age
+                })
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Seeds the board with [`SmartConnect::market_data`] for the subscribed tokens which have
+    /// not received a [`Message`] yet
+    pub async fn seed(
+        &self,
+        smart_connect: &SmartConnect,
+        subscription: &SubscriptionRequest,
+    ) -> Result<()> {
+        let mut exchange_tokens: HashMap<ExchangeType, Vec<String>> = HashMap::new();
+        for token_list in &subscription.param.token_list {
+            let missing = token_list
+                .tokens
+                .iter()
+                .filter(|token| self.get(token_list.exchange, token).is_none())
+                .cloned();
+            exchange_tokens
+                .entry(token_list.exchange.into())
+                .or_default()
+                .extend(missing);
+        }
+        exchange_tokens.retain(|_, tokens| !tokens.is_empty());
+
+        if exchange_tokens.is_empty() {
+            return Ok(());
+        }
+
+        let market_data_req = MarketDataReq::new(MarketMode::Full, exchange_tokens);
+        let market_data = smart_connect.market_data(&market_data_req).await?;
+
+        for unfetched in &market_data.unfetched {
+            warn!(
+                "Snapshot unavailable for {} {}: {}",
+                unfetched.exchange, unfetched.symbol_token, unfetched.message
+            );
+        }
+
+        market_data
+            .fetched
+            .iter()
+            .filter_map(snapshot_message)
+            .for_each(|message| self.update(message));
+
+        Ok(())
+    }
+
+    /// Connects the websocket, sends the subscription and spawns a task storing every
+    /// received [`Message`] on the board. The task ends when the stream closes.
+    pub async fn subscribe(
+        &self,
+        ws: &AngelOneWs,
+        subscription: SubscriptionRequest,
+    ) -> Result<JoinHandle<()>> {
+        let mut stream = ws.stream::<Message>().await?;
+        stream.subscribe(subscription).await?;
+
+        let board = self.clone();
+        Ok(tokio::spawn(async move {
+            while let Some(message) = stream.next().await {
+                match message {
+                    Ok(message) => board.update(message),
+                    Err(e) => error!("Quote board failed to read the websocket message: {e}"),
+                }
+            }
+            debug!("Quote board websocket stream closed");
+        }))
+    }
+}
+
+/// Converts the market data snapshot to a [`Message`] with prices in paise
+fn snapshot_message(data: &MarketDataResInner) -> Option<Message> {
+    let exchange_type = serde_json::from_value(data.exchange.clone().into()).ok()?;
+    let exchange = SubscriptionExchange::try_from(&exchange_type).ok()?;
+
+    let paise = |price: f64| (price * 100.0).round() as i64;
+
+    let exchange_timestamp = data
+        .data
+        .as_ref()
+        .and_then(|full| {
+            NaiveDateTime::parse_from_str(&full.exch_feed_time, "%d-%b-%Y %H:%M:%S").ok()
+        })
+        .and_then(|time| time.and_local_timezone(ist()).single())
+        .map_or_else(
+            || Utc::now().timestamp_millis(),
+            |time| time.timestamp_millis(),
+        );
+
+    let mut message = Message {
+        mode: SubscriptionMode::Ltp,
+        exchange,
+        token: data.symbol_token.clone(),
+        sequence_number: 0,
+        exchange_timestamp,
+        last_traded_price: paise(data.ltp),
+        quote: None,
+        snap_quote: None,
+    };
+
+    let (Some(ohlc), Some(full)) = (data.ohlc, &data.data) else {
+        return Some(message);
+    };
+
+    message.mode = SubscriptionMode::SnapQuote;
+    message.quote = Some(Quote {
+        last_traded_quantity: full.last_trade_qty,
+        average_traded_price: paise(full.avg_price),
+        volume_trade_for_the_day: full.trade_volume,
+        total_buy_quantity: full.tot_buy_quan as f64,
+        total_sell_quantity: full.tot_sell_quan as f64,
+        open_price_of_the_day: paise(ohlc.open),
+        high_price_of_the_day: paise(ohlc.high),
+        low_price_of_the_day: paise(ohlc.low),
+        closed_price: paise(ohlc.close),
+    });
+
+    let levels = |flag: BestFiveFlag, depth: &[OrderDepthInner]| {
+        depth
+            .iter()
+            .map(|level| BestFiveData {
+                flag,
+                quantity: level.quantity as i64,
+                price: paise(level.price),
+                order_count: level.orders as i16,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut best_five_data = levels(BestFiveFlag::Buy, &full.depth.buy);
+    best_five_data.extend(levels(BestFiveFlag::Sell, &full.depth.sell));
+
+    message.snap_quote = Some(SnapQuote {
+        last_traded_timestamp: exchange_timestamp / 1000,
+        open_interest: full.opn_interest,
+        open_interest_change_percentage: 0.0,
+        best_five_data,
+        upper_circuit_limit: paise(full.upper_circuit),
+        lower_circuit_limit: paise(full.lower_circuit),
+        week_52_high_price: paise(full.week_52_high),
+        week_52_low_price: paise(full.week_52_low),
+    });
+
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use crate::ws::{ltp, SubscriptionExchange};
+
+    use super::QuoteBoard;
+
+    #[tokio::test]
+    async fn board_keeps_latest_message() {
+        let board = QuoteBoard::new();
+        let mut rx = board.watch(SubscriptionExchange::NSECM, "3045");
+        assert!(board.is_stale(SubscriptionExchange::NSECM, "3045", TimeDelta::seconds(5)));
+
+        let now = Utc::now().timestamp_millis();
+        board.update(ltp("3045", 0, now, 80_000));
+        rx.changed().await.unwrap();
+        assert_eq!(rx.borrow().as_ref().unwrap().last_traded_price, 80_000);
+
+        // older ticks do not replace the latest one
+        board.update(ltp("3045", 0, now - 1_000, 79_000));
+        let latest = board.get(SubscriptionExchange::NSECM, "3045").unwrap();
+        assert_eq!(latest.last_traded_price, 80_000);
+        assert!(!board.is_stale(SubscriptionExchange::NSECM, "3045", TimeDelta::seconds(5)));
+
+        let mut old = ltp("3045", 0, now - 60_000, 10_000);
+        old.token = String::from("2885");
+        board.update(old);
+        assert_eq!(
+            board.stale(TimeDelta::seconds(5)),
+            vec![(SubscriptionExchange::NSECM, String::from("2885"))]
+        );
+    }
+}
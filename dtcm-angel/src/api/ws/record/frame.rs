@@ -0,0 +1,146 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use chrono::{DateTime, Utc};
+use serde::{
+    de::{Error as _, IgnoredAny},
+    Deserialize, Deserializer,
+};
+
+use crate::ws::Message;
+
+type Error = Box<dyn core::error::Error + Send + Sync>;
+
+// Leading bytes of every tick recording
+pub(super) const MAGIC: &[u8; 8] = b"DTCMTICK";
+
+// Version of the recording layout
+pub(super) const VERSION: u8 = 1;
+
+// Leading bytes of a gzip stream
+pub(super) const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Largest payload of a frame, far above the largest tick so that a corrupt length fails
+// instead of allocating up to 4 GiB
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Websocket frame as stored in a tick recording, a websocket stream of frames feeds
+/// [`super::TickRecorder::tap`] with the payloads as received
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Local time at which the frame was received
+    pub received_at: DateTime<Utc>,
+    /// Binary payload of the frame
+    pub payload: Vec<u8>,
+}
+
+impl RecordedFrame {
+    /// Returns a new instance for [`RecordedFrame`]
+    pub fn new(received_at: DateTime<Utc>, payload: Vec<u8>) -> Self {
+        Self {
+            received_at,
+            payload,
+        }
+    }
+
+    /// Decodes the payload into a [`Message`]
+    pub fn message(&self) -> Result<Message, Error> {
+        Message::try_from(self.payload.as_slice())
+    }
+
+    /// Writes the frame as `received_at (i64 micros) | length (u32) | payload`
+    pub(super) fn write_to<W>(&self, wtr: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        if self.payload.len() > MAX_FRAME_LEN {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "frame too large"));
+        }
+        let len = self.payload.len() as u32;
+
+        wtr.write_i64::<LE>(self.received_at.timestamp_micros())?;
+        wtr.write_u32::<LE>(len)?;
+        wtr.write_all(&self.payload)
+    }
+
+    /// Reads the next frame, returns `None` at the end of the recording
+    pub(super) fn read_from<R>(rdr: &mut R) -> io::Result<Option<Self>>
+    where
+        R: Read,
+    {
+        let micros = match rdr.read_i64::<LE>() {
+            Ok(micros) => micros,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let received_at = DateTime::from_timestamp_micros(micros)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid frame timestamp"))?;
+
+        let len = rdr.read_u32::<LE>()? as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("frame length {len} exceeds {MAX_FRAME_LEN}"),
+            ));
+        }
+        let mut payload = vec![0u8; len];
+        rdr.read_exact(&mut payload)?;
+
+        Ok(Some(Self::new(received_at, payload)))
+    }
+}
+
+impl TryFrom<Vec<u8>> for RecordedFrame {
+    type Error = Error;
+
+    /// Stamps the binary payload with the current time
+    fn try_from(payload: Vec<u8>) -> Result<Self, Self::Error> {
+        Ok(Self::new(Utc::now(), payload))
+    }
+}
+
+// text frames carry no ticks, the websocket stream passes them on as errors
+impl<'de> Deserialize<'de> for RecordedFrame {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        IgnoredAny::deserialize(deserializer)?;
+        Err(D::Error::custom("text frame is not a tick frame"))
+    }
+}
+
+/// Writes the recording header
+pub(super) fn write_header<W>(wtr: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    wtr.write_all(MAGIC)?;
+    wtr.write_u8(VERSION)
+}
+
+/// Reads and validates the recording header
+pub(super) fn read_header<R>(rdr: &mut R) -> io::Result<()>
+where
+    R: Read,
+{
+    let mut magic = [0u8; 8];
+    rdr.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "not a tick recording",
+        ));
+    }
+
+    let version = rdr.read_u8()?;
+    if version != VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported tick recording version {version}"),
+        ));
+    }
+
+    Ok(())
+}
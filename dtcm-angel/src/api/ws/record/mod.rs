@@ -0,0 +1,123 @@
+mod frame;
+pub use frame::RecordedFrame;
+
+mod recorder;
+pub use recorder::{RecordingStream, TickRecorder};
+
+mod replayer;
+pub use replayer::{ReplaySpeed, ReplayStream, TickReplayer};
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Cursor, ErrorKind},
+    };
+
+    use chrono::{TimeDelta, Utc};
+    use tokio_stream::StreamExt;
+
+    use crate::ws::{ltp, Message};
+
+    use super::{RecordedFrame, ReplaySpeed, TickRecorder, TickReplayer};
+
+    const EXCHANGE_MILLIS: i64 = 1_694_000_000_000;
+
+    fn tick(token: &str, sequence_number: i64) -> Message {
+        ltp(
+            token,
+            sequence_number,
+            EXCHANGE_MILLIS + sequence_number,
+            10_000 + sequence_number,
+        )
+    }
+
+    #[tokio::test]
+    async fn replay_yields_recorded_messages() {
+        let mut recorder = TickRecorder::new(vec![]).unwrap();
+        let now = Utc::now();
+        for seq in 0..3 {
+            let at = now + TimeDelta::milliseconds(seq);
+            recorder
+                .record_frame_at(at, &tick("3045", seq).to_bytes())
+                .unwrap();
+        }
+
+        let recording = recorder.into_inner();
+        let mut stream = TickReplayer::from_reader(Cursor::new(recording))
+            .unwrap()
+            .speed(ReplaySpeed::Unthrottled)
+            .stream();
+
+        let mut seqs = vec![];
+        while let Some(m) = stream.next().await {
+            let m = m.unwrap();
+            assert_eq!(m.token, "3045");
+            seqs.push(m.sequence_number);
+        }
+        assert_eq!(seqs, vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn compressed_sessions_are_appended() {
+        let path = std::env::temp_dir().join(format!("dtcm-ticks-{}.bin", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        for seq in 0..2 {
+            let mut recorder = TickRecorder::create(&path, true).unwrap();
+            recorder.record(&tick("2885", seq)).unwrap();
+        }
+
+        let frames = TickReplayer::open(&path)
+            .unwrap()
+            .frames()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let seqs = frames
+            .iter()
+            .map(|f| f.message().unwrap().sequence_number)
+            .collect::<Vec<_>>();
+        assert_eq!(seqs, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn tap_records_passing_frames_as_received() {
+        // trailing bytes the decoding ignores are recorded as well
+        let mut payload = tick("3045", 7).to_bytes();
+        payload.extend_from_slice(&[0xff, 0xfe]);
+        let frame = RecordedFrame::new(Utc::now(), payload);
+
+        let live = tokio_stream::iter(vec![Ok(frame.clone())]);
+        let mut stream = TickRecorder::new(vec![]).unwrap().tap(live);
+        assert_eq!(stream.next().await.unwrap().unwrap().sequence_number, 7);
+
+        let (_, recorder) = stream.into_inner();
+        let frames = TickReplayer::from_reader(Cursor::new(recorder.into_inner()))
+            .unwrap()
+            .frames()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload, frame.payload);
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut recorder = TickRecorder::new(vec![]).unwrap();
+        let oversized = recorder.record_frame(&vec![0; 1 << 20]).unwrap_err();
+        assert_eq!(oversized.kind(), ErrorKind::InvalidInput);
+
+        // a corrupt length fails instead of allocating the frame
+        let mut recording = recorder.into_inner();
+        recording.extend_from_slice(&0i64.to_le_bytes());
+        recording.extend_from_slice(&u32::MAX.to_le_bytes());
+        let corrupt = TickReplayer::from_reader(Cursor::new(recording))
+            .unwrap()
+            .frames()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(corrupt.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use futures_util::Stream;
+use log::error;
+
+use crate::ws::Message;
+
+use super::{frame::write_header, RecordedFrame};
+
+type Error = Box<dyn core::error::Error + Send + Sync>;
+
+/// Writes websocket frames with their receive timestamps to an append-only recording
+pub struct TickRecorder<W: Write> {
+    writer: W,
+}
+
+impl TickRecorder<Box<dyn Write + Send>> {
+    /// Opens the recording at path in append mode, the header is written only to a new file.
+    /// With compression every session is appended as a separate gzip member, so a file must
+    /// not mix compressed and uncompressed sessions.
+    pub fn create<P>(path: P, compress: bool) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_new = file.metadata()?.len() == 0;
+
+        let writer: Box<dyn Write + Send> = match compress {
+            true => Box::new(GzEncoder::new(BufWriter::new(file), Compression::fast())),
+            false => Box::new(BufWriter::new(file)),
+        };
+
+        match is_new {
+            true => Self::new(writer),
+            false => Ok(Self { writer }),
+        }
+    }
+}
+
+impl<W: Write> TickRecorder<W> {
+    /// Returns a new instance for [`TickRecorder`] after writing the header to the writer
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        write_header(&mut writer)?;
+        Ok(Self { writer })
+    }
+
+    /// Records the binary frame stamped with the current time
+    pub fn record_frame(&mut self, payload: &[u8]) -> io::Result<()> {
+        self.record_frame_at(Utc::now(), payload)
+    }
+
+    /// Records the binary frame stamped with the given receive time
+    pub fn record_frame_at(
+        &mut self,
+        received_at: DateTime<Utc>,
+        payload: &[u8],
+    ) -> io::Result<()> {
+        RecordedFrame::new(received_at, payload.to_vec()).write_to(&mut self.writer)
+    }
+
+    /// Records the [`Message`] re-encoded in its wire layout stamped with the current time,
+    /// [`TickRecorder::tap`] records the frames as received
+    pub fn record(&mut self, message: &Message) -> io::Result<()> {
+        self.record_frame(&message.to_bytes())
+    }
+
+    /// Flushes the buffered frames to the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the inner writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Wraps the frame stream, e.g. `ws.stream::<RecordedFrame>()`, so that every received
+    /// frame is recorded as is before being passed on decoded into a [`Message`]
+    pub fn tap<S>(self, stream: S) -> RecordingStream<S, W>
+    where
+        S: Stream<Item = Result<RecordedFrame, Error>> + Unpin,
+    {
+        RecordingStream {
+            inner: stream,
+            recorder: self,
+        }
+    }
+}
+
+/// Frame stream recording every frame passing through it, yielding the decoded [`Message`]
+pub struct RecordingStream<S, W: Write> {
+    inner: S,
+    recorder: TickRecorder<W>,
+}
+
+impl<S, W: Write> RecordingStream<S, W> {
+    /// Returns the inner stream and the recorder
+    pub fn into_inner(self) -> (S, TickRecorder<W>) {
+        (self.inner, self.recorder)
+    }
+}
+
+impl<S, W> Stream for RecordingStream<S, W>
+where
+    S: Stream<Item = Result<RecordedFrame, Error>> + Unpin,
+    W: Write + Unpin,
+{
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let frame = match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(frame))) => frame,
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if let Err(e) = frame.write_to(&mut self.recorder.writer) {
+            error!("Failed to record tick frame: {e}");
+        }
+
+        Poll::Ready(Some(frame.message()))
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    pin::Pin,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use flate2::bufread::MultiGzDecoder;
+use futures_util::{stream, Stream};
+use tokio::time::{sleep_until, Instant};
+
+use crate::ws::Message;
+
+use super::{
+    frame::{read_header, GZIP_MAGIC},
+    RecordedFrame,
+};
+
+type Error = Box<dyn core::error::Error + Send + Sync>;
+
+/// Message stream produced by the [`TickReplayer`], yields the same items as the live
+/// [`dtcm_angel_utils::ws::WsStream`]
+pub type ReplayStream = Pin<Box<dyn Stream<Item = Result<Message, Error>> + Send>>;
+
+/// Pace at which the recorded frames are replayed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplaySpeed {
+    /// Frames are spaced as they were received
+    #[default]
+    Original,
+    /// Frames are spaced as received, divided by the factor
+    Accelerated(f64),
+    /// Frames are yielded as fast as they can be read
+    Unthrottled,
+}
+
+/// Reads a tick recording written by [`super::TickRecorder`] and replays it as a message stream
+pub struct TickReplayer {
+    reader: Box<dyn BufRead + Send>,
+    speed: ReplaySpeed,
+}
+
+impl TickReplayer {
+    /// Opens the recording at path, compressed recordings are detected automatically
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Returns a new instance for [`TickReplayer`] reading the recording from the reader
+    pub fn from_reader<R>(reader: R) -> io::Result<Self>
+    where
+        R: Read + Send + 'static,
+    {
+        let mut reader = BufReader::new(reader);
+
+        let mut reader: Box<dyn BufRead + Send> = match reader.fill_buf()?.starts_with(&GZIP_MAGIC)
+        {
+            true => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+            false => Box::new(reader),
+        };
+
+        read_header(&mut reader)?;
+
+        Ok(Self {
+            reader,
+            speed: Default::default(),
+        })
+    }
+
+    /// Sets the [`ReplaySpeed`]
+    pub fn speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Returns the recorded frames in the order they were received
+    pub fn frames(self) -> impl Iterator<Item = io::Result<RecordedFrame>> {
+        let mut reader = self.reader;
+        std::iter::from_fn(move || RecordedFrame::read_from(&mut reader).transpose())
+    }
+
+    /// Returns the stream replaying the recorded messages at the configured [`ReplaySpeed`]
+    pub fn stream(self) -> ReplayStream {
+        let state = ReplayState {
+            reader: self.reader,
+            speed: self.speed,
+            origin: None,
+        };
+
+        Box::pin(stream::unfold(state, |mut state| async move {
+            let frame = match RecordedFrame::read_from(&mut state.reader) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return None,
+                Err(e) => return Some((Err(e.into()), state)),
+            };
+
+            if let Some(deadline) = state.deadline(frame.received_at) {
+                sleep_until(deadline).await;
+            }
+
+            Some((frame.message(), state))
+        }))
+    }
+}
+
+/// Progress of the replay stream
+struct ReplayState {
+    reader: Box<dyn BufRead + Send>,
+    speed: ReplaySpeed,
+    // first frame time and the instant it was replayed at
+    origin: Option<(DateTime<Utc>, Instant)>,
+}
+
+impl ReplayState {
+    /// Returns the instant at which the frame received at the given time is due
+    fn deadline(&mut self, received_at: DateTime<Utc>) -> Option<Instant> {
+        let factor = match self.speed {
+            ReplaySpeed::Original => 1.0,
+            ReplaySpeed::Accelerated(factor) if factor > 0.0 => factor,
+            ReplaySpeed::Accelerated(_) | ReplaySpeed::Unthrottled => return None,
+        };
+
+        let (first_received_at, started_at) = *self
+            .origin
+            .get_or_insert_with(|| (received_at, Instant::now()));
+
+        let elapsed = (received_at - first_received_at)
+            .to_std()
+            .unwrap_or_default();
+
+        Some(started_at + Duration::from_secs_f64(elapsed.as_secs_f64() / factor))
+    }
+}
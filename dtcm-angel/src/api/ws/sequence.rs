@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::warn;
+
+use super::{Message, SubscriptionExchange};
+
+// Nanoseconds per second
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// Outcome of checking the sequence number of a tick against the last one seen for its token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceEvent {
+    /// First tick seen for the token
+    First,
+    /// Tick follows the previous one
+    InOrder,
+    /// Ticks were skipped between the previous and this one
+    Gap {
+        /// Sequence number that was expected
+        expected: i64,
+        /// Sequence number that was received
+        received: i64,
+    },
+    /// Tick repeats the last sequence number
+    Duplicate,
+    /// Tick is older than the last one seen
+    OutOfOrder {
+        /// Last sequence number seen
+        last: i64,
+        /// Sequence number that was received
+        received: i64,
+    },
+}
+
+impl SequenceEvent {
+    /// Returns true if the tick continues the sequence
+    pub fn is_in_order(&self) -> bool {
+        matches!(self, Self::First | Self::InOrder)
+    }
+}
+
+/// Feed latency statistics, receive time minus the exchange time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of samples
+    pub count: u64,
+    /// Latest latency
+    pub last: TimeDelta,
+    /// Minimum latency
+    pub min: TimeDelta,
+    /// Maximum latency
+    pub max: TimeDelta,
+    // sum of the latencies in nanoseconds, wide enough to never overflow
+    total_nanos: i128,
+}
+
+impl LatencyStats {
+    /// Adds the latency sample
+    pub fn record(&mut self, latency: TimeDelta) {
+        if self.count == 0 {
+            self.min = latency;
+            self.max = latency;
+        } else {
+            self.min = self.min.min(latency);
+            self.max = self.max.max(latency);
+        }
+        self.count += 1;
+        self.last = latency;
+        self.total_nanos +=
+            i128::from(latency.num_seconds()) * NANOS_PER_SEC + i128::from(latency.subsec_nanos());
+    }
+
+    /// Returns the mean latency
+    pub fn mean(&self) -> Option<TimeDelta> {
+        if self.count == 0 {
+            return None;
+        }
+        let mean = self.total_nanos / i128::from(self.count);
+        TimeDelta::new(
+            mean.div_euclid(NANOS_PER_SEC) as i64,
+            mean.rem_euclid(NANOS_PER_SEC) as u32,
+        )
+    }
+}
+
+/// Sequence and latency counters of the feed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedMetrics {
+    /// Ticks tracked
+    pub messages: u64,
+    /// Gaps detected
+    pub gaps: u64,
+    /// Sequence numbers skipped over all the gaps
+    pub missed: u64,
+    /// Duplicate ticks
+    pub duplicates: u64,
+    /// Out of order ticks
+    pub out_of_order: u64,
+    /// Feed latency
+    pub latency: LatencyStats,
+}
+
+impl FeedMetrics {
+    fn record(&mut self, event: SequenceEvent, latency: Option<TimeDelta>) {
+        self.messages += 1;
+        match event {
+            SequenceEvent::Gap { expected, received } => {
+                self.gaps += 1;
+                self.missed += (received - expected) as u64;
+            }
+            SequenceEvent::Duplicate => self.duplicates += 1,
+            SequenceEvent::OutOfOrder { .. } => self.out_of_order += 1,
+            SequenceEvent::First | SequenceEvent::InOrder => {}
+        }
+        if let Some(latency) = latency {
+            self.latency.record(latency);
+        }
+    }
+}
+
+/// Per token state of the [`SequenceTracker`]
+#[derive(Debug, Clone, Copy, Default)]
+struct TokenSequence {
+    last: Option<i64>,
+    metrics: FeedMetrics,
+}
+
+/// Tracks the sequence numbers of the ticks per token, flagging gaps, duplicates and
+/// out of order ticks and measuring the feed latency
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    tokens: HashMap<(SubscriptionExchange, String), TokenSequence>,
+    metrics: FeedMetrics,
+}
+
+impl SequenceTracker {
+    /// Returns a new instance for [`SequenceTracker`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks the [`Message`] received now
+    pub fn track(&mut self, message: &Message) -> SequenceEvent {
+        self.track_at(message, Utc::now())
+    }
+
+    /// Tracks the [`Message`] received at the given time
+    pub fn track_at(&mut self, message: &Message, received_at: DateTime<Utc>) -> SequenceEvent {
+        let received = message.sequence_number;
+        let state = self
+            .tokens
+            .entry((message.exchange, message.token.clone()))
+            .or_default();
+
+        let event = match state.last {
+            None => SequenceEvent::First,
+            Some(last) if received == last + 1 => SequenceEvent::InOrder,
+            Some(last) if received == last => SequenceEvent::Duplicate,
+            Some(last) if received < last => SequenceEvent::OutOfOrder { last, received },
+            Some(last) => SequenceEvent::Gap {
+                expected: last + 1,
+                received,
+            },
+        };
+
+        if !event.is_in_order() {
+            warn!("Sequence {event:?} for token {}", message.token);
+        }
+        if !matches!(event, SequenceEvent::OutOfOrder { .. }) {
+            state.last = Some(received);
+        }
+
+        let latency = message
+            .exchange_time()
+            .map(|exchange_time| received_at.signed_duration_since(exchange_time));
+
+        state.metrics.record(event, latency);
+        self.metrics.record(event, latency);
+
+        event
+    }
+
+    /// Returns the metrics for the token
+    pub fn metrics(&self, exchange: SubscriptionExchange, token: &str) -> Option<&FeedMetrics> {
+        self.tokens
+            .get(&(exchange, token.to_string()))
+            .map(|state| &state.metrics)
+    }
+
+    /// Returns the metrics over all the tokens
+    pub fn total(&self) -> &FeedMetrics {
+        &self.metrics
+    }
+
+    /// Clears the sequence state and the metrics, e.g. after a reconnect
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta};
+
+    use crate::ws::{ltp, SubscriptionExchange};
+
+    use super::{LatencyStats, SequenceEvent, SequenceTracker};
+
+    const EXCHANGE_MILLIS: i64 = 1_735_184_700_000;
+
+    #[test]
+    fn tracker_flags_sequence_anomalies() {
+        let received_at = DateTime::from_timestamp_millis(EXCHANGE_MILLIS + 40).unwrap();
+        let mut tracker = SequenceTracker::new();
+
+        let events = [1, 2, 5, 5, 3, 6]
+            .map(|seq| tracker.track_at(&ltp("3045", seq, EXCHANGE_MILLIS, 80_000), received_at));
+        assert_eq!(
+            events,
+            [
+                SequenceEvent::First,
+                SequenceEvent::InOrder,
+                SequenceEvent::Gap {
+                    expected: 3,
+                    received: 5
+                },
+                SequenceEvent::Duplicate,
+                SequenceEvent::OutOfOrder {
+                    last: 5,
+                    received: 3
+                },
+                SequenceEvent::InOrder,
+            ]
+        );
+
+        let metrics = tracker
+            .metrics(SubscriptionExchange::NSECM, "3045")
+            .unwrap();
+        assert_eq!((metrics.gaps, metrics.missed), (1, 2));
+        assert_eq!((metrics.duplicates, metrics.out_of_order), (1, 1));
+        assert_eq!(metrics.latency.mean(), Some(TimeDelta::milliseconds(40)));
+        assert_eq!(tracker.total().messages, 6);
+    }
+
+    #[test]
+    fn latency_mean_over_many_samples() {
+        let mut latency = LatencyStats::default();
+        latency.record(TimeDelta::milliseconds(1));
+        latency.record(TimeDelta::milliseconds(2));
+        assert_eq!(latency.mean(), Some(TimeDelta::microseconds(1_500)));
+
+        // more samples than an i32 counts
+        latency.count = 5_000_000_000;
+        latency.total_nanos = -3_000_000 * 5_000_000_000;
+        assert_eq!(latency.mean(), Some(TimeDelta::milliseconds(-3)));
+    }
+}
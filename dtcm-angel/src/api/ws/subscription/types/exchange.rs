@@ -1,8 +1,8 @@
-use crate::Error;
+use crate::{types::ExchangeType, Error};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 /// Exchange type for subscription
-#[derive(Debug, Serialize_repr, Deserialize_repr, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Serialize_repr, Deserialize_repr, PartialEq, Eq, Hash, Clone, Copy)]
 #[repr(u8)]
 pub enum SubscriptionExchange {
     /// NSE Eq
@@ -44,3 +44,35 @@ impl TryFrom<u8> for SubscriptionExchange {
         Ok(_self)
     }
 }
+
+impl From<SubscriptionExchange> for ExchangeType {
+    fn from(value: SubscriptionExchange) -> Self {
+        match value {
+            SubscriptionExchange::NSECM => Self::NSE,
+            SubscriptionExchange::NSEFO => Self::NFO,
+            SubscriptionExchange::BSECM => Self::BSE,
+            SubscriptionExchange::BSEFO => Self::BFO,
+            SubscriptionExchange::MCXFO => Self::MCX,
+            SubscriptionExchange::NCXFO => Self::NCDEX,
+            SubscriptionExchange::CDEFO => Self::CDS,
+        }
+    }
+}
+
+impl TryFrom<&ExchangeType> for SubscriptionExchange {
+    type Error = Error;
+
+    fn try_from(value: &ExchangeType) -> Result<Self, Self::Error> {
+        let _self = match value {
+            ExchangeType::NSE => Self::NSECM,
+            ExchangeType::NFO => Self::NSEFO,
+            ExchangeType::BSE => Self::BSECM,
+            ExchangeType::BFO => Self::BSEFO,
+            ExchangeType::MCX => Self::MCXFO,
+            ExchangeType::NCDEX => Self::NCXFO,
+            ExchangeType::CDS => Self::CDEFO,
+            _ => return Err(Error::InvalidSubscriptionExchange),
+        };
+        Ok(_self)
+    }
+}
@@ -17,7 +17,7 @@ mod smart_connect;
 pub use smart_connect::SmartConnect;
 
 mod api;
-pub use api::{funds, gtt, market, order, portfolio, user, ws};
+pub use api::{algo, broker, funds, gtt, market, order, portfolio, report, risk, user, ws};
 
 /// Various types for Angel One API SDK
 pub mod types;
@@ -31,6 +31,15 @@ pub enum Error {
     /// errors from utils crate
     #[error(transparent)]
     UtilsError(#[from] dtcm_angel_utils::UtilsError),
+    /// JSON serialization errors
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    /// file errors
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    /// CSV serialization errors
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
     /// session not established
     #[error("unable to establish the session")]
     SessionEstablishmentError,
@@ -49,6 +58,61 @@ pub enum Error {
     /// interval error
     #[error("{0}")]
     IntervalError(String),
+    /// order rejected by the pre-trade validation
+    #[error("order validation failed: {0}")]
+    OrderValidation(#[from] order::OrderValidationError),
+    /// order placed without an order id in the response
+    #[error("order id missing in the place order response")]
+    OrderIdMissing,
+    /// order tracking stopped before the order reached a terminal state
+    #[error("tracking of order {0} closed")]
+    OrderTrackingClosed(String),
+    /// order ended without being filled
+    #[error("order {0} ended {1:?} without being filled")]
+    OrderNotFilled(String, order::OrderState),
+    /// order rejected by the risk manager
+    #[error("order rejected by the risk checks: {0}")]
+    RiskRejected(#[from] risk::RiskViolation),
+    /// order unknown to the broker
+    #[error("order {0} not found")]
+    OrderNotFound(String),
+    /// no working bracket or cover order leg to exit the position with
+    #[error("no working bracket order to exit the position of {0}")]
+    BracketNotFound(String),
+    /// target order still working after its cancellation failed
+    #[error("target order {0} is still working")]
+    TargetOrderWorking(String),
+    /// order modified or cancelled after reaching a terminal state
+    #[error("order {0} is already closed")]
+    OrderClosed(String),
+    /// position to convert not found
+    #[error("no position of {0} to convert")]
+    PositionNotFound(String),
+    /// position conversion not allowed for the open position
+    #[error("cannot convert the position of {0}: {1}")]
+    InvalidConversion(String, String),
+    /// snapshot written by a newer version of the crate
+    #[error("unsupported snapshot version {0}")]
+    UnsupportedSnapshotVersion(u32),
+    /// margin available does not cover a single lot of the order
+    #[error("margin {required:.2} required, {available:.2} available")]
+    InsufficientMargin {
+        /// Margin required
+        required: f64,
+        /// Margin available
+        available: f64,
+    },
+}
+
+impl Error {
+    /// Returns true if the request may have reached the server before failing, e.g. on a
+    /// timeout, so its outcome is unknown
+    pub fn is_ambiguous(&self) -> bool {
+        matches!(
+            self,
+            Self::UtilsError(dtcm_angel_utils::UtilsError::ReqwestError(e)) if !e.is_builder()
+        )
+    }
 }
 
 /// custom result type for crate
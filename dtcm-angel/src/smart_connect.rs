@@ -1,9 +1,14 @@
 use dtcm_angel_utils::{
     UtilsError,
-    http::{HttpClient, HttpFetcher, HttpSender, INSTRUMENT_URL},
+    http::{EndPoint, HttpClient, HttpFetcher, HttpSender, INSTRUMENT_URL, Response},
+};
+use log::{debug, error, trace, warn};
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::Display,
+    time::{Duration, Instant},
 };
-use log::{debug, error, trace};
-use std::{collections::HashMap, error::Error as StdError, fmt::Display};
 
 use crate::{
     Error, Result,
@@ -13,14 +18,16 @@ use crate::{
         RuleDetailReq, RuleDetailRes, RuleListReq, RuleListRes,
     },
     market::{
-        BrokerageReq, BrokerageResp, CandleDataReq, CandleDataRes, Instrument, IntradayScrip,
-        LtpDataReq, LtpDataRes, MarketDataReq, MarketDataRes, SearchScripReq, SearchScripRes,
+        BrokerageReq, BrokerageResp, CandleDataReq, CandleDataRes, Instrument, InstrumentMaster,
+        IntradayScrip, LtpDataReq, LtpDataRes, MarketDataReq, MarketDataRes, SearchScripReq,
+        SearchScripRes,
     },
     order::{
         CancelOrderReq, CancelOrderRes, IndividualOrderStatus, ModifyOrderReq, ModifyOrderRes,
-        OrderBook, PlaceOrderReq, PlaceOrderRes, TradeBook,
+        OrderBook, PlaceOrderReq, PlaceOrderRes, TradeBook, unique_order_tag,
     },
-    portfolio::{AllHoldings, ConvertPositionReq, Holding, Position},
+    portfolio::{AllHoldings, ConvertPositionReq, Holding, HoldingsAnalytics, Position},
+    risk::{MarginSizer, RiskManager},
     types::{
         ExchangeType, Interval, MarketDataExchange, MarketMode, OrderVariety, ProductType,
         RuleType, TransactionType,
@@ -28,6 +35,16 @@ use crate::{
     user::{LogoutReq, Profile, SessionReq, SessionRes, TokenReq},
 };
 
+// Delay before the first order book lookup of an ambiguously placed order, doubled after
+// every lookup
+const PLACEMENT_POLL_INITIAL: Duration = Duration::from_millis(500);
+
+// Upper bound of the delay between the order book lookups
+const PLACEMENT_POLL_MAX: Duration = Duration::from_secs(4);
+
+// Time an ambiguously placed order is looked for before it is taken as not placed
+const PLACEMENT_CONFIRM_DEADLINE: Duration = Duration::from_secs(15);
+
 /// Smart connect client to interact with Angel One API
 #[derive(Debug)]
 pub struct SmartConnect {
@@ -43,6 +60,12 @@ pub struct SmartConnect {
     pub user: Option<Profile>,
     /// Http client to make requests
     pub http: HttpClient,
+    /// Validates the orders before they are placed
+    pub validate_orders: bool,
+    /// Instrument master used for the lot size and tick size validation
+    pub instruments: Option<InstrumentMaster>,
+    /// Pre-trade risk limits checked before the orders are placed or modified
+    pub risk_manager: Option<RiskManager>,
 }
 
 impl SmartConnect {
@@ -64,6 +87,9 @@ impl SmartConnect {
             session: None,
             user: None,
             http,
+            validate_orders: false,
+            instruments: None,
+            risk_manager: None,
         })
     }
 
@@ -72,6 +98,24 @@ impl SmartConnect {
         Ok(HttpClient::get_json_url(INSTRUMENT_URL).await?)
     }
 
+    /// Enables the validation of the orders before they are placed
+    pub fn validate_orders(mut self, validate_orders: bool) -> Self {
+        self.validate_orders = validate_orders;
+        self
+    }
+
+    /// Sets the [`InstrumentMaster`] used to validate the orders against lot and tick sizes
+    pub fn instrument_master(mut self, instruments: InstrumentMaster) -> Self {
+        self.instruments = Some(instruments);
+        self
+    }
+
+    /// Sets the [`RiskManager`] enforcing the pre-trade limits
+    pub fn risk_manager(mut self, risk_manager: RiskManager) -> Self {
+        self.risk_manager = Some(risk_manager);
+        self
+    }
+
     /// Generates the session to receive authentication tokens and user information
     pub async fn generate_session<O>(&mut self, otp_token: O) -> Result<()>
     where
@@ -111,6 +155,14 @@ impl SmartConnect {
         Ok(token_req.send_data(&self.http).await?)
     }
 
+    /// Regenerates the authentication tokens and authorizes the following requests with the
+    /// new jwt token, the returned session carries the new feed token
+    pub async fn refresh_token(&self) -> Result<SessionRes> {
+        let session = self.token().await?;
+        self.http.jwt_token(&session.jwt_token);
+        Ok(session)
+    }
+
     /// Fetch the complete information of the user who is logged in
     pub async fn profile(&self) -> Result<Profile> {
         let body = HashMap::from([("refreshToken", self.current_refresh_token()?)]);
@@ -198,8 +250,8 @@ impl SmartConnect {
     }
 
     /// Sends the list rule request
-    pub async fn rule_list(&self, rule_list_req: &RuleListReq) -> Result<RuleListRes> {
-        Ok(rule_list_req.send_data(&self.http).await?)
+    pub async fn rule_list(&self, rule_list_req: &RuleListReq) -> Result<Vec<RuleListRes>> {
+        Ok(rule_list_req.send_vec(&self.http).await?)
     }
 
     /// Returns a new place order instance to be configured
@@ -215,11 +267,131 @@ impl SmartConnect {
         PlaceOrderReq::new(trading_symbol, symbol_token, transaction_type)
     }
 
-    /// Places the configured order
+    /// Validates the order, against its instrument if the [`InstrumentMaster`] is set
+    pub fn validate_order(&self, order_req: &PlaceOrderReq) -> Result<()> {
+        let instrument = self.instruments.as_ref().and_then(|instruments| {
+            instruments.get(&order_req.inner.exchange, &order_req.inner.symbol_token)
+        });
+
+        match instrument {
+            Some(instrument) => order_req.validate_with(instrument),
+            None => order_req.validate(),
+        }
+        .map_err(|e| {
+            error!("Order for {} rejected: {e}", order_req.inner.trading_symbol);
+            e.into()
+        })
+    }
+
+    /// Places the configured order, validating it first if enabled and checking it against
+    /// the [`RiskManager`] if set
     pub async fn place_order(&self, order_req: &PlaceOrderReq) -> Result<PlaceOrderRes> {
+        self.send_order(order_req, true).await
+    }
+
+    async fn send_order(
+        &self,
+        order_req: &PlaceOrderReq,
+        check_risk: bool,
+    ) -> Result<PlaceOrderRes> {
+        if self.validate_orders {
+            self.validate_order(order_req)?;
+        }
+        if let Some(risk_manager) = self.risk_manager.as_ref().filter(|_| check_risk) {
+            risk_manager.check_order(self, order_req).await?;
+        }
         Ok(order_req.send_data(&self.http).await?)
     }
 
+    /// Places the order at most once, retrying up to `retries` times on ambiguous failures.
+    /// The order is tagged with a unique tag if it has none, and after an ambiguous failure
+    /// the order book is polled for the tag. The order is placed again only if the order
+    /// book was read without it at the deadline, the ambiguous error is returned otherwise.
+    pub async fn place_order_once(
+        &self,
+        order_req: &PlaceOrderReq,
+        retries: usize,
+    ) -> Result<PlaceOrderRes> {
+        self.place_order_once_with(order_req, retries, true).await
+    }
+
+    /// Same as [`Self::place_order_once`], skipping the [`RiskManager`] checks unless
+    /// `check_risk` is set, for the exits of the kill switch
+    pub(crate) async fn place_order_once_with(
+        &self,
+        order_req: &PlaceOrderReq,
+        retries: usize,
+        check_risk: bool,
+    ) -> Result<PlaceOrderRes> {
+        let mut order_req = order_req.clone();
+        let order_tag = order_req
+            .inner
+            .order_tag
+            .get_or_insert_with(unique_order_tag)
+            .clone();
+
+        let mut attempt = 0;
+        loop {
+            let err = match self.send_order(&order_req, check_risk).await {
+                Ok(res) => return Ok(res),
+                Err(e) if e.is_ambiguous() => e,
+                Err(e) => return Err(e),
+            };
+            warn!("Placement of order {order_tag} is ambiguous: {err}");
+
+            let order = match self.confirm_placement(&order_tag).await {
+                Ok(order) => order,
+                Err(e) => {
+                    error!("Order book lookup for {order_tag} failed: {e}");
+                    return Err(err);
+                }
+            };
+
+            if let Some(order) = order {
+                debug!("Order {order_tag} found as {}", order.order_id);
+                return Ok(PlaceOrderRes {
+                    script: order.trading_symbol,
+                    order_id: Some(order.order_id),
+                    unique_order_id: order.unique_order_id,
+                });
+            }
+
+            if attempt >= retries {
+                return Err(err);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Polls the order book for the tag with backoff until the deadline. Returns the order
+    /// once found, none if the order book was read at the deadline without it, or the error
+    /// of the last lookup.
+    async fn confirm_placement(&self, order_tag: &str) -> Result<Option<OrderBook>> {
+        let deadline = Instant::now() + PLACEMENT_CONFIRM_DEADLINE;
+        let mut delay = PLACEMENT_POLL_INITIAL;
+        loop {
+            tokio::time::sleep(delay.min(deadline.saturating_duration_since(Instant::now()))).await;
+            let at_deadline = Instant::now() >= deadline;
+            match self.find_order_by_tag(order_tag).await {
+                Ok(Some(order)) => return Ok(Some(order)),
+                Ok(None) if at_deadline => return Ok(None),
+                Err(e) if at_deadline => return Err(e),
+                Ok(None) => debug!("Order {order_tag} not in the order book yet"),
+                Err(e) => warn!("Order book lookup for {order_tag} failed: {e}"),
+            }
+            delay = (delay * 2).min(PLACEMENT_POLL_MAX);
+        }
+    }
+
+    /// Returns the order book entry with the client order tag
+    pub async fn find_order_by_tag(&self, order_tag: &str) -> Result<Option<OrderBook>> {
+        Ok(self
+            .order_book()
+            .await?
+            .into_iter()
+            .find(|order| order.order_tag == order_tag))
+    }
+
     /// Returns a new modify order instance to be further configured by the caller
     pub fn new_modify_order<S, T, O>(
         trading_symbol: S,
@@ -234,8 +406,11 @@ impl SmartConnect {
         ModifyOrderReq::new(trading_symbol, symbol_token, order_id)
     }
 
-    /// Modifies the provided order
+    /// Modifies the provided order, checking it against the [`RiskManager`] if set
     pub async fn modify_order(&self, modify_order_req: &ModifyOrderReq) -> Result<ModifyOrderRes> {
+        if let Some(risk_manager) = &self.risk_manager {
+            risk_manager.check_modify(self, modify_order_req).await?;
+        }
         modify_order_req.send(&self.http).await
     }
 
@@ -298,6 +473,11 @@ impl SmartConnect {
         Ok(AllHoldings::fetch_data(&self.http, &{}).await?)
     }
 
+    /// Returns the allocation and profit analytics of the holdings
+    pub async fn holdings_analytics(&self) -> Result<HoldingsAnalytics> {
+        Ok(HoldingsAnalytics::new(&self.all_holdings().await?.holdings))
+    }
+
     /// Returns the portfolio position holdings
     pub async fn positions(&self) -> Result<Vec<Position>> {
         Ok(Position::fetch_vec(&self.http, &{}).await?)
@@ -316,6 +496,38 @@ impl SmartConnect {
         Ok(convert_position_req.send_data(&self.http).await?)
     }
 
+    /// Converts `quantity` of the live position to the new product type
+    pub async fn convert_position_from(
+        &self,
+        position: &Position,
+        new_product_type: ProductType,
+        quantity: usize,
+    ) -> Result<()> {
+        let req = ConvertPositionReq::from_position(position, new_product_type, quantity)?;
+        self.convert_position(&req).await
+    }
+
+    /// Converts every open position of the old product type to the new one, e.g. all the
+    /// intraday positions to delivery, and returns the outcome per position
+    pub async fn convert_all_positions(
+        &self,
+        old_product_type: ProductType,
+        new_product_type: ProductType,
+    ) -> Result<Vec<(Position, Result<()>)>> {
+        let mut converted = vec![];
+        for position in self.positions().await? {
+            if position.product_type != old_product_type || position.net_qty == 0 {
+                continue;
+            }
+            let quantity = position.net_qty.unsigned_abs() as usize;
+            let result = self
+                .convert_position_from(&position, new_product_type.clone(), quantity)
+                .await;
+            converted.push((position, result));
+        }
+        Ok(converted)
+    }
+
     /// Returns a new instance for Market data request
     pub fn new_market_data(
         mode: MarketMode,
@@ -367,7 +579,21 @@ where {
 
     /// Nse intraday scrips
     pub async fn nse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
-        Ok(match IntradayScrip::fetch_vec(&self.http, &{}).await {
+        self.intraday_scrips(EndPoint::NseIntraday).await
+    }
+
+    /// Bse intraday scrips
+    pub async fn bse_intraday_scrips(&self) -> Result<Vec<IntradayScrip>> {
+        self.intraday_scrips(EndPoint::BseIntraday).await
+    }
+
+    async fn intraday_scrips(&self, end_point: EndPoint) -> Result<Vec<IntradayScrip>> {
+        let res = self
+            .http
+            .get::<_, Vec<IntradayScrip>>(end_point, &{})
+            .await
+            .map(Response::into_vec);
+        Ok(match res {
             Ok(scrips) => scrips,
             Err(e) => {
                 if let UtilsError::ReqwestError(e) = &e {
@@ -417,4 +643,13 @@ where {
         margin_calc_req.add_positions(positions);
         Ok(margin_calc_req.send_data(&self.http).await?)
     }
+
+    /// Returns the margin sizer of the orders, with the lot sizes of the instrument master
+    pub fn margin_sizer(&self) -> MarginSizer<'_> {
+        let sizer = MarginSizer::new(self);
+        match &self.instruments {
+            Some(instruments) => sizer.instruments(instruments),
+            None => sizer,
+        }
+    }
 }
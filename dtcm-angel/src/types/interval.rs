@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, TimeDelta};
 
 use crate::Error;
 
@@ -46,6 +46,21 @@ impl Interval {
         }
     }
 
+    /// Returns the length of a candle for the interval
+    pub fn duration(&self) -> TimeDelta {
+        match self {
+            Self::_1m => TimeDelta::minutes(1),
+            Self::_2_1m => TimeDelta::minutes(2),
+            Self::_3m => TimeDelta::minutes(3),
+            Self::_5m => TimeDelta::minutes(5),
+            Self::_10m => TimeDelta::minutes(10),
+            Self::_15m => TimeDelta::minutes(15),
+            Self::_30m => TimeDelta::minutes(30),
+            Self::_1h => TimeDelta::hours(1),
+            Self::_1d => TimeDelta::days(1),
+        }
+    }
+
     /// Validates the days limit for the interval for the given dates
     pub fn valid(&self, from_date: NaiveDateTime, to_date: NaiveDateTime) -> crate::Result<()> {
         let days = (to_date - from_date).num_days();
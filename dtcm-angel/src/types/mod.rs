@@ -24,3 +24,6 @@ pub use market_mode::MarketMode;
 
 mod interval;
 pub use interval::Interval;
+
+mod order_status;
+pub use order_status::OrderStatusKind;
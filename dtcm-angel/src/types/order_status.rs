@@ -0,0 +1,56 @@
+/// Order status reported in the order book
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
+pub enum OrderStatusKind {
+    /// Open at the exchange
+    #[serde(rename = "open")]
+    Open,
+    /// Fully executed
+    #[serde(rename = "complete")]
+    Complete,
+    /// Cancelled
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    /// Rejected
+    #[serde(rename = "rejected")]
+    Rejected,
+    /// Stop loss order waiting for its trigger
+    #[serde(rename = "trigger pending")]
+    TriggerPending,
+    /// Sent to the exchange, not yet open
+    #[serde(rename = "open pending")]
+    OpenPending,
+    /// Under validation by the broker
+    #[serde(rename = "validation pending")]
+    ValidationPending,
+    /// Received by the broker
+    #[serde(rename = "put order req received")]
+    PutOrderReqReceived,
+    /// Modification sent to the exchange
+    #[serde(rename = "modify pending")]
+    ModifyPending,
+    /// Modification under validation by the broker
+    #[serde(rename = "modify validation pending")]
+    ModifyValidationPending,
+    /// Modified
+    #[serde(rename = "modified")]
+    Modified,
+    /// After market order received
+    #[serde(rename = "after market order req received")]
+    AfterMarketOrderReqReceived,
+    #[serde(untagged)]
+    /// unhandled values
+    Unknown(String),
+}
+
+impl Default for OrderStatusKind {
+    fn default() -> Self {
+        Self::Unknown(String::new())
+    }
+}
+
+impl OrderStatusKind {
+    /// Returns true if the order can no longer change
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Complete | Self::Cancelled | Self::Rejected)
+    }
+}
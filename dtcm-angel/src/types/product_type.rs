@@ -1,5 +1,5 @@
 /// Product type
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum ProductType {
     #[serde(rename = "DELIVERY")]
     /// Cash & Carry for equity (CNC)
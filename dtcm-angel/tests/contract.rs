@@ -0,0 +1,433 @@
+//! Contract tests of the Angel One API types
+//!
+//! Requests are checked against the golden files in `tests/fixtures/requests`, holding the
+//! method, url and serialized body sent for each endpoint. Run with `UPDATE_GOLDEN=1` to
+//! rewrite them after an intended change. Responses are parsed from the documented samples
+//! in `tests/fixtures/responses`.
+
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use chrono::NaiveDateTime;
+use dtcm_angel::{
+    funds::{MarginCalculatorPosition, MarginCalculatorReq, MarginCalculatorRes, Rms},
+    gtt::{
+        CancelRuleReq, CancelRuleRes, CreateRuleReq, CreateRuleRes, ModifyRuleReq, ModifyRuleRes,
+        RuleDetailReq, RuleDetailRes, RuleListReq, RuleListRes,
+    },
+    market::{
+        BrokeragePerProduct, BrokerageReq, BrokerageResp, CandleDataReq, CandleDataRes,
+        IntradayScrip, LtpDataReq, LtpDataRes, MarketDataReq, MarketDataRes, SearchScripReq,
+        SearchScripRes,
+    },
+    order::{
+        CancelOrderReq, CancelOrderRes, IndividualOrderStatus, ModifyOrderReq, ModifyOrderRes,
+        OrderBook, OrderSetter, PlaceOrderReq, PlaceOrderRes, TradeBook,
+    },
+    portfolio::{AllHoldings, ConvertPositionReq, Holding, Position},
+    types::{
+        DurationType, ExchangeType, Interval, MarketDataExchange, MarketMode, OrderStatusKind,
+        OrderType, OrderVariety, ProductType, RuleType, TransactionType,
+    },
+    user::{LogoutReq, Profile, SessionReq, SessionRes, TokenReq},
+};
+use dtcm_angel_utils::http::{EndPoint, HttpFetcher, HttpSender, Response};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+/// Every endpoint, keep in sync with [`contract`]
+fn end_points() -> Vec<EndPoint> {
+    use EndPoint::*;
+
+    vec![
+        Login,
+        Logout,
+        Token,
+        Refresh,
+        UserProfile,
+        OrderPlace,
+        OrderModify,
+        OrderCancel,
+        OrderBook,
+        LtpData,
+        TradeBook,
+        RmsLimit,
+        Holding,
+        Position,
+        ConvertPosition,
+        GttCreate,
+        GttModify,
+        GttCancel,
+        GttDetails,
+        GttList,
+        CandleData,
+        MarketData,
+        AllHolding,
+        IndividualOrderDetails(String::from("05ebf91b-bea4-4a1d-b0f2-4259606570e3")),
+        MarginApi,
+        Brokerage,
+        SearchScrip,
+        NseIntraday,
+        BseIntraday,
+    ]
+}
+
+/// Returns the request golden file of the endpoint, the match fails to compile when a
+/// variant is added without a contract
+fn contract(end_point: &EndPoint) -> &'static str {
+    use EndPoint::*;
+
+    match end_point {
+        Login => "session",
+        Logout => "logout",
+        Token | Refresh => "token",
+        UserProfile => "profile",
+        OrderPlace => "place_order",
+        OrderModify => "modify_order",
+        OrderCancel => "cancel_order",
+        OrderBook => "order_book",
+        LtpData => "ltp_data",
+        TradeBook => "trade_book",
+        RmsLimit => "rms",
+        Holding => "holding",
+        Position => "position",
+        ConvertPosition => "convert_position",
+        GttCreate => "create_rule",
+        GttModify => "modify_rule",
+        GttCancel => "cancel_rule",
+        GttDetails => "rule_detail",
+        GttList => "rule_list",
+        CandleData => "candle_data",
+        MarketData => "market_data",
+        AllHolding => "all_holdings",
+        IndividualOrderDetails(_) => "individual_order_status",
+        MarginApi => "margin_calculator",
+        Brokerage => "brokerage",
+        SearchScrip => "search_scrip",
+        NseIntraday => "nse_intraday",
+        BseIntraday => "bse_intraday",
+    }
+}
+
+fn fixture(kind: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(kind)
+        .join(format!("{name}.json"))
+}
+
+fn read_fixture(kind: &str, name: &str) -> String {
+    let path = fixture(kind, name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+fn assert_golden(name: &str, actual: Value) {
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        let golden = serde_json::to_string_pretty(&actual).unwrap();
+        fs::write(fixture("requests", name), golden + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&read_fixture("requests", name)).unwrap();
+    assert_eq!(actual, expected, "request contract {name} changed");
+}
+
+fn assert_post<T>(name: &str, req: &T)
+where
+    T: HttpSender + Serialize,
+{
+    assert_golden(
+        name,
+        json!({"method": "POST", "url": T::url(), "body": req}),
+    );
+}
+
+fn assert_get<T>(name: &str)
+where
+    T: HttpFetcher,
+{
+    assert_golden(name, json!({"method": "GET", "url": T::url()}));
+}
+
+fn parse<T>(name: &str) -> T
+where
+    T: DeserializeOwned,
+{
+    let res: Response<T> = serde_json::from_str(&read_fixture("responses", name))
+        .unwrap_or_else(|e| panic!("response {name}: {e}"));
+    assert!(res.status, "response {name} is not successful");
+    res.into_data().unwrap()
+}
+
+#[test]
+fn end_point_urls() {
+    for end_point in end_points() {
+        let name = contract(&end_point);
+        let golden: Value = serde_json::from_str(&read_fixture("requests", name)).unwrap();
+        assert_eq!(golden["url"], end_point.url(), "url of {end_point:?}");
+    }
+}
+
+#[test]
+fn order_requests() {
+    let place = PlaceOrderReq::new("SBIN-EQ", "3045", TransactionType::Buy)
+        .variety(OrderVariety::Normal)
+        .exchange(ExchangeType::NSE)
+        .order_type(OrderType::Limit)
+        .product_type(ProductType::IntraDay)
+        .duration(DurationType::Day)
+        .price(19500)
+        .quantity(1)
+        .order_tag("contract");
+    assert_post("place_order", &place);
+
+    let modify = ModifyOrderReq::new("SBIN-EQ", "3045", "201020000000080")
+        .variety(OrderVariety::Normal)
+        .exchange(ExchangeType::NSE)
+        .order_type(OrderType::Limit)
+        .product_type(ProductType::IntraDay)
+        .duration(DurationType::Day)
+        .price(194)
+        .quantity(1);
+    assert_post("modify_order", &modify);
+
+    let cancel = CancelOrderReq::new(OrderVariety::Normal, "201020000000080");
+    assert_post("cancel_order", &cancel);
+
+    assert_get::<OrderBook>("order_book");
+    assert_get::<TradeBook>("trade_book");
+    assert_golden(
+        "individual_order_status",
+        json!({
+            "method": "GET",
+            "url": EndPoint::IndividualOrderDetails(String::from(
+                "05ebf91b-bea4-4a1d-b0f2-4259606570e3"
+            ))
+            .url(),
+        }),
+    );
+}
+
+#[test]
+fn gtt_requests() {
+    let create = CreateRuleReq::new("SBIN-EQ", "3045")
+        .exchange(ExchangeType::NSE)
+        .transaction_type(TransactionType::Buy)
+        .product_type(ProductType::Delivery)
+        .price(195)
+        .qty(1)
+        .trigger_price(196)
+        .disclosed_qty(10);
+    assert_post("create_rule", &create);
+
+    let modify = ModifyRuleReq::new("1000014", "3045")
+        .exchange(ExchangeType::NSE)
+        .price(195)
+        .qty(1)
+        .trigger_price(196)
+        .disclosed_qty(10)
+        .time_period(365);
+    assert_post("modify_rule", &modify);
+
+    let cancel = CancelRuleReq::new("1000014", "3045", ExchangeType::NSE);
+    assert_post("cancel_rule", &cancel);
+
+    assert_post("rule_detail", &RuleDetailReq::new(1000014));
+
+    let list = RuleListReq::new(vec![RuleType::New, RuleType::Cancelled], 1, 10);
+    assert_post("rule_list", &list);
+}
+
+#[test]
+fn market_requests() {
+    let ltp = LtpDataReq::new(ExchangeType::NSE, "SBIN-EQ", "3045");
+    assert_post("ltp_data", &ltp);
+
+    let exchange_tokens = HashMap::from([(ExchangeType::NSE, vec![String::from("3045")])]);
+    let market = MarketDataReq::new(MarketMode::Full, exchange_tokens);
+    assert_post("market_data", &market);
+
+    let candle = CandleDataReq::new(
+        MarketDataExchange::NSE,
+        "3045",
+        Interval::_1m,
+        "2021-02-08 09:00",
+        "2021-02-08 09:16",
+    )
+    .unwrap();
+    assert_post("candle_data", &candle);
+
+    assert_post(
+        "search_scrip",
+        &SearchScripReq::new(ExchangeType::MCX, "Crude"),
+    );
+
+    let brokerage = BrokerageReq {
+        orders: vec![BrokeragePerProduct {
+            product_type: ProductType::Delivery,
+            transaction_type: TransactionType::Buy,
+            quantity: 10,
+            price: 800.0,
+            exchange: ExchangeType::BSE,
+            symbol_name: String::from("745AS33"),
+            token: String::from("17117"),
+        }],
+    };
+    assert_post("brokerage", &brokerage);
+
+    assert_get::<IntradayScrip>("nse_intraday");
+    assert_golden(
+        "bse_intraday",
+        json!({"method": "GET", "url": EndPoint::BseIntraday.url()}),
+    );
+}
+
+#[test]
+fn portfolio_and_funds_requests() {
+    assert_get::<Holding>("holding");
+    assert_get::<AllHoldings>("all_holdings");
+    assert_get::<Position>("position");
+
+    let convert = ConvertPositionReq::new("SBIN-EQ", 1)
+        .exchange(ExchangeType::NSE)
+        .old_product_type(ProductType::Delivery)
+        .new_product_type(ProductType::IntraDay)
+        .transaction_type(TransactionType::Buy)
+        .duration(DurationType::Day);
+    assert_post("convert_position", &convert);
+
+    assert_get::<Rms>("rms");
+
+    let mut margin = MarginCalculatorReq::new();
+    margin.add_position(MarginCalculatorPosition::new(
+        ExchangeType::NFO,
+        ProductType::IntraDay,
+        TransactionType::Sell,
+        "67300",
+        0.0,
+        50,
+    ));
+    assert_post("margin_calculator", &margin);
+}
+
+#[test]
+fn user_requests() {
+    let session = SessionReq {
+        client_code: String::from("E52473"),
+        password: String::from("1234"),
+        totp: String::from("123456"),
+    };
+    assert_post("session", &session);
+    assert_post("token", &TokenReq::new("eyJhbGciOiJIUzUxMiJ9.refresh"));
+    assert_post("logout", &LogoutReq::new("E52473"));
+    assert_get::<Profile>("profile");
+}
+
+#[test]
+fn order_responses() {
+    let place: PlaceOrderRes = parse("place_order");
+    assert_eq!(place.order_id.as_deref(), Some("200910000000111"));
+    let modify: ModifyOrderRes = parse("modify_order");
+    assert_eq!(modify.order_id, "201020000000080");
+    let cancel: CancelOrderRes = parse("cancel_order");
+    assert_eq!(cancel.order_id, "201020000000080");
+
+    let book: Vec<OrderBook> = parse("order_book");
+    assert_eq!(book[0].order_status, OrderStatusKind::Open);
+    assert_eq!(book[0].unfilled_shares, 1);
+    let update_time = NaiveDateTime::parse_from_str("2024-12-26 15:55:01", "%Y-%m-%d %H:%M:%S");
+    assert_eq!(book[0].update_time, update_time.ok());
+
+    let status: IndividualOrderStatus = parse("individual_order_status");
+    assert_eq!(
+        status.unique_order_id,
+        "05ebf91b-bea4-4a1d-b0f2-4259606570e3"
+    );
+    assert_eq!(status.order.order_status, OrderStatusKind::Complete);
+
+    let trades: Vec<TradeBook> = parse("trade_book");
+    assert_eq!(trades[0].trade_value, 175.0);
+    assert_eq!(trades[0].fill_size, 1);
+}
+
+#[test]
+fn gtt_responses() {
+    let create: CreateRuleRes = parse("create_rule");
+    assert_eq!(create.id, 1000014);
+    let modify: ModifyRuleRes = parse("modify_rule");
+    assert_eq!(modify.order_id, "1000014");
+    let cancel: CancelRuleRes = parse("cancel_rule");
+    assert_eq!(cancel.id, "1000014");
+
+    let detail: RuleDetailRes = parse("rule_detail");
+    assert_eq!((detail.qty, detail.trigger_price), (1, 196.0));
+
+    let rules: Vec<RuleListRes> = parse("rule_list");
+    assert_eq!(rules.len(), 1);
+    assert_eq!((rules[0].qty, rules[0].price), (1, 195.0));
+}
+
+#[test]
+fn market_responses() {
+    let ltp: LtpDataRes = parse("ltp_data");
+    assert_eq!(ltp.ltp, 19125.0);
+
+    let market: MarketDataRes = parse("market_data");
+    let quote = &market.fetched[0];
+    assert_eq!(quote.ohlc.unwrap().open, 568.75);
+    let full = quote.data.as_ref().unwrap();
+    assert_eq!((full.depth.buy.len(), full.depth.sell.len()), (5, 5));
+    assert_eq!(market.unfetched[0].error_code, "AB4903");
+
+    let candles: CandleDataRes = parse("candle_data");
+    assert_eq!(candles.0.len(), 3);
+    assert_eq!(candles.0[0].4, 19553.9);
+
+    let scrips: SearchScripRes = parse("search_scrip");
+    assert_eq!(scrips.0[0].token, "217704");
+
+    let brokerage: BrokerageResp = parse("brokerage");
+    assert_eq!(brokerage.summary.total_charges, 3.0796);
+    assert_eq!(brokerage.charges[0].breakup.len(), 3);
+
+    let intraday: Vec<IntradayScrip> = parse("nse_intraday");
+    assert_eq!(intraday[0].symbol_name, "ACC");
+}
+
+#[test]
+fn portfolio_and_funds_responses() {
+    let holdings: Vec<Holding> = parse("holding");
+    assert_eq!(holdings[0].profit_and_loss, 37.0);
+
+    let all: AllHoldings = parse("all_holdings");
+    assert_eq!(all.holdings.len(), 2);
+    assert_eq!(all.total_holding.unwrap().total_inv_value, 5116.0);
+
+    let positions: Vec<Position> = parse("position");
+    assert_eq!(positions[0].net_qty, 1);
+    assert_eq!(positions[0].buy_quantity, 1);
+    assert_eq!(positions[0].net_value, -2235.8);
+
+    let rms: Rms = parse("rms");
+    assert_eq!(rms.available_cash, 9999999999999.0);
+    assert_eq!(rms.utilized_span, None);
+
+    let margin: MarginCalculatorRes = parse("margin_calculator");
+    assert_eq!(margin.total_margin_required, 29612.35);
+    assert_eq!(margin.options_buy.option_details[0].lot_multiplier, 1);
+}
+
+#[test]
+fn user_responses() {
+    let session: SessionRes = parse("session");
+    assert!(session.feed_token.starts_with("eyJ"));
+    let token: SessionRes = parse("token");
+    assert!(token.refresh_token.starts_with("eyJ"));
+
+    let profile: Profile = parse("profile");
+    assert_eq!(profile.client_code, "E52473");
+    assert_eq!(profile.broker_id.as_deref(), Some("B2C"));
+
+    let logout: Response<Value> =
+        serde_json::from_str(&read_fixture("responses", "logout")).unwrap();
+    assert!(logout.status && logout.data.is_none());
+}